@@ -0,0 +1,469 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{endpoints::Endpoints, inject, session::Session, spinner};
+
+/// Balances of a set of addresses, keyed by address then denom
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AccountsSnapshot {
+    pub balances: BTreeMap<String, BTreeMap<String, u128>>,
+}
+
+/// Test accounts to fund from a key in the test keyring once the fork is ready
+#[derive(Args, Debug, Clone, Default)]
+pub struct FundArgs {
+    /// Fund an account once the fork is ready, as `address:coins`, e.g.
+    /// osmo1...:1000000uosmo,5000000uion. Repeat to fund more accounts.
+    #[arg(long, value_parser = parse_funding)]
+    pub fund: Vec<(String, String)>,
+
+    /// JSON file mapping addresses to the coins to fund them with once the fork is ready, e.g.
+    /// {"osmo1...": "1000000uosmo"}
+    #[arg(long)]
+    pub accounts_file: Option<PathBuf>,
+
+    /// Key in the home's test keyring to fund the accounts from, defaults to --operator-address
+    /// when it names a key
+    #[arg(long)]
+    pub fund_from: Option<String>,
+}
+
+pub fn parse_funding(s: &str) -> Result<(String, String), String> {
+    let (address, coins) = s
+        .split_once(':')
+        .ok_or_else(|| format!("Expected address:coins, got {}", s))?;
+    Ok((address.to_string(), coins.to_string()))
+}
+
+/// Accounts to fund once the fork is ready and the key funding them
+#[derive(Debug, Clone)]
+pub struct Funding {
+    accounts: Vec<(String, String)>,
+    from: String,
+    gas_prices: String,
+}
+
+impl FundArgs {
+    /// Accounts to fund from --fund and --accounts-file, `None` when there are none. The operator
+    /// funds them when no --fund-from is given and it names a key.
+    pub fn resolve(&self, operator: Option<&str>, gas_prices: String) -> Result<Option<Funding>> {
+        let mut accounts = self.fund.clone();
+        if let Some(path) = &self.accounts_file {
+            let content = std::fs::read_to_string(path)
+                .wrap_err(format!("Failed to read accounts file: {}", path.display()))?;
+            let file: BTreeMap<String, String> = serde_json::from_str(&content)
+                .wrap_err(format!("Failed to parse accounts file: {}", path.display()))?;
+            accounts.extend(file);
+        }
+        if accounts.is_empty() {
+            return Ok(None);
+        }
+
+        // an operator given as an address has no key to sign with
+        let operator_key = operator.filter(|operator| !operator.starts_with("osmo1"));
+        let from = self
+            .fund_from
+            .clone()
+            .or_else(|| operator_key.map(str::to_string))
+            .ok_or_else(|| {
+                eyre!(
+                    "Funding accounts needs --fund-from or an --operator-address naming a key in \
+                     the test keyring"
+                )
+            })?;
+
+        Ok(Some(Funding {
+            accounts,
+            from,
+            gas_prices,
+        }))
+    }
+}
+
+impl Funding {
+    /// Send every account its coins, one tx at a time so the sequence of the funding key is right
+    pub async fn fund(
+        &self,
+        osmosisd: &PathBuf,
+        osmosis_home: &PathBuf,
+        endpoints: &Endpoints,
+    ) -> Result<()> {
+        for (address, coins) in &self.accounts {
+            transfer(
+                osmosisd,
+                osmosis_home,
+                &self.from,
+                address,
+                coins,
+                &self.gas_prices,
+                endpoints,
+            )
+            .await?;
+            println!(
+                "{}",
+                format!("✓ Funded {} with {}.", address, coins).green()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Account the in-place testnet hands control of the chain to by default
+pub const DEFAULT_OPERATOR_ADDRESS: &str = "osmo12smx2wdlyttvyzvzg54y2vnqwq2qjateuf7thj";
+
+pub fn default_snapshot_path(osmosis_home: &Path) -> PathBuf {
+    osmosis_home.join("accounts_snapshot.json")
+}
+
+/// Record current balances of `addresses` into `path`
+pub async fn snapshot(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    addresses: &[String],
+    path: &PathBuf,
+    session: &Session,
+) -> Result<()> {
+    let mut snapshot = AccountsSnapshot::default();
+
+    for address in addresses {
+        let balances = spinner! {
+            session.events,
+            &format!("Querying balances of {}...", address),
+            &format!("✓ Queried balances of {}.", address),
+            query_balances(osmosisd, osmosis_home, address, &session.endpoints)?
+        };
+        snapshot.balances.insert(address.clone(), balances);
+    }
+
+    let content = serde_json::to_string_pretty(&snapshot)?;
+    std::fs::write(path, content).wrap_err(format!(
+        "Failed to write accounts snapshot: {}",
+        path.display()
+    ))?;
+
+    println!(
+        "{}",
+        format!("✓ Saved accounts snapshot to {}.", path.display()).green()
+    );
+
+    Ok(())
+}
+
+/// Top up every address in the snapshot at `path` back to its recorded balances by sending the
+/// missing amounts from `from`. Balances above the snapshot are left untouched since there is no
+/// way to sign on behalf of the snapshotted accounts.
+pub async fn restore(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    path: &PathBuf,
+    from: &str,
+    gas_prices: &str,
+    session: &Session,
+) -> Result<()> {
+    let content = std::fs::read_to_string(path).wrap_err(format!(
+        "Failed to read accounts snapshot: {}",
+        path.display()
+    ))?;
+    let snapshot: AccountsSnapshot =
+        serde_json::from_str(&content).wrap_err("Failed to parse accounts snapshot")?;
+
+    for (address, expected) in &snapshot.balances {
+        let current = query_balances(osmosisd, osmosis_home, address, &session.endpoints)?;
+
+        let missing = expected
+            .iter()
+            .filter_map(|(denom, amount)| {
+                let current_amount = current.get(denom).copied().unwrap_or_default();
+                (*amount > current_amount).then(|| format!("{}{}", amount - current_amount, denom))
+            })
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            println!(
+                "{}",
+                format!("✓ {} already matches snapshot.", address).green()
+            );
+            continue;
+        }
+
+        let coins = missing.join(",");
+        let txhash = spinner! {
+            session.events,
+            &format!("Sending {} to {}...", coins, address),
+            &format!("✓ Sent {} to {}.", coins, address),
+            bank_send(osmosisd, osmosis_home, from, address, &coins, gas_prices, &session.endpoints)?
+        };
+
+        spinner! {
+            session.events,
+            &format!("Waiting for tx {} to be included...", txhash),
+            &format!("✓ Tx {} included.", txhash),
+            wait_for_tx(osmosisd, osmosis_home, &txhash, &session.endpoints).await?
+        };
+    }
+
+    Ok(())
+}
+
+fn query_balances(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    address: &str,
+    endpoints: &Endpoints,
+) -> Result<BTreeMap<String, u128>> {
+    let output = Command::new(osmosisd)
+        .args(["query", "bank", "balances", address])
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--node", &endpoints.rpc_addr()])
+        .args(["--output", "json"])
+        .output()
+        .wrap_err("Failed to query balances")?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to query balances of {}: {}",
+            address,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let response: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .wrap_err("Failed to parse balances query response")?;
+
+    response["balances"]
+        .as_array()
+        .ok_or_else(|| eyre!("Unexpected balances query response"))?
+        .iter()
+        .map(|coin| {
+            let denom = coin["denom"]
+                .as_str()
+                .ok_or_else(|| eyre!("Missing denom in balances query response"))?;
+            let amount = coin["amount"]
+                .as_str()
+                .ok_or_else(|| eyre!("Missing amount in balances query response"))?
+                .parse::<u128>()
+                .wrap_err("Failed to parse balance amount")?;
+            Ok((denom.to_string(), amount))
+        })
+        .collect()
+}
+
+/// Send `coins` from key `from` to `to` and wait for the tx to be included, returning its hash
+pub async fn transfer(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    from: &str,
+    to: &str,
+    coins: &str,
+    gas_prices: &str,
+    endpoints: &Endpoints,
+) -> Result<String> {
+    let txhash = bank_send(
+        osmosisd,
+        osmosis_home,
+        from,
+        to,
+        coins,
+        gas_prices,
+        endpoints,
+    )?;
+    wait_for_tx(osmosisd, osmosis_home, &txhash, endpoints).await?;
+    Ok(txhash)
+}
+
+fn bank_send(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    from: &str,
+    to: &str,
+    coins: &str,
+    gas_prices: &str,
+    endpoints: &Endpoints,
+) -> Result<String> {
+    let args = ["bank", "send", from, to, coins].map(str::to_string);
+    inject::send(
+        osmosisd,
+        osmosis_home,
+        from,
+        args.into_iter(),
+        gas_prices,
+        endpoints,
+    )
+    .wrap_err(format!("Failed to send {} to {}", coins, to))
+}
+
+/// Wait for tx `txhash` to be included in a block, failing if it failed to execute there
+pub async fn wait_for_tx(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    txhash: &str,
+    endpoints: &Endpoints,
+) -> Result<()> {
+    // subsequent sends from the same account need the previous one committed to get the right sequence
+    for _ in 0..30 {
+        let output = Command::new(osmosisd)
+            .args(["query", "tx", txhash])
+            .arg("--home")
+            .arg(osmosis_home)
+            .args(["--node", &endpoints.rpc_addr()])
+            .args(["--output", "json"])
+            .output()
+            .wrap_err("Failed to query tx")?;
+
+        if output.status.success() {
+            // being accepted into the mempool only passed CheckTx, the tx can still fail in the block
+            let tx: serde_json::Value =
+                serde_json::from_slice(&output.stdout).wrap_err("Failed to parse tx")?;
+            if tx["code"].as_u64().unwrap_or_default() != 0 {
+                return Err(eyre!(
+                    "Tx {} failed: {}",
+                    txhash,
+                    tx["raw_log"].as_str().unwrap_or_default()
+                ));
+            }
+            return Ok(());
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    Err(eyre!("Timed out waiting for tx {} to be included", txhash))
+}
+
+/// Value of the first event attribute named `key` of included tx `txhash`
+pub fn tx_attribute(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    txhash: &str,
+    key: &str,
+    endpoints: &Endpoints,
+) -> Result<Option<String>> {
+    let output = Command::new(osmosisd)
+        .args(["query", "tx", txhash])
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--node", &endpoints.rpc_addr()])
+        .args(["--output", "json"])
+        .output()
+        .wrap_err("Failed to query tx")?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to query tx {}: {}",
+            txhash,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let tx: serde_json::Value =
+        serde_json::from_slice(&output.stdout).wrap_err("Failed to parse tx query response")?;
+    Ok(find_attribute(&tx, key))
+}
+
+/// Value of the first event attribute named `key` anywhere in a tx query response, whichever
+/// version of the events format it uses
+fn find_attribute(value: &serde_json::Value, key: &str) -> Option<String> {
+    match value {
+        serde_json::Value::Object(object) => {
+            if object.get("key").and_then(serde_json::Value::as_str) == Some(key) {
+                if let Some(value) = object.get("value").and_then(serde_json::Value::as_str) {
+                    return Some(value.to_string());
+                }
+            }
+            object.values().find_map(|value| find_attribute(value, key))
+        }
+        serde_json::Value::Array(items) => items.iter().find_map(|item| find_attribute(item, key)),
+        _ => None,
+    }
+}
+
+/// Address of key `key` in the home's test keyring
+pub fn key_address(osmosisd: &PathBuf, osmosis_home: &PathBuf, key: &str) -> Result<String> {
+    let output = Command::new(osmosisd)
+        .args(["keys", "show", key, "-a"])
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--keyring-backend", "test"])
+        .output()
+        .wrap_err("Failed to resolve key address")?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to resolve address of key {}: {}",
+            key,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Name of the key in the home's test keyring holding `address`, if any
+pub fn key_name(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    address: &str,
+) -> Result<Option<String>> {
+    let output = Command::new(osmosisd)
+        .args(["keys", "list"])
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--keyring-backend", "test"])
+        .args(["--output", "json"])
+        .output()
+        .wrap_err("Failed to list keys")?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to list keys: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let keys: Vec<serde_json::Value> =
+        serde_json::from_slice(&output.stdout).wrap_err("Failed to parse keys list")?;
+    Ok(keys
+        .iter()
+        .find(|key| key["address"].as_str() == Some(address))
+        .and_then(|key| key["name"].as_str())
+        .map(str::to_string))
+}
+
+/// Key in the test keyring of `operator`, given as an address or key name, to pass proposals
+/// with its validator's voting power
+pub fn operator_key(osmosisd: &PathBuf, osmosis_home: &PathBuf, operator: &str) -> Result<String> {
+    if !operator.starts_with("osmo1") {
+        return Ok(operator.to_string());
+    }
+    key_name(osmosisd, osmosis_home, operator)?.ok_or_else(|| {
+        eyre!(
+            "No key for operator {} in the test keyring, import it with `osmoinplace keys seed` or \
+             pass --operator-address as a key name",
+            operator
+        )
+    })
+}
+
+/// Address to hand control of the fork to, given as an address or as the name of a key in the
+/// home's test keyring
+pub fn operator_address(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    operator: Option<&str>,
+) -> Result<String> {
+    match operator {
+        None => Ok(DEFAULT_OPERATOR_ADDRESS.to_string()),
+        Some(address) if address.starts_with("osmo1") => Ok(address.to_string()),
+        Some(key) => key_address(osmosisd, osmosis_home, key),
+    }
+}
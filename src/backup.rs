@@ -0,0 +1,989 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    path::{Component, Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::{Args, ValueEnum};
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{disk, du, platform, safe_mode::SafeMode, snapshot, upgrade};
+
+/// Frame magic numbers, to tell archive formats apart on restore
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4d, 0x18];
+
+/// Where `backup` and `restore` default to
+pub fn default_path() -> PathBuf {
+    platform::home_dir().join(".osmosisd_bak")
+}
+
+/// Where named backups are kept
+pub fn root() -> PathBuf {
+    platform::home_dir().join(".osmosisd_backups")
+}
+
+/// Backup name, which can't point outside of `root()`
+pub fn parse_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    match valid {
+        true => Ok(name.to_string()),
+        false => Err(format!(
+            "invalid backup name {:?}, use letters, digits, - and _",
+            name
+        )),
+    }
+}
+
+/// Path of the backup named `name` if given, else `path`
+pub fn named_or(path: &Option<PathBuf>, name: &Option<String>) -> Option<PathBuf> {
+    match name {
+        Some(name) => Some(root().join(name)),
+        None => path.clone(),
+    }
+}
+
+/// Recorded next to each backup as `<backup>.json` to tell what it holds and catch corruption
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    /// Unix timestamp the backup was taken at
+    pub created: u64,
+    /// Last block height of the backed up home, if known
+    pub height: Option<u64>,
+    /// Version of the osmosisd the home was last run with
+    #[serde(default)]
+    pub osmosisd_version: Option<String>,
+    /// Files of the backup relative to it, a single empty path for archives
+    #[serde(default)]
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Modification time in nanoseconds since the epoch, to tell the file changed without
+    /// reading it
+    #[serde(default)]
+    pub modified: Option<u64>,
+    /// Taken by the first `backup verify` rather than on backup, which would read the whole home
+    /// and make linked backups as slow as a copy
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl FileEntry {
+    fn new(backup_path: &Path, path: PathBuf) -> Result<Self> {
+        let metadata = entry_path(backup_path, &path).metadata()?;
+        Ok(FileEntry {
+            path,
+            size: metadata.len(),
+            modified: modified(&metadata),
+            sha256: None,
+        })
+    }
+
+    /// Whether `other` describes the same unchanged file
+    fn matches(&self, other: &FileEntry) -> bool {
+        self.path == other.path
+            && self.size == other.size
+            && self.modified.is_some()
+            && self.modified == other.modified
+    }
+}
+
+fn modified(metadata: &std::fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(modified.as_nanos()).ok()
+}
+
+fn manifest_path(backup_path: &Path) -> PathBuf {
+    let mut path = backup_path.as_os_str().to_owned();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+/// Record what the backup at `backup_path` of `osmosis_home` holds. Files are only described by
+/// their size and modification time, checksums recorded by an earlier `backup verify` are kept
+/// for the files that didn't change since.
+pub fn write_manifest(osmosisd: &PathBuf, osmosis_home: &Path, backup_path: &Path) -> Result<()> {
+    let files = match backup_path.is_dir() {
+        true => files(backup_path, Path::new(""))?,
+        false => vec![PathBuf::new()],
+    };
+
+    let previous: HashMap<PathBuf, FileEntry> = read_manifest(backup_path)
+        .map(|manifest| {
+            manifest
+                .files
+                .into_iter()
+                .map(|file| (file.path.clone(), file))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let manifest = Manifest {
+        created: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        height: height(osmosis_home),
+        osmosisd_version: upgrade::binary_version(osmosisd).ok(),
+        files: files
+            .into_iter()
+            .map(|path| {
+                let mut file = FileEntry::new(backup_path, path)?;
+                file.sha256 = previous
+                    .get(&file.path)
+                    .filter(|previous| previous.matches(&file))
+                    .and_then(|previous| previous.sha256.clone());
+                Ok(file)
+            })
+            .collect::<Result<_>>()?,
+    };
+
+    save_manifest(backup_path, &manifest)
+}
+
+fn save_manifest(backup_path: &Path, manifest: &Manifest) -> Result<()> {
+    let path = manifest_path(backup_path);
+    std::fs::write(&path, serde_json::to_string_pretty(manifest)?).wrap_err(format!(
+        "Failed to write backup manifest: {}",
+        path.display()
+    ))
+}
+
+pub fn remove_manifest(backup_path: &Path) -> Result<()> {
+    let path = manifest_path(backup_path);
+    if path.exists() {
+        std::fs::remove_file(&path).wrap_err(format!(
+            "Failed to remove backup manifest: {}",
+            path.display()
+        ))?;
+    }
+    Ok(())
+}
+
+fn read_manifest(backup_path: &Path) -> Option<Manifest> {
+    let content = std::fs::read_to_string(manifest_path(backup_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Check every file of the backup at `backup_path` against its manifest: its size and
+/// modification time, and its checksum when one was recorded. With `checksum`, the files without
+/// one are hashed and their checksums recorded for the next verification.
+pub fn verify(backup_path: &Path, checksum: bool) -> Result<()> {
+    let mut manifest = read_manifest(backup_path).ok_or_else(|| {
+        eyre!(
+            "No manifest for backup {}, take the backup again to verify it",
+            backup_path.display()
+        )
+    })?;
+
+    let mut problems = vec![];
+    let mut hashed = false;
+    for file in &mut manifest.files {
+        let full = entry_path(backup_path, &file.path);
+        let Ok(metadata) = full.metadata() else {
+            problems.push(format!("{} is missing", full.display()));
+            continue;
+        };
+
+        if metadata.len() != file.size {
+            problems.push(format!(
+                "{} is {} bytes, expected {}",
+                full.display(),
+                metadata.len(),
+                file.size
+            ));
+            continue;
+        }
+        if file.modified.is_some() && modified(&metadata) != file.modified {
+            problems.push(format!("{} was modified since the backup", full.display()));
+            continue;
+        }
+
+        match &file.sha256 {
+            Some(expected) if sha256(&full)? != *expected => {
+                problems.push(format!("{} doesn't match its checksum", full.display()))
+            }
+            Some(_) => {}
+            None if checksum => {
+                file.sha256 = Some(sha256(&full)?);
+                hashed = true;
+            }
+            None => {}
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(eyre!(
+            "Backup {} is corrupted:\n  {}",
+            backup_path.display(),
+            problems.join("\n  ")
+        ));
+    }
+
+    if hashed {
+        save_manifest(backup_path, &manifest)?;
+    }
+
+    Ok(())
+}
+
+/// Whether `backup_path` has a manifest to verify against, backups taken by older versions don't
+pub fn has_manifest(backup_path: &Path) -> bool {
+    manifest_path(backup_path).exists()
+}
+
+/// Path of a manifest entry, the backup itself for archives
+fn entry_path(backup_path: &Path, path: &Path) -> PathBuf {
+    match path.as_os_str().is_empty() {
+        true => backup_path.to_path_buf(),
+        false => backup_path.join(path),
+    }
+}
+
+/// Files under `dir`, relative to the backup root
+pub fn files(root: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(root.join(dir))
+        .wrap_err(format!("Failed to read {}", root.join(dir).display()))?
+    {
+        let entry = entry?;
+        let path = dir.join(entry.file_name());
+        match entry.file_type()?.is_dir() {
+            true => files.extend(self::files(root, &path)?),
+            false => files.push(path),
+        }
+    }
+    Ok(files)
+}
+
+fn sha256(path: &Path) -> Result<String> {
+    let mut file = File::open(path).wrap_err(format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).wrap_err(format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Last height signed by the validator of a fork, falling back to the height of the snapshot the
+/// home was created from for homes that never validated
+pub fn height(osmosis_home: &Path) -> Option<u64> {
+    let signed =
+        std::fs::read_to_string(osmosis_home.join("data").join("priv_validator_state.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|state| state["height"].as_str()?.parse::<u64>().ok())
+            .filter(|height| *height > 0);
+
+    signed.or_else(|| snapshot::read_metadata(osmosis_home)?.height)
+}
+
+/// Print the named backups with their creation time, source height and size
+pub fn list() -> Result<()> {
+    let backups = named_backups()?;
+    if backups.is_empty() {
+        println!("No named backups in {}", root().display());
+        return Ok(());
+    }
+
+    for path in backups {
+        let height = read_manifest(&path)
+            .and_then(|manifest| manifest.height)
+            .map_or("-".to_string(), |height| height.to_string());
+
+        println!(
+            "{:<24} {:>8}  height {:>10}  {:>8.1} GB",
+            name(&path).bold(),
+            du::format_age(age(&path)),
+            height,
+            disk::usage(&path)? as f64 / 1e9
+        );
+    }
+
+    Ok(())
+}
+
+/// Backups in the named backup root, by name
+fn named_backups() -> Result<Vec<PathBuf>> {
+    let root = root();
+    let mut backups = match root.exists() {
+        true => std::fs::read_dir(&root)
+            .wrap_err(format!("Failed to read {}", root.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?,
+        false => vec![],
+    };
+    backups.retain(|path| path.extension().is_none_or(|ext| ext != "json"));
+    backups.sort();
+    Ok(backups)
+}
+
+fn name(backup_path: &Path) -> String {
+    backup_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Time since the backup was taken, from its manifest or else its modification time
+fn age(backup_path: &Path) -> Option<Duration> {
+    let created = match read_manifest(backup_path) {
+        Some(manifest) => UNIX_EPOCH + Duration::from_secs(manifest.created),
+        None => backup_path.metadata().ok()?.modified().ok()?,
+    };
+    SystemTime::now().duration_since(created).ok()
+}
+
+/// Remove the backup named `name`
+pub fn delete(name: &str, safe_mode: &SafeMode) -> Result<()> {
+    let name = parse_name(name).map_err(|e| eyre!(e))?;
+    let path = root().join(&name);
+    if !path.exists() {
+        return Err(eyre!("No backup named {} in {}", name, root().display()));
+    }
+    safe_mode.check_backup_delete(&path)?;
+
+    match path.is_dir() {
+        true => std::fs::remove_dir_all(&path),
+        false => std::fs::remove_file(&path),
+    }
+    .wrap_err(format!("Failed to remove backup: {}", path.display()))?;
+    remove_manifest(&path)?;
+
+    println!("{} {}", "✓ Deleted backup".green(), name);
+    Ok(())
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BackupArgs {
+    /// Write a single compressed archive instead of copying the home directory
+    #[arg(long, value_enum, default_value_t = Compression::None)]
+    pub compress: Compression,
+
+    /// Only copy the files that changed since the existing plain backup at the same path was
+    /// taken
+    #[arg(long, conflicts_with = "compress")]
+    pub incremental: bool,
+
+    /// How to copy the home directory into a plain backup
+    #[arg(long, value_enum, default_value_t = Method::Copy, conflicts_with = "compress")]
+    pub method: Method,
+
+    /// Only back up the chain state (data/ and wasm/), not the config and keys
+    #[arg(long)]
+    pub data_only: bool,
+}
+
+impl Default for BackupArgs {
+    fn default() -> Self {
+        BackupArgs {
+            compress: Compression::None,
+            incremental: false,
+            method: Method::Copy,
+            data_only: false,
+        }
+    }
+}
+
+/// Directories of the home holding the chain state, all that --data-only backs up and restores
+pub const STATE_DIRS: [&str; 2] = ["data", "wasm"];
+
+/// Whether `path`, relative to the home or a backup, is part of the chain state
+pub fn is_state(path: &Path) -> bool {
+    path.components()
+        .find_map(|component| match component {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        })
+        .is_some_and(|name| STATE_DIRS.iter().any(|dir| name == *dir))
+}
+
+/// How many named backups to keep when pruning
+#[derive(Args, Debug, Clone, Default)]
+pub struct Retention {
+    /// Keep only this many of the newest named backups
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub keep: Option<u64>,
+
+    /// Remove named backups older than this many days
+    #[arg(long)]
+    pub max_age_days: Option<u64>,
+}
+
+impl Retention {
+    pub fn is_set(&self) -> bool {
+        self.keep.is_some() || self.max_age_days.is_some()
+    }
+
+    /// Items of `backups`, given with their age, falling outside the retention. Backups of
+    /// unknown age count as the oldest for `keep` but never expire by age.
+    fn expired<T>(&self, mut backups: Vec<(Option<Duration>, T)>) -> Vec<T> {
+        // newest first, backups of unknown age last
+        backups.sort_by_key(|(age, _)| age.unwrap_or(Duration::MAX));
+
+        let max_age = self
+            .max_age_days
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+        backups
+            .into_iter()
+            .enumerate()
+            .filter(|(i, (age, _))| {
+                self.keep.is_some_and(|keep| *i as u64 >= keep)
+                    || max_age.is_some_and(|max_age| age.is_some_and(|age| age > max_age))
+            })
+            .map(|(_, (_, backup))| backup)
+            .collect()
+    }
+}
+
+/// Remove the named backups falling outside `retention`, or only report them on `dry_run`
+pub fn prune(retention: &Retention, dry_run: bool, safe_mode: &SafeMode) -> Result<()> {
+    let backups = named_backups()?
+        .into_iter()
+        .map(|path| (age(&path), path))
+        .collect();
+    let expired = retention.expired(backups);
+
+    if expired.is_empty() {
+        println!("No named backups to prune");
+        return Ok(());
+    }
+
+    // the retention asked for these, so they aren't confirmed one by one
+    let safe_mode = SafeMode {
+        yes: true,
+        ..*safe_mode
+    };
+    for path in expired {
+        match dry_run {
+            true => println!(
+                "Would delete backup {} ({}, {:.1} GB)",
+                name(&path),
+                du::format_age(age(&path)),
+                disk::usage(&path)? as f64 / 1e9
+            ),
+            false => delete(&name(&path), &safe_mode)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// How a backup is written
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Single tar archive compressed with zstd, best ratio
+    Zstd,
+    /// Single tar archive compressed with lz4, fastest
+    Lz4,
+    /// Plain copy of the home directory
+    None,
+}
+
+/// How a plain backup copies the home directory
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// Reflink if the filesystem supports it, else hardlink on the same filesystem, else copy
+    Auto,
+    /// Byte for byte copy
+    Copy,
+    /// Hardlink the database files that are never modified once written, copy the rest
+    Hardlink,
+    /// Copy-on-write clone of every file (btrfs, XFS, APFS)
+    Reflink,
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Method::Auto => "auto",
+            Method::Copy => "copy",
+            Method::Hardlink => "hardlink",
+            Method::Reflink => "reflink",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Copy `osmosis_home` to `backup_path` with `method`, returning the method actually used
+pub fn copy(
+    osmosis_home: &Path,
+    backup_path: &Path,
+    method: Method,
+    data_only: bool,
+) -> Result<Method> {
+    let method = match method {
+        Method::Auto => detect(osmosis_home, backup_path)?,
+        method => method,
+    };
+
+    if !data_only {
+        copy_tree(osmosis_home, backup_path, method)?;
+        return Ok(method);
+    }
+
+    std::fs::create_dir_all(backup_path).wrap_err(format!(
+        "Failed to create directory: {}",
+        backup_path.display()
+    ))?;
+    for dir in STATE_DIRS {
+        if osmosis_home.join(dir).exists() {
+            copy_tree(&osmosis_home.join(dir), &backup_path.join(dir), method)?;
+        }
+    }
+
+    Ok(method)
+}
+
+fn copy_tree(src: &Path, dest: &Path, method: Method) -> Result<()> {
+    match method {
+        Method::Copy | Method::Auto => {
+            let options = fs_extra::dir::CopyOptions::new().copy_inside(true);
+            fs_extra::dir::copy(src, dest, &options).wrap_err(format!(
+                "Failed to copy {} to {}",
+                src.display(),
+                dest.display()
+            ))?;
+        }
+        Method::Hardlink => link_tree(src, dest)?,
+        Method::Reflink => reflink(src, dest)?,
+    }
+    Ok(())
+}
+
+/// Cheapest method `osmosis_home` can be backed up to `backup_path` with
+fn detect(osmosis_home: &Path, backup_path: &Path) -> Result<Method> {
+    let backup_dir = backup_path
+        .ancestors()
+        .skip(1)
+        .find(|p| p.exists())
+        .ok_or_else(|| eyre!("No existing ancestor of {}", backup_path.display()))?;
+
+    if !platform::same_filesystem(osmosis_home, backup_dir)? {
+        return Ok(Method::Copy);
+    }
+
+    // try cloning a small file of our own, the node never sees it
+    let source = osmosis_home.join(".osmoinplace-reflink-source");
+    let probe = backup_dir.join(".osmoinplace-reflink-probe");
+    std::fs::write(&source, b"osmoinplace")
+        .wrap_err(format!("Failed to write {}", source.display()))?;
+    let supported = reflink(&source, &probe).is_ok();
+    let _ = std::fs::remove_file(&source);
+    let _ = std::fs::remove_file(&probe);
+
+    Ok(match supported {
+        true => Method::Reflink,
+        false => Method::Hardlink,
+    })
+}
+
+fn reflink(src: &Path, dest: &Path) -> Result<()> {
+    let mut cmd = Command::new("cp");
+    match cfg!(target_os = "macos") {
+        true => cmd.arg("-cR"),
+        false => cmd.arg("-R").arg("--reflink=always"),
+    };
+
+    let output = cmd
+        .arg(src)
+        .arg(dest)
+        .output()
+        .wrap_err("Failed to run cp")?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to reflink {}: {}",
+            src.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+/// Bring the plain backup at `backup_path` up to date with `osmosis_home`, copying only the files
+/// whose size changed or that were modified after they were last copied, and removing the ones no
+/// longer in the home. With `data_only` only the chain state is synced, the rest of the backup is
+/// left as it is. Returns the copied files.
+pub fn sync(osmosis_home: &Path, backup_path: &Path, data_only: bool) -> Result<HashSet<PathBuf>> {
+    let mut home_files = files(osmosis_home, Path::new(""))?;
+    if data_only {
+        home_files.retain(|path| is_state(path));
+    }
+    let mut changed = HashSet::new();
+
+    for path in &home_files {
+        let (from, to) = (osmosis_home.join(path), backup_path.join(path));
+        let source = from.metadata()?;
+
+        let unchanged = to.metadata().is_ok_and(|copy| {
+            copy.len() == source.len()
+                && matches!(
+                    (source.modified(), copy.modified()),
+                    (Ok(modified), Ok(copied)) if modified <= copied
+                )
+        });
+        if unchanged {
+            continue;
+        }
+
+        // the copy may be a hardlink to a file of the home, never write through it
+        if to.exists() {
+            std::fs::remove_file(&to).wrap_err(format!("Failed to remove {}", to.display()))?;
+        }
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)
+                .wrap_err(format!("Failed to create directory: {}", parent.display()))?;
+        }
+        std::fs::copy(&from, &to).wrap_err(format!("Failed to copy {}", from.display()))?;
+        changed.insert(path.clone());
+    }
+
+    let home_files = home_files.into_iter().collect::<HashSet<_>>();
+    for path in files(backup_path, Path::new(""))? {
+        if !home_files.contains(&path) && (!data_only || is_state(&path)) {
+            let stale = backup_path.join(&path);
+            std::fs::remove_file(&stale)
+                .wrap_err(format!("Failed to remove {}", stale.display()))?;
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Whether the database engines never modify `path` once it is written, so the home and a backup
+/// can share it. Everything else (write-ahead logs, manifests, validator state) is appended to or
+/// rewritten in place.
+fn is_immutable(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("ldb" | "sst")
+    )
+}
+
+fn link_tree(src: &Path, dest: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest)
+        .wrap_err(format!("Failed to create directory: {}", dest.display()))?;
+
+    for entry in std::fs::read_dir(src).wrap_err(format!("Failed to read {}", src.display()))? {
+        let entry = entry?;
+        let (from, to) = (entry.path(), dest.join(entry.file_name()));
+
+        if entry.file_type()?.is_dir() {
+            link_tree(&from, &to)?;
+        } else if is_immutable(&from) {
+            std::fs::hard_link(&from, &to)
+                .wrap_err(format!("Failed to hardlink {}", from.display()))?;
+        } else {
+            std::fs::copy(&from, &to).wrap_err(format!("Failed to copy {}", from.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Pack the contents of `osmosis_home` into a single compressed tar archive at `archive_path`
+pub fn pack(
+    osmosis_home: &Path,
+    archive_path: &Path,
+    compression: Compression,
+    data_only: bool,
+) -> Result<()> {
+    let file = File::create(archive_path).wrap_err(format!(
+        "Failed to create backup archive: {}",
+        archive_path.display()
+    ))?;
+    pack_into(osmosis_home, file, compression, data_only)
+}
+
+/// Stream the contents of `osmosis_home` as a compressed tar archive into `writer`
+pub fn pack_into(
+    osmosis_home: &Path,
+    writer: impl Write,
+    compression: Compression,
+    data_only: bool,
+) -> Result<()> {
+    match compression {
+        Compression::Zstd => {
+            let encoder =
+                zstd::Encoder::new(writer, 3).wrap_err("Failed to create zstd encoder")?;
+            append_home(tar::Builder::new(encoder), osmosis_home, data_only)?
+                .finish()
+                .wrap_err("Failed to finish zstd stream")?;
+        }
+        Compression::Lz4 => {
+            let encoder = lz4::EncoderBuilder::new()
+                .build(writer)
+                .wrap_err("Failed to create lz4 encoder")?;
+            let (_, result) =
+                append_home(tar::Builder::new(encoder), osmosis_home, data_only)?.finish();
+            result.wrap_err("Failed to finish lz4 stream")?;
+        }
+        Compression::None => {
+            return Err(eyre!("Uncompressed backups are plain directory copies"));
+        }
+    }
+
+    Ok(())
+}
+
+fn append_home<W: Write>(
+    mut archive: tar::Builder<W>,
+    osmosis_home: &Path,
+    data_only: bool,
+) -> Result<W> {
+    let dirs = match data_only {
+        true => STATE_DIRS.to_vec(),
+        false => vec!["."],
+    };
+    for dir in dirs {
+        let path = osmosis_home.join(dir);
+        if path.exists() {
+            archive
+                .append_dir_all(dir, &path)
+                .wrap_err(format!("Failed to add {} to backup", path.display()))?;
+        }
+    }
+    archive
+        .into_inner()
+        .wrap_err("Failed to finish backup archive")
+}
+
+/// Extract a backup archive written by `pack` into `osmosis_home`, detecting its compression
+pub fn unpack(archive_path: &Path, osmosis_home: &Path, data_only: bool) -> Result<()> {
+    let file = File::open(archive_path).wrap_err(format!(
+        "Failed to open backup archive: {}",
+        archive_path.display()
+    ))?;
+    unpack_from(BufReader::new(file), osmosis_home, data_only)
+        .wrap_err(format!("Failed to restore {}", archive_path.display()))
+}
+
+/// Decompress `reader` according to the zstd or lz4 frame it starts with
+pub fn decoder(mut reader: impl BufRead + 'static) -> Result<Box<dyn Read>> {
+    let buffered = reader.fill_buf().wrap_err("Failed to read archive")?;
+    let magic: Option<[u8; 4]> = buffered.get(..4).and_then(|magic| magic.try_into().ok());
+
+    Ok(match magic {
+        Some(ZSTD_MAGIC) => {
+            Box::new(zstd::Decoder::with_buffer(reader).wrap_err("Failed to create zstd decoder")?)
+        }
+        Some(LZ4_MAGIC) => {
+            Box::new(lz4::Decoder::new(reader).wrap_err("Failed to create lz4 decoder")?)
+        }
+        _ => return Err(eyre!("Not a zstd or lz4 archive")),
+    })
+}
+
+/// Extract a backup archive streamed from `reader` into `osmosis_home`, only the chain state if
+/// `data_only`
+pub fn unpack_from(
+    reader: impl BufRead + 'static,
+    osmosis_home: &Path,
+    data_only: bool,
+) -> Result<()> {
+    std::fs::create_dir_all(osmosis_home).wrap_err(format!(
+        "Failed to create directory: {}",
+        osmosis_home.display()
+    ))?;
+
+    let decoder = decoder(reader)?;
+    let mut archive = tar::Archive::new(decoder);
+    if !data_only {
+        return archive
+            .unpack(osmosis_home)
+            .wrap_err("Failed to extract backup archive");
+    }
+
+    for entry in archive
+        .entries()
+        .wrap_err("Failed to read backup archive")?
+    {
+        let mut entry = entry.wrap_err("Failed to read backup archive")?;
+        if is_state(&entry.path()?) {
+            entry
+                .unpack_in(osmosis_home)
+                .wrap_err("Failed to extract backup archive")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_names() {
+        assert_eq!(parse_name("pre-v25_1").as_deref(), Ok("pre-v25_1"));
+    }
+
+    #[test]
+    fn rejects_names_escaping_the_backup_directory() {
+        for name in ["", "..", "a/b", "../etc", "with space", "dot.name"] {
+            assert!(parse_name(name).is_err(), "{:?} was accepted", name);
+        }
+    }
+
+    #[test]
+    fn data_only_sync_keeps_the_config_and_keys_of_the_backup() {
+        let (home, backup) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        for (dir, file) in [
+            (home.path(), "data/application.db/000001.log"),
+            (backup.path(), "data/stale.db/000001.log"),
+            (backup.path(), "config/config.toml"),
+            (backup.path(), "keyring-test/operator.info"),
+        ] {
+            std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+            std::fs::write(dir.join(file), file).unwrap();
+        }
+
+        sync(home.path(), backup.path(), true).unwrap();
+
+        assert!(backup
+            .path()
+            .join("data/application.db/000001.log")
+            .exists());
+        assert!(!backup.path().join("data/stale.db/000001.log").exists());
+        assert!(backup.path().join("config/config.toml").exists());
+        assert!(backup.path().join("keyring-test/operator.info").exists());
+    }
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[test]
+    fn manifest_takes_checksums_on_verify_and_keeps_them_for_unchanged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = dir.path().join("backup");
+        std::fs::create_dir_all(backup.join("data")).unwrap();
+        std::fs::write(backup.join("data/000001.sst"), "immutable").unwrap();
+        std::fs::write(backup.join("data/LOG"), "appended").unwrap();
+
+        let osmosisd = PathBuf::from("osmosisd-not-installed");
+        write_manifest(&osmosisd, dir.path(), &backup).unwrap();
+        let manifest = read_manifest(&backup).unwrap();
+        assert!(manifest.files.iter().all(|file| file.sha256.is_none()));
+
+        verify(&backup, false).unwrap();
+        assert!(read_manifest(&backup)
+            .unwrap()
+            .files
+            .iter()
+            .all(|file| file.sha256.is_none()));
+
+        verify(&backup, true).unwrap();
+        assert!(read_manifest(&backup)
+            .unwrap()
+            .files
+            .iter()
+            .all(|file| file.sha256.is_some()));
+
+        // an incremental backup rewrote LOG
+        std::fs::write(backup.join("data/LOG"), "appended more").unwrap();
+        write_manifest(&osmosisd, dir.path(), &backup).unwrap();
+        let manifest = read_manifest(&backup).unwrap();
+        let sha256 = |path: &str| {
+            manifest
+                .files
+                .iter()
+                .find(|file| file.path == Path::new(path))
+                .unwrap()
+                .sha256
+                .clone()
+        };
+        assert!(sha256("data/000001.sst").is_some());
+        assert!(sha256("data/LOG").is_none());
+    }
+
+    #[test]
+    fn verify_catches_files_changed_after_the_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = dir.path().join("backup");
+        std::fs::create_dir_all(&backup).unwrap();
+        std::fs::write(backup.join("LOG"), "before").unwrap();
+        std::fs::write(backup.join("MANIFEST"), "before").unwrap();
+
+        write_manifest(
+            &PathBuf::from("osmosisd-not-installed"),
+            dir.path(),
+            &backup,
+        )
+        .unwrap();
+        verify(&backup, true).unwrap();
+
+        // same size, only the modification time gives it away without a checksum
+        let log = File::options()
+            .write(true)
+            .open(backup.join("LOG"))
+            .unwrap();
+        log.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        std::fs::remove_file(backup.join("MANIFEST")).unwrap();
+
+        let error = verify(&backup, false).unwrap_err().to_string();
+        assert!(
+            error.contains("LOG was modified since the backup"),
+            "{}",
+            error
+        );
+        assert!(error.contains("MANIFEST is missing"), "{}", error);
+    }
+
+    fn backups() -> Vec<(Option<Duration>, &'static str)> {
+        vec![
+            (Some(DAY * 10), "ten-days"),
+            (None, "unknown"),
+            (Some(DAY / 2), "half-day"),
+            (Some(DAY * 3), "three-days"),
+        ]
+    }
+
+    #[test]
+    fn keeps_the_newest_backups() {
+        let retention = Retention {
+            keep: Some(2),
+            max_age_days: None,
+        };
+        assert_eq!(retention.expired(backups()), ["ten-days", "unknown"]);
+    }
+
+    #[test]
+    fn backups_of_unknown_age_go_first_when_keeping() {
+        let retention = Retention {
+            keep: Some(3),
+            max_age_days: None,
+        };
+        assert_eq!(retention.expired(backups()), ["unknown"]);
+    }
+
+    #[test]
+    fn expires_backups_by_age_but_not_those_of_unknown_age() {
+        let retention = Retention {
+            keep: None,
+            max_age_days: Some(2),
+        };
+        assert_eq!(retention.expired(backups()), ["three-days", "ten-days"]);
+    }
+
+    #[test]
+    fn expires_backups_outside_either_limit() {
+        let retention = Retention {
+            keep: Some(3),
+            max_age_days: Some(5),
+        };
+        assert_eq!(retention.expired(backups()), ["ten-days", "unknown"]);
+    }
+
+    #[test]
+    fn expires_nothing_without_retention() {
+        assert!(Retention::default().expired(backups()).is_empty());
+    }
+}
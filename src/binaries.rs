@@ -0,0 +1,430 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use futures::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{output::Events, platform, policy::Policy, spinner};
+
+const RELEASES_URL: &str = "https://github.com/osmosis-labs/osmosis/releases/download";
+
+/// Checksums published with every osmosis release, in `sha256sum` format
+const CHECKSUMS_FILE: &str = "sha256sum.txt";
+
+/// Prefix of binaries built from source, e.g. `git:osmosis-labs/osmosis#my-branch`
+const GIT_PREFIX: &str = "git:";
+
+/// Directory the managed osmosisd releases are installed into, one directory per version
+pub fn dir() -> PathBuf {
+    platform::home_dir().join(".cache/osmoinplace/bin")
+}
+
+/// File naming the version `bin use` made the default
+fn default_file() -> PathBuf {
+    dir().join("default")
+}
+
+fn binary_path(version: &str) -> PathBuf {
+    dir().join(version).join("osmosisd")
+}
+
+/// Release tag of `version`, e.g. `v25.0.0` for both `v25.0.0` and `25.0.0`
+fn tag(version: &str) -> String {
+    format!("v{}", version.trim_start_matches('v'))
+}
+
+/// Whether `osmosisd` names a release version like `v25.0.0` rather than a binary
+fn is_version(osmosisd: &Path) -> bool {
+    let name = osmosisd.to_string_lossy();
+    let mut parts = name.strip_prefix('v').unwrap_or("").split('.');
+    parts
+        .next()
+        .is_some_and(|major| major.parse::<u64>().is_ok())
+        && parts.next().is_some()
+}
+
+/// Binary to run for `osmosisd`: the managed binary of a version like `v25.0.0`, the default set
+/// with `bin use` in place of the plain `osmosisd`, or else `osmosisd` itself
+pub fn resolve(osmosisd: &Path) -> Result<PathBuf> {
+    if is_version(osmosisd) {
+        let version = tag(&osmosisd.to_string_lossy());
+        let path = binary_path(&version);
+        if !path.exists() {
+            return Err(eyre!(
+                "osmosisd {} is not installed, install it with `osmoinplace bin install {}`",
+                version,
+                version
+            ));
+        }
+        return Ok(path);
+    }
+
+    if osmosisd == Path::new("osmosisd") {
+        if let Ok(version) = std::fs::read_to_string(default_file()) {
+            let path = binary_path(version.trim());
+            if path.exists() {
+                return Ok(path);
+            }
+        }
+    }
+
+    Ok(osmosisd.to_path_buf())
+}
+
+/// Whether `osmosisd` is a `git:` ref to build from source
+pub fn is_git(osmosisd: &Path) -> bool {
+    osmosisd.to_string_lossy().starts_with(GIT_PREFIX)
+}
+
+/// Like [`resolve`], but builds `git:owner/repo#ref` from source first
+pub async fn resolve_or_build(
+    osmosisd: &Path,
+    policy: &Policy,
+    events: &Events,
+) -> Result<PathBuf> {
+    match osmosisd.to_string_lossy().strip_prefix(GIT_PREFIX) {
+        Some(spec) => build(spec, policy, events).await,
+        None => resolve(osmosisd),
+    }
+}
+
+/// Build `owner/repo#ref` (or `<url>#ref`) with `make build`, cached by the commit `ref` points
+/// to so a branch is only rebuilt when it moves
+async fn build(spec: &str, policy: &Policy, events: &Events) -> Result<PathBuf> {
+    let (repo, git_ref) = spec.split_once('#').unwrap_or((spec, "HEAD"));
+    let url = match repo.contains("://") {
+        true => repo.to_string(),
+        false => format!("https://github.com/{}.git", repo),
+    };
+
+    let commit = resolve_ref(&url, git_ref, policy).await?;
+    let path = dir().join("git").join(&commit).join("osmosisd");
+    if path.exists() {
+        println!(
+            "{}",
+            format!(
+                "✓ Using osmosisd built from {}#{} ({}).",
+                repo,
+                git_ref,
+                &commit[..12]
+            )
+            .green()
+        );
+        return Ok(path);
+    }
+
+    let src = dir()
+        .parent()
+        .unwrap()
+        .join("src")
+        .join(repo.rsplit("://").next().unwrap_or(repo).replace('/', "_"));
+    if !src.join(".git").exists() {
+        std::fs::create_dir_all(&src).wrap_err(format!("Failed to create {}", src.display()))?;
+        git(&src, &["init", "--quiet"]).await?;
+        git(&src, &["remote", "add", "origin", &url]).await?;
+    }
+
+    let src = &src;
+    let commit = &commit;
+    policy
+        .network(&format!("Fetching {}#{}", repo, git_ref), || async move {
+            git(src, &["fetch", "--quiet", "--depth", "1", "origin", commit]).await
+        })
+        .await?;
+    git(src, &["checkout", "--quiet", "--force", commit]).await?;
+
+    spinner! {
+        events,
+        &format!("Building osmosisd from {}#{} ({}), this takes a while...", repo, git_ref, &commit[..12]),
+        &format!("✓ Built osmosisd from {}#{}.", repo, git_ref),
+        make_build(src).await?
+    };
+
+    let dir = path.parent().unwrap();
+    std::fs::create_dir_all(dir).wrap_err(format!("Failed to create {}", dir.display()))?;
+    std::fs::copy(src.join("build").join("osmosisd"), &path).wrap_err(format!(
+        "Failed to cache the built osmosisd to {}",
+        path.display()
+    ))?;
+
+    Ok(path)
+}
+
+/// File recording the commit `git_ref` of `url` pointed to when last resolved
+fn ref_file(url: &str, git_ref: &str) -> PathBuf {
+    let name = format!("{}#{}", url, git_ref)
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect::<String>();
+    dir().join("git").join("refs").join(name)
+}
+
+/// Commit hash `git_ref` of `url` points to, without a round trip when it already is one. With
+/// --offline the commit it pointed to when last resolved stands in for it, so its cached build is
+/// used.
+async fn resolve_ref(url: &str, git_ref: &str, policy: &Policy) -> Result<String> {
+    if git_ref.len() == 40 && git_ref.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(git_ref.to_lowercase());
+    }
+
+    let ref_file = ref_file(url, git_ref);
+    if policy.offline {
+        let commit = std::fs::read_to_string(&ref_file).map_err(|_| {
+            eyre!(
+                "{}#{} was never resolved, which needs the network, disabled by --offline",
+                url,
+                git_ref
+            )
+        })?;
+        println!(
+            "{}",
+            format!(
+                "Using the commit {} pointed to when last resolved, it may have moved since.",
+                git_ref
+            )
+            .yellow()
+        );
+        return Ok(commit.trim().to_string());
+    }
+
+    let output = policy
+        .network(&format!("Resolving {}", git_ref), || async move {
+            let output = Command::new("git")
+                .args(["ls-remote", url, git_ref, &format!("{}^{{}}", git_ref)])
+                // a timed out attempt must not leave git running
+                .kill_on_drop(true)
+                .output()
+                .await
+                .wrap_err("Failed to run git, is it installed?")?;
+            if !output.status.success() {
+                return Err(eyre!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        })
+        .await?;
+
+    // annotated tags are listed twice, the `^{}` line has the commit they point to
+    let refs = output
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .collect::<Vec<_>>();
+    let commit = refs
+        .iter()
+        .find(|(_, name)| name.ends_with("^{}"))
+        .or_else(|| refs.first())
+        .map(|(commit, _)| commit.to_string())
+        .ok_or_else(|| eyre!("No branch or tag {} in {}", git_ref, url))?;
+
+    let refs_dir = ref_file.parent().unwrap();
+    std::fs::create_dir_all(refs_dir)
+        .wrap_err(format!("Failed to create {}", refs_dir.display()))?;
+    std::fs::write(&ref_file, &commit).wrap_err(format!(
+        "Failed to record {} in {}",
+        commit,
+        ref_file.display()
+    ))?;
+    Ok(commit)
+}
+
+async fn git(src: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(src)
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .wrap_err("Failed to run git, is it installed?")?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+async fn make_build(src: &Path) -> Result<()> {
+    let output = Command::new("make")
+        .arg("build")
+        .current_dir(src)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .wrap_err("Failed to run make, is it installed?")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let tail = stderr.lines().rev().take(20).collect::<Vec<_>>();
+        return Err(eyre!(
+            "make build failed:\n{}",
+            tail.into_iter().rev().collect::<Vec<_>>().join("\n")
+        ));
+    }
+    Ok(())
+}
+
+/// Download the official release `version` for this platform, verified against the release's
+/// published checksum
+pub async fn install(version: &str, policy: &Policy) -> Result<()> {
+    let version = tag(version);
+    let path = binary_path(&version);
+    if path.exists() {
+        println!(
+            "{}",
+            format!("✓ osmosisd {} is already installed.", version).green()
+        );
+        return Ok(());
+    }
+
+    let file_name = format!(
+        "osmosisd-{}-{}",
+        version.trim_start_matches('v'),
+        platform::Platform::host()?
+    );
+    let expected = fetch_checksum(&version, &file_name, policy).await?;
+
+    let dir = path.parent().unwrap();
+    std::fs::create_dir_all(dir).wrap_err(format!("Failed to create {}", dir.display()))?;
+    let partial = path.with_extension("partial");
+    let url = format!("{}/{}/{}", RELEASES_URL, version, file_name);
+    let checksum = download(&url, &partial, policy)
+        .await
+        .wrap_err(format!("Failed to download osmosisd {}", version))?;
+
+    if checksum != expected {
+        std::fs::remove_file(&partial).ok();
+        return Err(eyre!(
+            "Checksum mismatch for {}, expected sha256 {} but got {}, the download is corrupted",
+            file_name,
+            expected,
+            checksum
+        ));
+    }
+
+    platform::make_executable(&partial)?;
+    std::fs::rename(&partial, &path).wrap_err(format!("Failed to install {}", path.display()))?;
+    println!(
+        "{}",
+        format!("✓ Installed osmosisd {} to {}.", version, path.display()).green()
+    );
+
+    Ok(())
+}
+
+/// Print the installed versions, marking the default one
+pub fn list() -> Result<()> {
+    let default = std::fs::read_to_string(default_file()).unwrap_or_default();
+    let mut versions = match std::fs::read_dir(dir()) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|version| binary_path(version).exists())
+            .collect::<Vec<_>>(),
+        Err(_) => vec![],
+    };
+    versions.sort();
+
+    if versions.is_empty() {
+        println!("No osmosisd releases installed, see `osmoinplace bin install`");
+    }
+    for version in versions {
+        let marker = if version == default.trim() { "*" } else { " " };
+        println!("{} {}", marker, version);
+    }
+    Ok(())
+}
+
+/// Make installed `version` the default osmosisd
+pub fn use_version(version: &str) -> Result<()> {
+    let version = tag(version);
+    if !binary_path(&version).exists() {
+        return Err(eyre!(
+            "osmosisd {} is not installed, install it with `osmoinplace bin install {}`",
+            version,
+            version
+        ));
+    }
+
+    std::fs::write(default_file(), &version).wrap_err("Failed to set default osmosisd")?;
+    println!(
+        "{}",
+        format!("✓ osmosisd {} is now the default.", version).green()
+    );
+    Ok(())
+}
+
+/// Published sha256 of `file_name` in release `version`
+async fn fetch_checksum(version: &str, file_name: &str, policy: &Policy) -> Result<String> {
+    let url = &format!("{}/{}/{}", RELEASES_URL, version, CHECKSUMS_FILE);
+    let client = &policy.client()?;
+    let content = policy
+        .network("Fetching release checksums", || async move {
+            Ok(client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?)
+        })
+        .await
+        .wrap_err(format!("Failed to fetch checksums of osmosisd {}", version))?;
+
+    // `sha256sum` format: `<digest>  <file name>`
+    content
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim().trim_start_matches('*') == file_name)
+        .map(|(digest, _)| digest.to_lowercase())
+        .ok_or_else(|| {
+            eyre!(
+                "osmosisd {} has no release binary {} for this platform",
+                version,
+                file_name
+            )
+        })
+}
+
+/// Download `url` to `path`, returning its sha256. The network timeout covers connecting and the
+/// response headers, not the transfer of the whole binary.
+async fn download(url: &str, path: &Path, policy: &Policy) -> Result<String> {
+    let client = &policy.client()?;
+    let response = policy
+        .network("Downloading osmosisd", || async move {
+            Ok(client.get(url).send().await?.error_for_status()?)
+        })
+        .await?;
+
+    let pb = ProgressBar::new(response.content_length().unwrap_or(0));
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{msg}\n[{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")?
+            .progress_chars("#>-"),
+    );
+    pb.set_message(format!("Downloading {}...", url).cyan().to_string());
+
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .wrap_err(format!("Failed to create {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.wrap_err("Failed to download chunk")?;
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+        pb.inc(chunk.len() as u64);
+    }
+    file.flush().await?;
+
+    pb.finish_and_clear();
+    Ok(format!("{:x}", hasher.finalize()))
+}
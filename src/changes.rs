@@ -0,0 +1,269 @@
+use std::{path::PathBuf, process::Command};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use serde::Deserialize;
+
+/// Changes an upgrade claims to make, checked against the running upgraded node
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Modules the upgrade adds
+    #[serde(default)]
+    pub added_modules: Vec<String>,
+    /// Modules (and their stores) the upgrade removes
+    #[serde(default)]
+    pub removed_modules: Vec<String>,
+    #[serde(default)]
+    pub params: Vec<ParamChange>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ParamChange {
+    /// Module to query params of, e.g. `poolmanager`
+    pub module: String,
+    /// Dotted path into the params query response, e.g. `params.taker_fee_params.default_taker_fee`
+    pub key: String,
+    /// Expected value, compared as a string. Only the presence of `key` is checked if unset.
+    pub value: Option<String>,
+}
+
+pub fn load(path: &PathBuf) -> Result<Manifest> {
+    let content = std::fs::read_to_string(path).wrap_err(format!(
+        "Failed to read changes manifest: {}",
+        path.display()
+    ))?;
+    toml::from_str(&content).wrap_err(format!(
+        "Failed to parse changes manifest: {}",
+        path.display()
+    ))
+}
+
+/// Check every item of `manifest` against the running node, failing if any doesn't hold
+pub fn check(osmosisd: &PathBuf, osmosis_home: &PathBuf, manifest: &Manifest) -> Result<()> {
+    let mut failures = 0;
+    let mut report = |description: String, result: Result<()>| match result {
+        Ok(()) => println!("{}", format!("✓ {}", description).green()),
+        Err(e) => {
+            failures += 1;
+            println!("{}", format!("✗ {}: {}", description, e).red());
+        }
+    };
+
+    if !manifest.added_modules.is_empty() || !manifest.removed_modules.is_empty() {
+        let modules = module_names(osmosisd, osmosis_home)?;
+
+        for module in &manifest.added_modules {
+            report(
+                format!("module {} added", module),
+                match modules.contains(module) {
+                    true => Ok(()),
+                    false => Err(eyre!("not in module versions")),
+                },
+            );
+        }
+
+        for module in &manifest.removed_modules {
+            report(
+                format!("module {} removed", module),
+                match modules.contains(module) {
+                    true => Err(eyre!("still in module versions")),
+                    false => Ok(()),
+                },
+            );
+        }
+    }
+
+    for param in &manifest.params {
+        let description = match &param.value {
+            Some(value) => format!("{} {} = {}", param.module, param.key, value),
+            None => format!("{} {} exists", param.module, param.key),
+        };
+        report(description, check_param(osmosisd, osmosis_home, param));
+    }
+
+    if failures > 0 {
+        return Err(eyre!("{} expected change(s) did not hold", failures));
+    }
+
+    Ok(())
+}
+
+fn query(osmosisd: &PathBuf, osmosis_home: &PathBuf, args: &[&str]) -> Result<serde_json::Value> {
+    let output = Command::new(osmosisd)
+        .arg("query")
+        .args(args)
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--output", "json"])
+        .output()
+        .wrap_err(format!("Failed to query {}", args.join(" ")))?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to query {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .wrap_err(format!("Failed to parse {} query response", args.join(" ")))
+}
+
+fn module_names(osmosisd: &PathBuf, osmosis_home: &PathBuf) -> Result<Vec<String>> {
+    parse_module_names(&query(
+        osmosisd,
+        osmosis_home,
+        &["upgrade", "module_versions"],
+    )?)
+}
+
+fn parse_module_names(response: &serde_json::Value) -> Result<Vec<String>> {
+    Ok(response["module_versions"]
+        .as_array()
+        .ok_or_else(|| eyre!("Unexpected module versions query response"))?
+        .iter()
+        .filter_map(|module| module["name"].as_str().map(str::to_string))
+        .collect())
+}
+
+fn check_param(osmosisd: &PathBuf, osmosis_home: &PathBuf, param: &ParamChange) -> Result<()> {
+    let response = query(osmosisd, osmosis_home, &[&param.module, "params"])?;
+    check_param_in(&response, param)
+}
+
+/// Check `param` against the params query `response` of its module
+fn check_param_in(response: &serde_json::Value, param: &ParamChange) -> Result<()> {
+    let actual = param
+        .key
+        .split('.')
+        .try_fold(response, |value, segment| value.get(segment))
+        .ok_or_else(|| eyre!("not in params"))?;
+
+    let Some(expected) = &param.value else {
+        return Ok(());
+    };
+
+    let actual = match actual {
+        serde_json::Value::String(actual) => actual.clone(),
+        actual => actual.to_string(),
+    };
+    match &actual == expected {
+        true => Ok(()),
+        false => Err(eyre!("got {}", actual)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn param(key: &str, value: Option<&str>) -> ParamChange {
+        ParamChange {
+            module: "poolmanager".to_string(),
+            key: key.to_string(),
+            value: value.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parses_manifests_with_every_section_optional() {
+        let manifest: Manifest = toml::from_str("").unwrap();
+        assert!(manifest.added_modules.is_empty());
+        assert!(manifest.removed_modules.is_empty());
+        assert!(manifest.params.is_empty());
+
+        let manifest: Manifest = toml::from_str(
+            r#"
+            added_modules = ["smartaccount"]
+
+            [[params]]
+            module = "poolmanager"
+            key = "params.taker_fee_params.default_taker_fee"
+            value = "0.001500000000000000"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(manifest.added_modules, ["smartaccount"]);
+        assert_eq!(manifest.params.len(), 1);
+        assert_eq!(manifest.params[0].module, "poolmanager");
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_params_without_a_key() {
+        assert!(toml::from_str::<Manifest>("added_module = [\"typo\"]").is_err());
+        assert!(toml::from_str::<Manifest>("[[params]]\nmodule = \"gov\"").is_err());
+        assert!(toml::from_str::<Manifest>("added_modules = \"not a list\"").is_err());
+    }
+
+    #[test]
+    fn checks_nested_params_as_strings() {
+        let response = json!({
+            "params": {
+                "taker_fee_params": { "default_taker_fee": "0.001500000000000000" },
+                "pool_creation_fee": [],
+                "max_pools": 100,
+                "enabled": true,
+            }
+        });
+
+        for (key, value) in [
+            (
+                "params.taker_fee_params.default_taker_fee",
+                "0.001500000000000000",
+            ),
+            ("params.max_pools", "100"),
+            ("params.enabled", "true"),
+            ("params.pool_creation_fee", "[]"),
+        ] {
+            assert!(
+                check_param_in(&response, &param(key, Some(value))).is_ok(),
+                "{}",
+                key
+            );
+        }
+
+        let error = check_param_in(&response, &param("params.max_pools", Some("200"))).unwrap_err();
+        assert_eq!(error.to_string(), "got 100");
+    }
+
+    #[test]
+    fn checks_only_the_presence_of_params_without_a_value() {
+        let response = json!({ "params": { "taker_fee_params": {} } });
+
+        assert!(check_param_in(&response, &param("params.taker_fee_params", None)).is_ok());
+        for key in [
+            "params.missing",
+            "params.taker_fee_params.missing",
+            "",
+            "params.",
+        ] {
+            let error = check_param_in(&response, &param(key, None)).unwrap_err();
+            assert_eq!(error.to_string(), "not in params", "{}", key);
+        }
+    }
+
+    #[test]
+    fn reads_module_names_of_the_module_versions_query() {
+        let response = json!({
+            "module_versions": [
+                { "name": "bank", "version": "4" },
+                { "version": "1" },
+                { "name": "smartaccount", "version": "1" },
+            ]
+        });
+        assert_eq!(
+            parse_module_names(&response).unwrap(),
+            ["bank", "smartaccount"]
+        );
+
+        assert!(parse_module_names(&json!({ "module_versions": [] }))
+            .unwrap()
+            .is_empty());
+        assert!(parse_module_names(&json!({})).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use std::{collections::BTreeMap, path::PathBuf, process::Command};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{accounts, endpoints::Endpoints, gov, inject, session::Session, spinner};
+
+/// Wasm contracts to deploy once the fork is ready
+#[derive(Args, Debug, Clone, Default)]
+pub struct DeployArgs {
+    /// TOML file listing wasm contracts to store and instantiate once the fork is ready, before
+    /// the on_ready hook runs
+    #[arg(long)]
+    pub deploy_contracts: Option<PathBuf>,
+
+    /// Path to write the code ids and addresses of the deployed contracts to, as JSON
+    #[arg(long, requires = "deploy_contracts")]
+    pub deployed_contracts_output: Option<PathBuf>,
+}
+
+/// Contracts file, with `[[contracts]]` tables deployed in order
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Key in the test keyring to store and instantiate with
+    pub from: String,
+    pub contracts: Vec<Contract>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Contract {
+    pub name: String,
+    /// Wasm artifact, relative to the contracts file
+    pub wasm: PathBuf,
+    /// Instantiate message, as a TOML table or a JSON string
+    pub init_msg: Value,
+    /// Label of the instance, defaults to `name`
+    pub label: Option<String>,
+    /// Admin allowed to migrate the instance, none by default
+    pub admin: Option<String>,
+    /// Coins to send along with the instantiation
+    pub funds: Option<String>,
+}
+
+/// Code id and address of a deployed contract
+#[derive(Serialize, Debug)]
+pub struct Deployed {
+    pub code_id: u64,
+    pub address: String,
+}
+
+/// Contracts to deploy and where to write the result
+#[derive(Debug, Clone)]
+pub struct Deployment {
+    manifest: Manifest,
+    output: Option<PathBuf>,
+    gas_prices: String,
+}
+
+impl DeployArgs {
+    /// Contracts to deploy from --deploy-contracts, `None` when not given. Loaded upfront so a bad
+    /// file fails before the fork is started.
+    pub fn resolve(&self, gas_prices: String) -> Result<Option<Deployment>> {
+        let Some(path) = &self.deploy_contracts else {
+            return Ok(None);
+        };
+
+        Ok(Some(Deployment {
+            manifest: load(path)?,
+            output: self.deployed_contracts_output.clone(),
+            gas_prices,
+        }))
+    }
+}
+
+pub fn load(path: &PathBuf) -> Result<Manifest> {
+    let content = std::fs::read_to_string(path)
+        .wrap_err(format!("Failed to read contracts file: {}", path.display()))?;
+    let mut manifest: Manifest = toml::from_str(&content).wrap_err(format!(
+        "Failed to parse contracts file: {}",
+        path.display()
+    ))?;
+
+    let dir = path.parent().map(PathBuf::from).unwrap_or_default();
+    for contract in &mut manifest.contracts {
+        contract.wasm = dir.join(&contract.wasm);
+        if !contract.wasm.exists() {
+            return Err(eyre!(
+                "Wasm artifact of {} not found: {}",
+                contract.name,
+                contract.wasm.display()
+            ));
+        }
+    }
+
+    Ok(manifest)
+}
+
+impl Deployment {
+    /// Store and instantiate the contracts in order, printing and writing out where they ended up
+    pub async fn deploy(
+        &self,
+        osmosisd: &PathBuf,
+        osmosis_home: &PathBuf,
+        session: &Session,
+    ) -> Result<()> {
+        let from = &self.manifest.from;
+        allow_upload(osmosisd, osmosis_home, from, &self.gas_prices, session).await?;
+
+        let mut deployed = BTreeMap::new();
+        for contract in &self.manifest.contracts {
+            let code_id = spinner! {
+                session.events,
+                &format!("Storing {}...", contract.name),
+                &format!("✓ Stored {}.", contract.name),
+                store(osmosisd, osmosis_home, from, contract, &self.gas_prices, &session.endpoints)
+                    .await?
+            };
+
+            let address = spinner! {
+                session.events,
+                &format!("Instantiating {} from code {}...", contract.name, code_id),
+                &format!("✓ Instantiated {}.", contract.name),
+                instantiate(
+                    osmosisd,
+                    osmosis_home,
+                    from,
+                    contract,
+                    code_id,
+                    &self.gas_prices,
+                    &session.endpoints,
+                )
+                .await?
+            };
+
+            println!(
+                "{:<30} code {:>6} {}",
+                contract.name.green(),
+                code_id,
+                address
+            );
+            deployed.insert(contract.name.clone(), Deployed { code_id, address });
+        }
+
+        if let Some(output) = &self.output {
+            std::fs::write(output, serde_json::to_string_pretty(&deployed)?).wrap_err(format!(
+                "Failed to write deployed contracts: {}",
+                output.display()
+            ))?;
+            println!(
+                "{}",
+                format!("✓ Saved deployed contracts to {}.", output.display()).green()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Mainnet only lets allowlisted addresses upload code, open uploads to everybody through a gov
+/// proposal passed by `from` when it isn't one of them
+async fn allow_upload(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    from: &str,
+    gas_prices: &str,
+    session: &Session,
+) -> Result<()> {
+    let output = Command::new(osmosisd)
+        .args(["query", "wasm", "params"])
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--node", &session.endpoints.rpc_addr()])
+        .args(["--output", "json"])
+        .output()
+        .wrap_err("Failed to query wasm params")?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to query wasm params: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let response: Value =
+        serde_json::from_slice(&output.stdout).wrap_err("Failed to parse wasm params")?;
+    let mut params = response.get("params").cloned().unwrap_or(response);
+
+    let address = accounts::key_address(osmosisd, osmosis_home, from)?;
+    let access = &params["code_upload_access"];
+    let allowed = match access["permission"].as_str().unwrap_or_default() {
+        "Everybody" | "ACCESS_TYPE_EVERYBODY" => true,
+        _ => access["addresses"]
+            .as_array()
+            .is_some_and(|addresses| addresses.iter().any(|a| a.as_str() == Some(&address))),
+    };
+    if allowed {
+        return Ok(());
+    }
+
+    params["code_upload_access"] = json!({ "permission": "Everybody", "addresses": [] });
+    let proposal = gov::Proposal::Messages {
+        title: "Allow code upload".to_string(),
+        messages: vec![json!({
+            "@type": "/cosmwasm.wasm.v1.MsgUpdateParams",
+            "authority": gov::GOV_AUTHORITY,
+            "params": params,
+        })],
+    };
+    gov::pass(osmosisd, osmosis_home, &proposal, from, gas_prices, session)
+        .await
+        .wrap_err(format!(
+            "Failed to open code upload, {} needs the validator's voting power to pass it",
+            from
+        ))?;
+    Ok(())
+}
+
+async fn store(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    from: &str,
+    contract: &Contract,
+    gas_prices: &str,
+    endpoints: &Endpoints,
+) -> Result<u64> {
+    let txhash = inject::send(
+        osmosisd,
+        osmosis_home,
+        from,
+        [
+            "wasm".to_string(),
+            "store".to_string(),
+            contract.wasm.display().to_string(),
+        ]
+        .into_iter(),
+        gas_prices,
+        endpoints,
+    )
+    .wrap_err(format!("Failed to store {}", contract.name))?;
+    accounts::wait_for_tx(osmosisd, osmosis_home, &txhash, endpoints).await?;
+
+    accounts::tx_attribute(osmosisd, osmosis_home, &txhash, "code_id", endpoints)?
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| eyre!("No code id in the events of tx {}", txhash))
+}
+
+async fn instantiate(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    from: &str,
+    contract: &Contract,
+    code_id: u64,
+    gas_prices: &str,
+    endpoints: &Endpoints,
+) -> Result<String> {
+    let init_msg = match &contract.init_msg {
+        Value::String(msg) => msg.clone(),
+        msg => msg.to_string(),
+    };
+    let mut args = vec![
+        "wasm".to_string(),
+        "instantiate".to_string(),
+        code_id.to_string(),
+        init_msg,
+        "--label".to_string(),
+        contract
+            .label
+            .clone()
+            .unwrap_or_else(|| contract.name.clone()),
+    ];
+    match &contract.admin {
+        Some(admin) => args.extend(["--admin".to_string(), admin.clone()]),
+        None => args.push("--no-admin".to_string()),
+    }
+    if let Some(funds) = &contract.funds {
+        args.extend(["--amount".to_string(), funds.clone()]);
+    }
+
+    let txhash = inject::send(
+        osmosisd,
+        osmosis_home,
+        from,
+        args.into_iter(),
+        gas_prices,
+        endpoints,
+    )
+    .wrap_err(format!("Failed to instantiate {}", contract.name))?;
+    accounts::wait_for_tx(osmosisd, osmosis_home, &txhash, endpoints).await?;
+
+    accounts::tx_attribute(
+        osmosisd,
+        osmosis_home,
+        &txhash,
+        "_contract_address",
+        endpoints,
+    )?
+    .ok_or_else(|| eyre!("No contract address in the events of tx {}", txhash))
+}
@@ -0,0 +1,120 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use clap::{ArgAction, Args};
+use color_eyre::eyre::{eyre, Context, Result};
+
+use crate::platform;
+
+/// Run the node through cosmovisor
+#[derive(Args, Debug, Clone, Default)]
+pub struct CosmovisorArgs {
+    /// Lay the binaries out in the home's cosmovisor directory, as `genesis/bin` and
+    /// `upgrades/<name>/bin`, and run the node through cosmovisor so it switches binaries at the
+    /// upgrade height the way validators do
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true",
+        requires_all = ["upgrade_handler", "new_osmosisd_bin"]
+    )]
+    pub cosmovisor: bool,
+}
+
+fn dir(osmosis_home: &Path) -> PathBuf {
+    osmosis_home.join("cosmovisor")
+}
+
+fn upgrade_dir(osmosis_home: &Path, name: &str) -> PathBuf {
+    // cosmovisor looks upgrades up by their lowercased name
+    dir(osmosis_home).join("upgrades").join(name.to_lowercase())
+}
+
+/// Link `genesis` and each of `upgrades` into the cosmovisor layout of `osmosis_home`, with
+/// `current` on `genesis`, or on the first upgrade when the node already halted for it
+pub fn layout(
+    osmosis_home: &Path,
+    genesis: Option<&Path>,
+    upgrades: &[(String, PathBuf)],
+) -> Result<()> {
+    if which::which("cosmovisor").is_err() {
+        return Err(eyre!(
+            "cosmovisor not found in PATH, install it with `go install cosmossdk.io/tools/cosmovisor/cmd/cosmovisor@latest`"
+        ));
+    }
+
+    let genesis_dir = dir(osmosis_home).join("genesis");
+    if let Some(genesis) = genesis {
+        link(genesis, &genesis_dir)?;
+    }
+    for (name, binary) in upgrades {
+        link(binary, &upgrade_dir(osmosis_home, name))?;
+    }
+
+    let current = match (genesis, upgrades.first()) {
+        (Some(_), _) => genesis_dir,
+        (None, Some((name, _))) => upgrade_dir(osmosis_home, name),
+        (None, None) => return Err(eyre!("No binary to lay out for cosmovisor")),
+    };
+    let current_link = dir(osmosis_home).join("current");
+    let _ = std::fs::remove_file(&current_link);
+    platform::symlink(&current, &current_link)
+        .wrap_err(format!("Failed to link {}", current_link.display()))?;
+    Ok(())
+}
+
+/// Link `binary` as `<dir>/bin/osmosisd`
+fn link(binary: &Path, dir: &Path) -> Result<()> {
+    let binary = which::which(binary).wrap_err(format!("{} not found", binary.display()))?;
+    let bin_dir = dir.join("bin");
+    std::fs::create_dir_all(&bin_dir)
+        .wrap_err(format!("Failed to create {}", bin_dir.display()))?;
+
+    let path = bin_dir.join("osmosisd");
+    let _ = std::fs::remove_file(&path);
+    platform::symlink(&std::fs::canonicalize(&binary)?, &path)
+        .wrap_err(format!("Failed to link {}", path.display()))
+}
+
+/// Command running `osmosisd`, through cosmovisor when the run laid the home out for it.
+/// Cosmovisor exits after switching binaries instead of restarting the node, so the next phase
+/// starts it with its own arguments.
+pub fn command(osmosisd: &Path, osmosis_home: &Path, cosmovisor: bool) -> Command {
+    match cosmovisor {
+        true => run(osmosis_home),
+        false => Command::new(osmosisd),
+    }
+}
+
+/// `cosmovisor run`, configured to manage the binaries of `osmosis_home`
+pub fn run(osmosis_home: &Path) -> Command {
+    let mut cmd = Command::new("cosmovisor");
+    cmd.arg("run")
+        .env("DAEMON_NAME", "osmosisd")
+        .env("DAEMON_HOME", osmosis_home)
+        .env("DAEMON_RESTART_AFTER_UPGRADE", "false")
+        .env("DAEMON_ALLOW_DOWNLOAD_BINARIES", "false")
+        // backing up the mainnet state before every upgrade would take ages
+        .env("UNSAFE_SKIP_BACKUP", "true");
+    cmd
+}
+
+/// Fail unless cosmovisor switched `current` to `osmosisd`
+pub fn check_current(osmosis_home: &Path, osmosisd: &Path) -> Result<()> {
+    let current = std::fs::canonicalize(dir(osmosis_home).join("current/bin/osmosisd"))
+        .wrap_err("Failed to read the current cosmovisor binary")?;
+    let expected = std::fs::canonicalize(which::which(osmosisd)?)?;
+    if current != expected {
+        return Err(eyre!(
+            "cosmovisor runs {} instead of switching to {}",
+            current.display(),
+            expected.display()
+        ));
+    }
+    Ok(())
+}
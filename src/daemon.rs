@@ -0,0 +1,255 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::{du, platform, readiness, session::Session, upgrade};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Extra time `stop` gives the instance on top of --shutdown-timeout-secs before killing it
+const STOP_GRACE: Duration = Duration::from_secs(10);
+
+/// Directory next to `osmosis_home` with the pid and log file of a detached instance. It's kept
+/// out of the home itself as that gets wiped when downloading or restoring the state.
+fn dir(osmosis_home: &Path) -> PathBuf {
+    let name = osmosis_home
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    osmosis_home.with_file_name(format!("{}.daemon", name))
+}
+
+fn pid_file(osmosis_home: &Path) -> PathBuf {
+    dir(osmosis_home).join("osmoinplace.pid")
+}
+
+pub fn log_file(osmosis_home: &Path) -> PathBuf {
+    dir(osmosis_home).join("osmoinplace.log")
+}
+
+/// Pid of the detached instance for `osmosis_home` if it's still running, a stale pid file is
+/// removed
+fn running(osmosis_home: &Path) -> Result<Option<u32>> {
+    let pid_file = pid_file(osmosis_home);
+    let Ok(content) = std::fs::read_to_string(&pid_file) else {
+        return Ok(None);
+    };
+    let pid = content
+        .trim()
+        .parse::<u32>()
+        .wrap_err(format!("Invalid pid file: {}", pid_file.display()))?;
+
+    if platform::is_alive(pid) {
+        return Ok(Some(pid));
+    }
+    std::fs::remove_file(&pid_file).ok();
+    Ok(None)
+}
+
+/// Run this invocation again without --detach in the background, with its output going to the
+/// log file next to `osmosis_home`
+pub fn detach(osmosis_home: &Path) -> Result<()> {
+    if let Some(pid) = running(osmosis_home)? {
+        return Err(eyre!(
+            "osmoinplace is already running for {} (pid {}), stop it with `osmoinplace stop`",
+            osmosis_home.display(),
+            pid
+        ));
+    }
+
+    std::fs::create_dir_all(dir(osmosis_home))?;
+    let log_file = log_file(osmosis_home);
+    let log = File::create(&log_file)
+        .wrap_err(format!("Failed to create log file: {}", log_file.display()))?;
+
+    let exe = std::env::current_exe().wrap_err("Failed to locate osmoinplace executable")?;
+    let mut cmd = Command::new(exe);
+    // however --detach was given, the background copy must not detach again
+    cmd.args(
+        std::env::args()
+            .skip(1)
+            .filter(|arg| arg != "--detach" && !arg.starts_with("--detach=")),
+    );
+    for (name, _) in std::env::vars_os() {
+        let name = name.to_string_lossy();
+        if name.starts_with("OSMOINPLACE_") && name.ends_with("_DETACH") {
+            cmd.env_remove(name.as_ref());
+        }
+    }
+    // the mock marks this process for the osmosisd it spawns, the copy must run as osmoinplace
+    #[cfg(feature = "mock")]
+    cmd.env_remove(crate::mock::MOCK_ENV);
+    cmd.stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    // keep Ctrl-C and hangups of this terminal from reaching it
+    platform::detach_from_terminal(&mut cmd);
+    let child = cmd
+        .spawn()
+        .wrap_err("Failed to start detached osmoinplace")?;
+
+    std::fs::write(pid_file(osmosis_home), child.id().to_string())?;
+
+    println!(
+        "{}",
+        format!(
+            "Started osmoinplace in the background (pid {}).",
+            child.id()
+        )
+        .green()
+    );
+    println!("Logs: {}", log_file.display());
+    println!("Follow them with `osmoinplace logs -f`, stop it with `osmoinplace stop`.");
+    Ok(())
+}
+
+/// Stop the detached instance cleanly, it forwards the signal to osmosisd. It's killed if it
+/// doesn't exit within `shutdown_timeout` plus some grace time.
+pub fn stop(osmosis_home: &Path, shutdown_timeout: Option<Duration>) -> Result<()> {
+    let Some(pid) = running(osmosis_home)? else {
+        println!("osmoinplace is not running for {}", osmosis_home.display());
+        return Ok(());
+    };
+
+    println!(
+        "{}",
+        format!("Stopping osmoinplace (pid {})...", pid).cyan()
+    );
+    // pid is the detached instance recorded in the pid file, checked to be alive
+    platform::terminate(pid);
+
+    let deadline = shutdown_timeout.map(|timeout| Instant::now() + timeout + STOP_GRACE);
+    while platform::is_alive(pid) {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            println!(
+                "{}",
+                format!("osmoinplace (pid {}) didn't stop in time, killing it", pid).yellow()
+            );
+            platform::kill(pid);
+            break;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    std::fs::remove_file(pid_file(osmosis_home)).ok();
+    println!("{}", "✓ Stopped osmoinplace.".green());
+    Ok(())
+}
+
+/// State of the node for a home, fields are `None` when unknown
+#[derive(Serialize, Debug)]
+struct Report {
+    /// Whether the node answers on its RPC
+    running: bool,
+    /// Pid of the instance started with --detach
+    pid: Option<u32>,
+    uptime_secs: Option<u64>,
+    chain_id: Option<String>,
+    height: Option<u64>,
+    catching_up: Option<bool>,
+    osmosisd_version: Option<String>,
+    rpc: String,
+    grpc: String,
+    api: String,
+}
+
+/// Print whether a node is running for the home, with its height, sync state, version and
+/// endpoints. The version is the running node's, or else the one of `osmosisd`. With
+/// `--output json` the report goes to stdout as a single JSON object instead.
+pub async fn status(osmosis_home: &Path, osmosisd: &PathBuf, session: &Session) -> Result<()> {
+    let pid = running(osmosis_home)?;
+    let uptime = match pid {
+        Some(_) => std::fs::metadata(pid_file(osmosis_home))?
+            .modified()
+            .ok()
+            .and_then(|modified| modified.elapsed().ok()),
+        None => None,
+    };
+    let node = readiness::status(&session.endpoints).await.ok();
+    let osmosisd_version = match readiness::app_version(&session.endpoints).await {
+        Ok(version) => Some(version),
+        Err(_) => upgrade::binary_version(osmosisd).ok(),
+    };
+
+    let report = Report {
+        running: node.is_some(),
+        pid,
+        uptime_secs: uptime.map(|uptime| uptime.as_secs()),
+        chain_id: node.as_ref().map(|node| node.chain_id.clone()),
+        height: node.as_ref().map(|node| node.latest_block_height),
+        catching_up: node.as_ref().map(|node| node.catching_up),
+        osmosisd_version,
+        rpc: session.endpoints.rpc_addr(),
+        grpc: session.endpoints.grpc_addr(),
+        api: session.endpoints.api_addr(),
+    };
+
+    match session.events.json() {
+        true => session.events.report(&report)?,
+        false => print_report(osmosis_home, &report, uptime),
+    }
+    Ok(())
+}
+
+fn print_report(osmosis_home: &Path, report: &Report, uptime: Option<Duration>) {
+    match report.pid {
+        Some(pid) => {
+            println!("{:<12} running (pid {})", "osmoinplace", pid);
+            println!("{:<12} {}", "started", du::format_age(uptime));
+        }
+        None => println!(
+            "{:<12} not running detached for {}",
+            "osmoinplace",
+            osmosis_home.display()
+        ),
+    }
+
+    match (&report.chain_id, report.height, report.catching_up) {
+        (Some(chain_id), Some(height), Some(catching_up)) => {
+            println!("{:<12} {}", "chain id", chain_id);
+            println!("{:<12} {}", "height", height);
+            println!("{:<12} {}", "catching up", catching_up);
+        }
+        _ => println!("{:<12} not reachable", "node rpc"),
+    }
+    if let Some(version) = &report.osmosisd_version {
+        println!("{:<12} {}", "osmosisd", version);
+    }
+    println!("{:<12} {}", "rpc", report.rpc);
+    println!("{:<12} {}", "grpc", report.grpc);
+    println!("{:<12} {}", "api", report.api);
+}
+
+/// Print the log of the detached instance, with `follow` keep printing what's appended to it
+pub fn logs(osmosis_home: &Path, follow: bool) -> Result<()> {
+    let log_file = log_file(osmosis_home);
+    let mut file =
+        File::open(&log_file).wrap_err(format!("No log file at {}", log_file.display()))?;
+
+    let mut stdout = std::io::stdout();
+    loop {
+        let mut chunk = vec![];
+        file.read_to_end(&mut chunk)?;
+        stdout.write_all(&chunk)?;
+        stdout.flush()?;
+
+        if !follow {
+            return Ok(());
+        }
+        // start over when a new detached run recreated the log file
+        if std::fs::metadata(&log_file)
+            .is_ok_and(|meta| meta.len() < file.stream_position().unwrap_or(0))
+        {
+            file = File::open(&log_file)?;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::eyre::{Context, Result};
+use colored::Colorize;
+
+use crate::{backup, endpoints::Endpoints, readiness, upgrade};
+
+/// Node log lines kept to go into a diagnostics bundle
+pub const RECENT_LINES: usize = 1000;
+
+/// Consensus failure of the node to collect diagnostics about
+pub struct Crash<'a> {
+    pub osmosisd: &'a PathBuf,
+    pub osmosis_home: &'a Path,
+    pub upgrade_handler: Option<&'a str>,
+    /// Log line reporting the failure
+    pub failure: &'a str,
+    pub recent_lines: Vec<String>,
+    pub endpoints: &'a Endpoints,
+}
+
+/// Whether consensus `failure` is the halt for a scheduled upgrade rather than a crash
+pub fn is_upgrade_halt(failure: &str) -> bool {
+    failure.contains("UPGRADE") && failure.contains("NEEDED")
+}
+
+/// Collect a diagnostics bundle for `crash` to attach to a bug report, only warning if that fails
+/// as the failure itself is what matters
+pub async fn report(crash: Crash<'_>) {
+    match collect(crash).await {
+        Ok(bundle) => println!(
+            "{} {}",
+            "Consensus failure, diagnostics collected in".yellow(),
+            bundle.display()
+        ),
+        Err(e) => println!(
+            "{}",
+            format!("Failed to collect diagnostics: {:?}", e).yellow()
+        ),
+    }
+}
+
+/// Pack the last log lines, a summary of the node and its config into a timestamped tarball in the
+/// current directory
+async fn collect(crash: Crash<'_>) -> Result<PathBuf> {
+    // the node keeps running after a consensus failure, so its RPC usually still answers
+    let height = match readiness::status(crash.endpoints).await {
+        Ok(status) => Some(status.latest_block_height),
+        Err(_) => backup::height(crash.osmosis_home),
+    };
+    let collected = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let summary = serde_json::json!({
+        "failure": crash.failure,
+        "height": height,
+        "osmosisd": crash.osmosisd,
+        "osmosisd_version": upgrade::binary_version(crash.osmosisd).ok(),
+        "upgrade_handler": crash.upgrade_handler,
+        "osmosis_home": crash.osmosis_home,
+        "collected": collected,
+    });
+
+    let bundle = PathBuf::from(format!("osmoinplace-diagnostics-{}.tar.zst", collected));
+    let file = File::create(&bundle).wrap_err(format!(
+        "Failed to create diagnostics bundle: {}",
+        bundle.display()
+    ))?;
+    let mut tar = tar::Builder::new(zstd::Encoder::new(file, 0)?.auto_finish());
+
+    append(
+        &mut tar,
+        "summary.json",
+        &serde_json::to_string_pretty(&summary)?,
+    )?;
+    append(&mut tar, "node.log", &crash.recent_lines.join("\n"))?;
+    if let Some(stack_trace) = stack_trace(&crash.recent_lines) {
+        append(&mut tar, "stack_trace.txt", &stack_trace)?;
+    }
+    for config in ["app.toml", "config.toml"] {
+        let path = crash.osmosis_home.join("config").join(config);
+        if path.exists() {
+            tar.append_path_with_name(&path, format!("config/{}", config))?;
+        }
+    }
+    tar.into_inner()?;
+
+    Ok(bundle)
+}
+
+fn append<W: std::io::Write>(tar: &mut tar::Builder<W>, name: &str, content: &str) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tar.append_data(&mut header, name, content.as_bytes())?;
+    Ok(())
+}
+
+/// Go panic in the log lines, from the panic message to the end of the goroutine dumps
+fn stack_trace(lines: &[String]) -> Option<String> {
+    let start = lines
+        .iter()
+        .position(|line| line.starts_with("panic:") || line.starts_with("goroutine "))?;
+    Some(lines[start..].join("\n"))
+}
@@ -0,0 +1,128 @@
+use std::path::Path;
+#[cfg(unix)]
+use std::process::Command;
+
+use color_eyre::eyre::{eyre, Context, Result};
+
+/// Available space in bytes on the filesystem containing `path` (or its closest existing ancestor)
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| eyre!("No existing ancestor of {}", path.display()))?;
+
+    let output = Command::new("df")
+        .arg("-Pk")
+        .arg(existing)
+        .output()
+        .wrap_err("Failed to run df")?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to query available space of {}: {}",
+            existing.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    // Filesystem 1024-blocks Used Available Capacity Mounted on
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available_kb = stdout
+        .lines()
+        .nth(1)
+        .and_then(|line| line.split_whitespace().nth(3))
+        .ok_or_else(|| eyre!("Unexpected df output: {}", stdout))?
+        .parse::<u64>()
+        .wrap_err("Failed to parse df output")?;
+
+    Ok(available_kb * 1024)
+}
+
+/// Disk usage in bytes of `path`, 0 if it doesn't exist
+#[cfg(unix)]
+pub fn usage(path: &Path) -> Result<u64> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let output = Command::new("du")
+        .arg("-sk")
+        .arg(path)
+        .output()
+        .wrap_err("Failed to run du")?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to query disk usage of {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    // <kb>\t<path>
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let used_kb = stdout
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| eyre!("Unexpected du output: {}", stdout))?
+        .parse::<u64>()
+        .wrap_err("Failed to parse du output")?;
+
+    Ok(used_kb * 1024)
+}
+
+#[cfg(windows)]
+pub fn available_space(path: &Path) -> Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| eyre!("No existing ancestor of {}", path.display()))?;
+    let wide = existing
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect::<Vec<u16>>();
+
+    let mut available = 0;
+    // SAFETY: `wide` is a nul terminated path and the null pointers are optional outputs
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error()).wrap_err(format!(
+            "Failed to query available space of {}",
+            existing.display()
+        ));
+    }
+    Ok(available)
+}
+
+/// Windows has no `du`, so sizes of the files under `path` are added up instead
+#[cfg(windows)]
+pub fn usage(path: &Path) -> Result<u64> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => {
+            return Err(e).wrap_err(format!("Failed to query disk usage of {}", path.display()))
+        }
+    };
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        total += usage(&entry?.path())?;
+    }
+    Ok(total)
+}
@@ -0,0 +1,155 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use color_eyre::eyre::Result;
+use colored::Colorize;
+
+use crate::{disk, platform, snapshot};
+
+const GB: f64 = 1e9;
+
+/// Backups older than this are suggested to be refreshed or removed
+const STALE_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Where osmoinplace keeps state, with the paths used by default by each command
+fn workspaces(osmosis_home: &Path) -> Vec<(&'static str, PathBuf)> {
+    let home = platform::home_dir();
+    vec![
+        ("home", osmosis_home.to_path_buf()),
+        ("backup", home.join(".osmosisd_bak")),
+        ("follower", home.join(".osmosisd_follower")),
+        ("named backups", crate::backup::root()),
+        ("follower backup", home.join(".osmosisd_follower_bak")),
+    ]
+}
+
+/// Components of a node home, `data` excludes the tx index
+fn components(workspace: &Path) -> Result<Vec<(&'static str, u64)>> {
+    let data = disk::usage(&workspace.join("data"))?;
+    let tx_index = disk::usage(&workspace.join("data").join("tx_index.db"))?;
+    let wasm = disk::usage(&workspace.join("wasm"))?;
+
+    let mut logs = 0;
+    if workspace.exists() {
+        for entry in std::fs::read_dir(workspace)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "log") {
+                logs += path.metadata()?.len();
+            }
+        }
+    }
+
+    Ok(vec![
+        ("data", data.saturating_sub(tx_index)),
+        ("tx_index", tx_index),
+        ("wasm", wasm),
+        ("logs", logs),
+    ])
+}
+
+fn age(path: &Path) -> Option<Duration> {
+    let modified = path.metadata().ok()?.modified().ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+pub fn format_age(age: Option<Duration>) -> String {
+    match age {
+        Some(age) if age.as_secs() >= 24 * 60 * 60 => {
+            format!("{}d ago", age.as_secs() / (24 * 60 * 60))
+        }
+        Some(age) => format!("{}h ago", age.as_secs() / (60 * 60)),
+        None => "-".to_string(),
+    }
+}
+
+/// Print disk usage by workspace, component and age, with suggestions for what to prune
+pub fn report(osmosis_home: &Path) -> Result<()> {
+    let mut suggestions = vec![];
+    let mut total = 0;
+
+    for (name, path) in workspaces(osmosis_home) {
+        if !path.exists() {
+            continue;
+        }
+
+        let size = disk::usage(&path)?;
+        total += size;
+        let workspace_age = age(&path);
+        println!(
+            "{:<16} {:>8.1} GB  {:>8}  {}",
+            name.bold(),
+            size as f64 / GB,
+            format_age(workspace_age),
+            path.display()
+        );
+
+        for (component, size) in components(&path)? {
+            if size > 0 {
+                println!("  {:<14} {:>8.1} GB", component, size as f64 / GB);
+            }
+            if component == "tx_index" && size as f64 > 50.0 * GB {
+                suggestions.push(format!(
+                    "The tx index of {} takes {:.1} GB, disable it with `tx_index.indexer = \"null\"` in config.toml if you don't query txs",
+                    path.display(),
+                    size as f64 / GB
+                ));
+            }
+        }
+
+        if name.contains("backup") && workspace_age.is_some_and(|age| age > STALE_AGE) {
+            suggestions.push(format!(
+                "{} is {}, refresh it with `osmoinplace backup` or remove it",
+                path.display(),
+                format_age(workspace_age)
+            ));
+        }
+    }
+
+    let cache = snapshot::cache_dir();
+    let cache_size = disk::usage(&cache)?;
+    let downloads = cache.with_file_name("downloads");
+    let downloads_size = disk::usage(&downloads)?;
+    total += cache_size + downloads_size;
+
+    println!(
+        "{:<16} {:>8.1} GB",
+        "caches".bold(),
+        (cache_size + downloads_size) as f64 / GB
+    );
+    if cache_size > 0 {
+        println!(
+            "  {:<14} {:>8.1} GB  {}",
+            "snapshots",
+            cache_size as f64 / GB,
+            cache.display()
+        );
+        suggestions
+            .push("Remove cached snapshot archives with `osmoinplace cache clean`".to_string());
+    }
+    if downloads_size > 0 {
+        println!(
+            "  {:<14} {:>8.1} GB  {}",
+            "downloads",
+            downloads_size as f64 / GB,
+            downloads.display()
+        );
+        suggestions.push(format!(
+            "Partial downloads in {} are only needed to --resume, remove them otherwise",
+            downloads.display()
+        ));
+    }
+
+    println!("{:<16} {:>8.1} GB", "total".bold(), total as f64 / GB);
+
+    if !suggestions.is_empty() {
+        println!();
+        println!("{}", "Suggestions:".cyan());
+        for suggestion in suggestions {
+            println!("  - {}", suggestion);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Context, Result};
+use colored::Colorize;
+
+use crate::{
+    node_config::{self, ConfigFile},
+    readiness,
+};
+
+pub const DEFAULT_CHAIN_ID: &str = "edgenet";
+const RPC_PORT: u16 = 26657;
+const P2P_PORT: u16 = 26656;
+const ABCI_PORT: u16 = 26658;
+const GRPC_PORT: u16 = 9090;
+const API_PORT: u16 = 1317;
+const PPROF_PORT: u16 = 6060;
+
+/// Chain id and listen addresses of a fork. Each run has its own, so several forks can be
+/// driven from one process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoints {
+    chain_id: String,
+    /// Added to every port of the node, `None` leaves the ports in the home's config alone
+    port_offset: Option<u16>,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints::new(DEFAULT_CHAIN_ID, None)
+    }
+}
+
+/// Largest offset that keeps every port of the node valid, ABCI's being the highest
+pub const MAX_PORT_OFFSET: u16 = u16::MAX - ABCI_PORT;
+
+/// Parse a --port-offset, rejecting one that would shift a port past 65535
+pub fn parse_port_offset(offset: &str) -> Result<u16, String> {
+    let offset = offset
+        .parse::<u16>()
+        .map_err(|e| format!("invalid port offset {:?}: {}", offset, e))?;
+    match offset <= MAX_PORT_OFFSET {
+        true => Ok(offset),
+        false => Err(format!(
+            "port offset {} is too large, the highest is {}",
+            offset, MAX_PORT_OFFSET
+        )),
+    }
+}
+
+impl Endpoints {
+    /// Endpoints of the fork on `chain_id`, with every port of the node shifted by `port_offset`
+    /// so several forks run side by side
+    pub fn new(chain_id: impl Into<String>, port_offset: Option<u16>) -> Endpoints {
+        Endpoints {
+            chain_id: chain_id.into(),
+            port_offset,
+        }
+    }
+
+    /// Use `chain_id` for the fork, or else the one a previous run pointed the home's client.toml
+    /// at, so commands against an existing fork don't need --chain-id repeated. The home is
+    /// unknown for remote runs.
+    pub fn resolve(
+        chain_id: Option<&str>,
+        osmosis_home: Option<&Path>,
+        port_offset: Option<u16>,
+    ) -> Endpoints {
+        let chain_id = chain_id
+            .map(str::to_string)
+            .or_else(|| {
+                osmosis_home.and_then(|home| node_config::get(home, ConfigFile::Client, "chain-id"))
+            })
+            // `osmosisd init` leaves it empty
+            .filter(|chain_id| !chain_id.is_empty())
+            .unwrap_or_else(|| DEFAULT_CHAIN_ID.to_string());
+        Endpoints::new(chain_id, port_offset)
+    }
+
+    /// Chain id of the fork
+    pub fn chain_id(&self) -> &str {
+        &self.chain_id
+    }
+
+    pub fn port_offset(&self) -> Option<u16> {
+        self.port_offset
+    }
+
+    fn port(&self, base: u16) -> u16 {
+        base.checked_add(self.port_offset.unwrap_or(0))
+            .expect("port offset is checked to be at most MAX_PORT_OFFSET")
+    }
+
+    pub fn rpc_addr(&self) -> String {
+        format!("tcp://127.0.0.1:{}", self.port(RPC_PORT))
+    }
+
+    /// RPC address as an http URL, e.g. to query it
+    pub fn rpc_url(&self) -> String {
+        self.rpc_addr().replacen("tcp://", "http://", 1)
+    }
+
+    pub fn grpc_addr(&self) -> String {
+        format!("127.0.0.1:{}", self.port(GRPC_PORT))
+    }
+
+    pub fn api_addr(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port(API_PORT))
+    }
+
+    /// Rewrite the listen addresses in the home's config.toml and app.toml to the offset ports, a
+    /// no-op without --port-offset or --name. Run before each start as the home may have been
+    /// replaced since.
+    pub fn configure_ports(&self, osmosis_home: &Path) -> Result<()> {
+        if self.port_offset.is_none() {
+            return Ok(());
+        }
+
+        for (file, key, addr) in [
+            (ConfigFile::Config, "rpc.laddr", self.rpc_addr()),
+            (
+                ConfigFile::Config,
+                "p2p.laddr",
+                format!("tcp://0.0.0.0:{}", self.port(P2P_PORT)),
+            ),
+            (
+                ConfigFile::Config,
+                "proxy_app",
+                format!("tcp://127.0.0.1:{}", self.port(ABCI_PORT)),
+            ),
+            (
+                ConfigFile::Config,
+                "rpc.pprof_laddr",
+                format!("localhost:{}", self.port(PPROF_PORT)),
+            ),
+            (
+                ConfigFile::App,
+                "grpc.address",
+                format!("localhost:{}", self.port(GRPC_PORT)),
+            ),
+            (
+                ConfigFile::App,
+                "api.address",
+                format!("tcp://localhost:{}", self.port(API_PORT)),
+            ),
+        ] {
+            node_config::set(osmosis_home, file, key, addr)?;
+        }
+
+        Ok(())
+    }
+
+    /// Point the home's client.toml at the fork so osmosisd CLI commands work without flags
+    pub fn write_client_config(&self, osmosis_home: &Path) -> Result<()> {
+        node_config::set(
+            osmosis_home,
+            ConfigFile::Client,
+            "chain-id",
+            self.chain_id(),
+        )?;
+        node_config::set(osmosis_home, ConfigFile::Client, "node", self.rpc_addr())?;
+        node_config::set(osmosis_home, ConfigFile::Client, "keyring-backend", "test")?;
+
+        Ok(())
+    }
+
+    /// `KEY=value` lines describing the fork, usable both as a docker-compose env file and from a
+    /// shell with `set -a; . <file>; set +a`. `OSMOSISD_*` variables are picked up by osmosisd as
+    /// defaults for the corresponding flags.
+    pub fn env(&self, osmosis_home: &Path) -> String {
+        self.osmosisd_env(osmosis_home)
+            .into_iter()
+            .chain([
+                ("OSMOSIS_RPC", self.rpc_addr()),
+                ("OSMOSIS_GRPC", self.grpc_addr()),
+                ("OSMOSIS_API", self.api_addr()),
+            ])
+            .map(|(key, value)| format!("{}={}\n", key, value))
+            .collect()
+    }
+
+    /// Defaults osmosisd takes for its `--home`, `--chain-id`, `--node` and `--keyring-backend`
+    /// flags, only applied to the commands that have them
+    pub fn osmosisd_env(&self, osmosis_home: &Path) -> Vec<(&'static str, String)> {
+        vec![
+            ("OSMOSISD_HOME", osmosis_home.display().to_string()),
+            ("OSMOSISD_CHAIN_ID", self.chain_id().to_string()),
+            ("OSMOSISD_NODE", self.rpc_addr()),
+            ("OSMOSISD_KEYRING_BACKEND", "test".to_string()),
+        ]
+    }
+
+    /// Environment of hook commands, so scripts don't hardcode endpoints. The home is unknown for
+    /// hooks of remote runs, the height when the node's RPC doesn't answer.
+    pub fn hook_env(
+        &self,
+        osmosis_home: Option<&Path>,
+        height: Option<u64>,
+    ) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("OSMOINPLACE_RPC", self.rpc_url()),
+            ("OSMOINPLACE_GRPC", self.grpc_addr()),
+            ("OSMOINPLACE_API", self.api_addr()),
+            ("OSMOINPLACE_CHAIN_ID", self.chain_id().to_string()),
+        ];
+        if let Some(osmosis_home) = osmosis_home {
+            env.push(("OSMOINPLACE_HOME", osmosis_home.display().to_string()));
+        }
+        if let Some(height) = height {
+            env.push(("OSMOINPLACE_HEIGHT", height.to_string()));
+        }
+        env
+    }
+
+    /// Print a box with everything needed to connect to the ready fork
+    pub async fn print_banner(&self, osmosis_home: &Path) {
+        let height = readiness::status(self)
+            .await
+            .map(|status| status.latest_block_height.to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+        let operator = std::fs::read_to_string(operator_file(osmosis_home)).ok();
+
+        let mut rows = vec![
+            ("Chain ID", self.chain_id().to_string()),
+            ("Height", height),
+            ("RPC", self.rpc_url()),
+            ("gRPC", self.grpc_addr()),
+            ("REST", self.api_addr()),
+        ];
+        if let Some(operator) = operator {
+            rows.push(("Whale", operator.trim().to_string()));
+        }
+        rows.push(("Home", osmosis_home.display().to_string()));
+
+        // labels are padded to 10 columns
+        let title = "Fork ready";
+        let width = rows
+            .iter()
+            .map(|(_, value)| 10 + value.len())
+            .chain([title.len()])
+            .max()
+            .unwrap_or_default();
+        let border = "─".repeat(width + 2);
+
+        println!("{}", format!("╭{}╮", border).green());
+        println!(
+            "{} {} {}",
+            "│".green(),
+            format!("{:<width$}", title, width = width).bold(),
+            "│".green()
+        );
+        for (label, value) in rows {
+            println!(
+                "{} {} {:<vwidth$} {}",
+                "│".green(),
+                format!("{:<10}", label).cyan(),
+                value,
+                "│".green(),
+                vwidth = width - 10
+            );
+        }
+        println!("{}", format!("╰{}╯", border).green());
+    }
+}
+
+/// File in the home recording the operator the fork was created with, who holds its voting power
+/// and funds
+fn operator_file(osmosis_home: &Path) -> PathBuf {
+    osmosis_home.join("operator_address")
+}
+
+/// Record `operator_address` for the ready banner
+pub fn write_operator(osmosis_home: &Path, operator_address: &str) -> Result<()> {
+    std::fs::write(operator_file(osmosis_home), operator_address)
+        .wrap_err("Failed to record the operator address")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_port_offsets_up_to_the_highest_port() {
+        assert_eq!(parse_port_offset("0"), Ok(0));
+        assert_eq!(parse_port_offset("100"), Ok(100));
+        assert_eq!(
+            parse_port_offset(&MAX_PORT_OFFSET.to_string()),
+            Ok(MAX_PORT_OFFSET)
+        );
+    }
+
+    #[test]
+    fn rejects_port_offsets_overflowing_a_port() {
+        assert!(parse_port_offset(&(MAX_PORT_OFFSET + 1).to_string()).is_err());
+        assert!(parse_port_offset("70000").is_err());
+        assert!(parse_port_offset("-1").is_err());
+        assert!(parse_port_offset("ten").is_err());
+    }
+}
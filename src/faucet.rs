@@ -0,0 +1,122 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::Mutex,
+};
+
+use crate::{accounts, session::Session, spinner};
+
+/// Key in the test keyring the faucet sends from
+pub struct Faucet {
+    pub osmosisd: PathBuf,
+    pub osmosis_home: PathBuf,
+    pub from: String,
+    pub gas_prices: String,
+    pub session: Session,
+}
+
+impl Faucet {
+    /// Send `amount` to `address` from the faucet key against the local node
+    pub async fn send(&self, address: &str, amount: &str) -> Result<()> {
+        let txhash = spinner! {
+            self.session.events,
+            &format!("Sending {} to {}...", amount, address),
+            &format!("✓ Sent {} to {}.", amount, address),
+            accounts::transfer(
+                &self.osmosisd,
+                &self.osmosis_home,
+                &self.from,
+                address,
+                amount,
+                &self.gas_prices,
+                &self.session.endpoints,
+            )
+            .await?
+        };
+        println!("{}", format!("tx {}", txhash).cyan());
+        Ok(())
+    }
+
+    /// Serve `GET /fund?address=osmo1...` on `addr` until interrupted, sending `amount` to the
+    /// address of every request. Sends go out one at a time so the faucet key's sequence is right.
+    pub async fn serve(self, addr: SocketAddr, amount: String) -> Result<()> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .wrap_err(format!("Failed to bind faucet to {}", addr))?;
+        println!(
+            "{}",
+            format!(
+                "Serving faucet on http://{}/fund?address=<address>, sending {} per request",
+                addr, amount
+            )
+            .cyan()
+        );
+
+        let faucet = Arc::new(Mutex::new(self));
+        let amount = Arc::new(amount);
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                continue;
+            };
+
+            let faucet = faucet.clone();
+            let amount = amount.clone();
+            tokio::spawn(async move {
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(&mut stream);
+                if reader.read_line(&mut request_line).await.is_err() {
+                    return;
+                }
+
+                let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match requested_address(target) {
+                    Ok(address) => match faucet.lock().await.send(&address, &amount).await {
+                        Ok(()) => (
+                            "200 OK",
+                            serde_json::json!({ "address": address, "amount": *amount }),
+                        ),
+                        Err(e) => (
+                            "500 Internal Server Error",
+                            serde_json::json!({ "error": e.to_string() }),
+                        ),
+                    },
+                    Err(e) => (
+                        "400 Bad Request",
+                        serde_json::json!({ "error": e.to_string() }),
+                    ),
+                };
+
+                let body = body.to_string();
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    }
+}
+
+/// Address in the `address` query parameter of a `/fund` request target
+fn requested_address(target: &str) -> Result<String> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != "/fund" {
+        return Err(eyre!("Unknown path {}, use /fund?address=<address>", path));
+    }
+
+    let address = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("address="))
+        .ok_or_else(|| eyre!("Missing address query parameter"))?;
+    if !address.starts_with("osmo1") || !address.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(eyre!("Invalid address: {}", address));
+    }
+
+    Ok(address.to_string())
+}
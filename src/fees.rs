@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use color_eyre::eyre::Result;
+use serde::Deserialize;
+
+use crate::node_config::{self, ConfigFile};
+
+/// Gas prices used by the tx helpers when neither --gas-prices nor --fees-preset is given
+const DEFAULT_GAS_PRICES: &str = "0.025uosmo";
+
+/// Fee settings for the fork, tuned for who is using it
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeesPreset {
+    /// Zero fees and no fee market, for frictionless frontend development
+    Free,
+    /// Low minimum gas price with the adaptive fee market on
+    Realistic,
+    /// Mainnet-like gas prices and fee market
+    Mainnet,
+}
+
+impl FeesPreset {
+    fn min_gas_prices(self) -> &'static str {
+        match self {
+            FeesPreset::Free => "0uosmo",
+            FeesPreset::Realistic => "0.0025uosmo",
+            FeesPreset::Mainnet => "0.025uosmo",
+        }
+    }
+
+    /// Configure the node's minimum gas prices and fee market in app.toml
+    pub fn configure(self, osmosis_home: &Path) -> Result<()> {
+        node_config::set(
+            osmosis_home,
+            ConfigFile::App,
+            "minimum-gas-prices",
+            self.min_gas_prices(),
+        )?;
+        node_config::set(
+            osmosis_home,
+            ConfigFile::App,
+            "osmosis-mempool.adaptive-fee-enabled",
+            self != FeesPreset::Free,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Gas prices for the tx helpers, `explicit` --gas-prices wins over the preset
+pub fn gas_prices(preset: Option<FeesPreset>, explicit: &Option<String>) -> String {
+    explicit.clone().unwrap_or_else(|| {
+        preset
+            .map(FeesPreset::min_gas_prices)
+            .unwrap_or(DEFAULT_GAS_PRICES)
+            .to_string()
+    })
+}
@@ -0,0 +1,102 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{eyre, Result};
+use colored::Colorize;
+
+use crate::{
+    backup, backup::BackupArgs, disk, download_mainnet_state, health, policy::Policy,
+    safe_mode::SafeMode, session::Session, DownloadArgs,
+};
+
+/// Keep an un-forked copy of mainnet state in `workspace` synced, restarting the node when it
+/// crashes and copying the workspace to `snapshot_path` every `snapshot_interval` so it can be
+/// used as a warm source for `restore` / `magic-start --backup-path`.
+#[allow(clippy::too_many_arguments)]
+pub async fn follow_mainnet(
+    osmosisd: &PathBuf,
+    workspace: &PathBuf,
+    snapshot_path: &Path,
+    snapshot_interval: Duration,
+    min_free_bytes: u64,
+    safe_mode: &SafeMode,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    if !workspace.exists() {
+        download_mainnet_state(
+            osmosisd,
+            workspace,
+            &DownloadArgs::default(),
+            safe_mode,
+            policy,
+            session,
+        )
+        .await?;
+    }
+
+    let mut last_snapshot = Instant::now();
+
+    loop {
+        let mut child = spawn_node(osmosisd, workspace)?;
+        health::set_node_running(true);
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+
+            if let Some(status) = child.try_wait()? {
+                health::set_node_running(false);
+                println!(
+                    "{}",
+                    format!("Node exited with {}, restarting...", status).yellow()
+                );
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                break;
+            }
+
+            let available = disk::available_space(workspace)?;
+            if available < min_free_bytes {
+                stop_node(&mut child)?;
+                return Err(eyre!(
+                    "Only {} GB left on the disk of {}, stopped following mainnet",
+                    available / 1_000_000_000,
+                    workspace.display()
+                ));
+            }
+
+            if last_snapshot.elapsed() >= snapshot_interval {
+                // the node must be stopped to get a consistent copy of the databases
+                stop_node(&mut child)?;
+                backup(
+                    osmosisd,
+                    workspace,
+                    Some(snapshot_path.to_path_buf()),
+                    &BackupArgs::default(),
+                    safe_mode,
+                    session,
+                )
+                .await?;
+                last_snapshot = Instant::now();
+                break;
+            }
+        }
+    }
+}
+
+fn spawn_node(osmosisd: &PathBuf, workspace: &PathBuf) -> Result<Child> {
+    Ok(Command::new(osmosisd)
+        .arg("start")
+        .arg("--home")
+        .arg(workspace)
+        .spawn()?)
+}
+
+fn stop_node(child: &mut Child) -> Result<()> {
+    child.kill()?;
+    child.wait()?;
+    health::set_node_running(false);
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+
+use crate::{accounts, endpoints::Endpoints, inject};
+
+const ATOM_DENOM: &str = "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+
+/// Gas used keyed by message type
+pub type GasReport = BTreeMap<String, u64>;
+
+/// Representative `osmosisd tx` arguments simulated for the report, `{from}` and `{address}` are replaced
+/// with the key name and its address.
+const TXS: &[(&str, &[&str])] = &[
+    (
+        "bank/MsgSend",
+        &["bank", "send", "{from}", "{address}", "1uosmo"],
+    ),
+    (
+        "poolmanager/MsgSwapExactAmountIn",
+        &[
+            "poolmanager",
+            "swap-exact-amount-in",
+            "1000uosmo",
+            "1",
+            "--swap-route-pool-ids",
+            "1",
+            "--swap-route-denoms",
+            ATOM_DENOM,
+        ],
+    ),
+    (
+        "distribution/MsgWithdrawDelegatorReward",
+        &["distribution", "withdraw-all-rewards"],
+    ),
+];
+
+/// Simulate the representative transactions and write the gas used per message type to `output`,
+/// printing the difference against `compare` if set.
+pub fn run(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    from: &str,
+    output: &PathBuf,
+    compare: &Option<PathBuf>,
+    endpoints: &Endpoints,
+) -> Result<()> {
+    let address = accounts::key_address(osmosisd, osmosis_home, from)?;
+
+    let mut report = GasReport::new();
+    for (msg_type, args) in TXS {
+        let args = args
+            .iter()
+            .map(|arg| arg.replace("{from}", from).replace("{address}", &address));
+        let gas = simulate(osmosisd, osmosis_home, from, args, endpoints)
+            .wrap_err(format!("Failed to simulate {}", msg_type))?;
+        report.insert(msg_type.to_string(), gas);
+    }
+
+    std::fs::write(output, serde_json::to_string_pretty(&report)?)
+        .wrap_err(format!("Failed to write gas report: {}", output.display()))?;
+    println!(
+        "{}",
+        format!("✓ Saved gas report to {}.", output.display()).green()
+    );
+
+    match compare {
+        Some(compare) => {
+            let baseline = std::fs::read_to_string(compare)
+                .wrap_err(format!("Failed to read gas report: {}", compare.display()))?;
+            let baseline: GasReport =
+                serde_json::from_str(&baseline).wrap_err("Failed to parse gas report")?;
+            print_diff(&baseline, &report);
+        }
+        None => {
+            for (msg_type, gas) in &report {
+                println!("{:<45} {:>12}", msg_type, gas);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_diff(baseline: &GasReport, report: &GasReport) {
+    println!(
+        "{:<45} {:>12} {:>12} {:>12}",
+        "message", "baseline", "current", "diff"
+    );
+    for (msg_type, gas) in report {
+        let Some(base) = baseline.get(msg_type) else {
+            println!("{:<45} {:>12} {:>12} {:>12}", msg_type, "-", gas, "-");
+            continue;
+        };
+
+        let diff = *gas as i128 - *base as i128;
+        let diff = match diff {
+            d if d > 0 => format!("+{}", d).red(),
+            d if d < 0 => d.to_string().green(),
+            d => d.to_string().normal(),
+        };
+        println!("{:<45} {:>12} {:>12} {:>12}", msg_type, base, gas, diff);
+    }
+}
+
+fn simulate(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    from: &str,
+    args: impl Iterator<Item = String>,
+    endpoints: &Endpoints,
+) -> Result<u64> {
+    let output = inject::tx_command(osmosisd, osmosis_home, from, args, endpoints)
+        .arg("--dry-run")
+        .output()
+        .wrap_err("Failed to simulate tx")?;
+
+    // gas estimate is printed to stderr as `gas estimate: <gas>`
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr
+        .lines()
+        .chain(String::from_utf8_lossy(&output.stdout).lines())
+        .find_map(|line| line.trim().strip_prefix("gas estimate:"))
+        .ok_or_else(|| eyre!("No gas estimate in output: {}", stderr))?
+        .trim()
+        .parse::<u64>()
+        .wrap_err("Failed to parse gas estimate")
+}
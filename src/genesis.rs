@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::{Context, Result};
+use serde_json::Value;
+
+/// Deep merge `overrides` into `base`. Objects are merged key by key, any other value in
+/// `overrides` (including arrays) replaces the one in `base`.
+pub fn deep_merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                deep_merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Apply overrides file on top of the genesis file in place
+pub fn apply_overrides(genesis_path: &PathBuf, overrides_path: &PathBuf) -> Result<()> {
+    let genesis = std::fs::read_to_string(genesis_path).wrap_err(format!(
+        "Failed to read genesis file: {}",
+        genesis_path.display()
+    ))?;
+    let overrides = std::fs::read_to_string(overrides_path).wrap_err(format!(
+        "Failed to read genesis overrides file: {}",
+        overrides_path.display()
+    ))?;
+
+    let mut genesis: Value =
+        serde_json::from_str(&genesis).wrap_err("Failed to parse genesis file")?;
+    let overrides: Value =
+        serde_json::from_str(&overrides).wrap_err("Failed to parse genesis overrides file")?;
+
+    deep_merge(&mut genesis, overrides);
+
+    std::fs::write(genesis_path, serde_json::to_string(&genesis)?)
+        .wrap_err("Failed to write genesis file")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn merges_nested_objects_key_by_key() {
+        let mut genesis = json!({
+            "app_state": { "gov": { "params": { "voting_period": "432000s", "quorum": "0.2" } } }
+        });
+        deep_merge(
+            &mut genesis,
+            json!({ "app_state": { "gov": { "params": { "voting_period": "30s" } } } }),
+        );
+
+        assert_eq!(
+            genesis,
+            json!({
+                "app_state": { "gov": { "params": { "voting_period": "30s", "quorum": "0.2" } } }
+            })
+        );
+    }
+
+    #[test]
+    fn replaces_arrays_instead_of_merging_them() {
+        let mut genesis = json!({ "validators": [{ "name": "a" }, { "name": "b" }] });
+        deep_merge(&mut genesis, json!({ "validators": [{ "name": "c" }] }));
+
+        assert_eq!(genesis, json!({ "validators": [{ "name": "c" }] }));
+    }
+
+    #[test]
+    fn adds_new_keys() {
+        let mut genesis = json!({ "chain_id": "osmosis-1" });
+        deep_merge(
+            &mut genesis,
+            json!({ "app_state": { "epochs": { "epochs": [] } } }),
+        );
+
+        assert_eq!(
+            genesis,
+            json!({ "chain_id": "osmosis-1", "app_state": { "epochs": { "epochs": [] } } })
+        );
+    }
+}
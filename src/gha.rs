@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::{Context, Result};
+use colored::Colorize;
+
+/// Home directory used when running inside GitHub Actions, runners have far more space on /mnt
+pub const GHA_HOME_DIR: &str = "/mnt/.osmosisd";
+
+const ACTION_TEMPLATE: &str = r#"name: osmoinplace
+description: Start an osmosis in-place testnet with mainnet state
+
+inputs:
+  osmosisd-version:
+    description: osmosisd release to install
+    required: true
+  upgrade-handler:
+    description: Optional upgrade handler to trigger
+    required: false
+    default: ""
+  new-osmosisd-version:
+    description: osmosisd release to run the upgrade with
+    required: false
+    default: ""
+  on-ready:
+    description: Command to run once the chain is ready
+    required: false
+    default: ""
+
+runs:
+  using: composite
+  steps:
+    - name: Prepare /mnt
+      shell: bash
+      run: sudo mkdir -p /mnt && sudo chown -R "$USER" /mnt
+
+    - name: Install osmosisd
+      shell: bash
+      env:
+        OSMOSISD_VERSION: ${{ inputs.osmosisd-version }}
+        NEW_OSMOSISD_VERSION: ${{ inputs.new-osmosisd-version }}
+      run: |
+        install_osmosisd() {
+          sudo curl -sSfL -o "/usr/local/bin/$2" \
+            "https://github.com/osmosis-labs/osmosis/releases/download/$1/osmosisd-${1#v}-linux-amd64"
+          sudo chmod +x "/usr/local/bin/$2"
+        }
+        install_osmosisd "$OSMOSISD_VERSION" osmosisd
+        if [ -n "$NEW_OSMOSISD_VERSION" ]; then
+          install_osmosisd "$NEW_OSMOSISD_VERSION" osmosisd_new
+        fi
+
+    - name: Install osmoinplace
+      shell: bash
+      run: cargo install osmoinplace
+
+    # the state is far over the 10GB actions/cache limit, so it's downloaded on every run
+    - name: Download mainnet state
+      shell: bash
+      run: |
+        timeout 180m osmoinplace -y download-mainnet-state
+        osmoinplace backup --path /mnt/.osmosisd_bak
+
+    - name: Start in-place testnet
+      shell: bash
+      env:
+        UPGRADE_HANDLER: ${{ inputs.upgrade-handler }}
+        NEW_OSMOSISD_VERSION: ${{ inputs.new-osmosisd-version }}
+        ON_READY: ${{ inputs.on-ready }}
+      run: |
+        args=()
+        if [ -n "$UPGRADE_HANDLER" ]; then
+          args+=(--upgrade-handler "$UPGRADE_HANDLER")
+        fi
+        if [ -n "$NEW_OSMOSISD_VERSION" ]; then
+          args+=(--new-osmosisd-bin osmosisd_new)
+        fi
+        if [ -n "$ON_READY" ]; then
+          args+=(--on-ready "$ON_READY")
+        fi
+        timeout 120m osmoinplace -y magic-start --backup-path /mnt/.osmosisd_bak "${args[@]}"
+"#;
+
+/// Whether we are running inside a GitHub Actions runner
+pub fn is_github_actions() -> bool {
+    std::env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true")
+}
+
+/// Write the composite action to `path`
+pub fn setup(path: &PathBuf) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).wrap_err(format!(
+            "Failed to create directory: {}",
+            parent.display()
+        ))?;
+    }
+
+    std::fs::write(path, ACTION_TEMPLATE)
+        .wrap_err(format!("Failed to write action: {}", path.display()))?;
+
+    println!(
+        "{}",
+        format!("✓ Wrote GitHub Actions composite action to {}.", path.display()).green()
+    );
+    println!(
+        "Use it in a workflow with:\n\n  - uses: ./{}\n    with:\n      osmosisd-version: v25.0.0\n",
+        path.parent().unwrap_or(path).display()
+    );
+
+    Ok(())
+}
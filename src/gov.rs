@@ -0,0 +1,577 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use clap::{ArgAction, Args};
+use color_eyre::eyre::{eyre, Context, Result};
+use serde_json::{json, Value};
+
+use crate::{accounts, endpoints::Endpoints, inject, readiness, session::Session, spinner};
+
+/// Gov module account, the authority of governance gated messages
+pub const GOV_AUTHORITY: &str = "osmo10d07y265gmmuvt4z0w9aw880jnsr700jjeq4qp";
+
+/// Longest voting period worth waiting for, mainnet's multi-day one has to be shortened first
+const MAX_VOTING_PERIOD: Duration = Duration::from_secs(30 * 60);
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Voting periods set by --fast-gov, the expedited one has to stay shorter
+const FAST_VOTING_PERIOD: &str = "30s";
+const FAST_EXPEDITED_VOTING_PERIOD: &str = "15s";
+
+/// Unbonding time set by --short-unbonding
+const SHORT_UNBONDING_TIME: &str = "60s";
+
+/// Epoch durations set by --fast-epochs, the week has to stay longer than the day
+const FAST_EPOCHS: [(&str, &str); 2] = [("day", "60s"), ("week", "300s")];
+
+/// Flag of the osmosisd builds whose `in-place-testnet` patches module params and epoch
+/// durations into the state as it creates the fork, taking a JSON file of them
+pub const PATCH_PARAMS_FLAG: &str = "--patch-params";
+
+#[derive(Args, Debug, Clone)]
+pub struct GovArgs {
+    /// Key of the fork's validator in the test keyring, to submit the proposal and vote with
+    #[arg(long)]
+    pub from: String,
+
+    /// Gas prices for the transactions, defaults to the --fees-preset minimum or 0.025uosmo
+    #[arg(long)]
+    pub gas_prices: Option<String>,
+}
+
+/// Module params and epoch durations to shorten. They are patched into the state as the fork is
+/// created when its osmosisd's `in-place-testnet` takes --patch-params. Stock builds don't, the
+/// params are then changed through a proposal passed by the operator once the fork is up, within
+/// the voting period the fork starts with, and epochs can't be shortened.
+#[derive(Args, Debug, Clone, Default)]
+pub struct FastParamsArgs {
+    /// Shorten the gov voting period to 30s (15s expedited)
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true"
+    )]
+    pub fast_gov: bool,
+
+    /// Shorten the staking unbonding time to 60s
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true"
+    )]
+    pub short_unbonding: bool,
+
+    /// Shorten the day and week epochs to 60s and 300s, needs an osmosisd whose
+    /// in-place-testnet takes --patch-params
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true"
+    )]
+    pub fast_epochs: bool,
+}
+
+/// Params to shorten, whether they are patched as the fork is created and the operator passing
+/// the proposal otherwise
+#[derive(Debug, Clone)]
+pub struct FastParams {
+    args: FastParamsArgs,
+    patch: bool,
+    operator: String,
+    gas_prices: String,
+}
+
+impl FastParamsArgs {
+    /// Params to shorten on the fork created by `osmosisd`, with the proposal signed by
+    /// `operator`, an address or key name, when it can't patch them. `None` when there are none.
+    pub fn resolve(
+        &self,
+        osmosisd: &Path,
+        operator: Option<&str>,
+        gas_prices: String,
+    ) -> Result<Option<FastParams>> {
+        if !(self.fast_gov || self.short_unbonding || self.fast_epochs) {
+            return Ok(None);
+        }
+        let patch = patches_params(osmosisd);
+        if self.fast_epochs && !patch {
+            return Err(eyre!(
+                "--fast-epochs needs an osmosisd whose in-place-testnet takes {}, {} doesn't: epoch durations are x/epochs state that no proposal changes",
+                PATCH_PARAMS_FLAG,
+                osmosisd.display()
+            ));
+        }
+        Ok(Some(FastParams {
+            args: self.clone(),
+            patch,
+            operator: operator
+                .unwrap_or(accounts::DEFAULT_OPERATOR_ADDRESS)
+                .to_string(),
+            gas_prices,
+        }))
+    }
+}
+
+/// Whether the `in-place-testnet` of `osmosisd` takes --patch-params
+pub fn patches_params(osmosisd: &Path) -> bool {
+    Command::new(osmosisd)
+        .args(["in-place-testnet", "--help"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(PATCH_PARAMS_FLAG))
+}
+
+/// Module params and epoch durations to patch for `args`, keyed by module
+fn patch(args: &FastParamsArgs) -> Value {
+    let mut patch = json!({});
+    if args.fast_gov {
+        patch["gov"] = json!({
+            "voting_period": FAST_VOTING_PERIOD,
+            "expedited_voting_period": FAST_EXPEDITED_VOTING_PERIOD,
+        });
+    }
+    if args.short_unbonding {
+        patch["staking"] = json!({ "unbonding_time": SHORT_UNBONDING_TIME });
+    }
+    if args.fast_epochs {
+        patch["epochs"] = FAST_EPOCHS
+            .into_iter()
+            .map(|(epoch, duration)| (epoch.to_string(), json!(duration)))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    patch
+}
+
+impl FastParams {
+    /// Where the params patched as the fork of `osmosis_home` is created are written, `None`
+    /// when they are shortened through a proposal instead
+    pub fn patch_path(&self, osmosis_home: &Path) -> Option<PathBuf> {
+        self.patch
+            .then(|| osmosis_home.join("config").join("patch-params.json"))
+    }
+
+    /// Write the params to patch as the fork of `osmosis_home` is created, returning where they
+    /// went, `None` when they are shortened through a proposal instead
+    pub fn write_patch(&self, osmosis_home: &Path) -> Result<Option<PathBuf>> {
+        let Some(path) = self.patch_path(osmosis_home) else {
+            return Ok(None);
+        };
+        std::fs::write(&path, patch(&self.args).to_string())
+            .wrap_err(format!("Failed to write {}", path.display()))?;
+        Ok(Some(path))
+    }
+
+    /// Pass a single proposal updating the params of every module to shorten, for a node that
+    /// didn't patch them as the fork was created
+    pub async fn apply(
+        &self,
+        osmosisd: &PathBuf,
+        osmosis_home: &PathBuf,
+        session: &Session,
+    ) -> Result<()> {
+        if self.args.fast_epochs {
+            return Err(eyre!(
+                "Epochs are only shortened as the fork is created, run the fork phase for --fast-epochs"
+            ));
+        }
+        let from = accounts::operator_key(osmosisd, osmosis_home, &self.operator)?;
+
+        let mut messages = vec![];
+        if self.args.fast_gov {
+            let mut params = query(
+                osmosisd,
+                osmosis_home,
+                &["gov", "params"],
+                &session.endpoints,
+            )?["params"]
+                .take();
+            params["voting_period"] = json!(FAST_VOTING_PERIOD);
+            if params.get("expedited_voting_period").is_some() {
+                params["expedited_voting_period"] = json!(FAST_EXPEDITED_VOTING_PERIOD);
+            }
+            messages.push(json!({
+                "@type": "/cosmos.gov.v1.MsgUpdateParams",
+                "authority": GOV_AUTHORITY,
+                "params": params,
+            }));
+        }
+        if self.args.short_unbonding {
+            let mut params = query(
+                osmosisd,
+                osmosis_home,
+                &["staking", "params"],
+                &session.endpoints,
+            )?["params"]
+                .take();
+            params["unbonding_time"] = json!(SHORT_UNBONDING_TIME);
+            messages.push(json!({
+                "@type": "/cosmos.staking.v1beta1.MsgUpdateParams",
+                "authority": GOV_AUTHORITY,
+                "params": params,
+            }));
+        }
+
+        let proposal = Proposal::Messages {
+            title: "Shorten params for testing".to_string(),
+            messages,
+        };
+        pass(
+            osmosisd,
+            osmosis_home,
+            &proposal,
+            &from,
+            &self.gas_prices,
+            session,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// What to put up for a vote
+pub enum Proposal {
+    /// Proposal JSON in the `osmosisd tx gov submit-proposal` format, the minimum deposit is
+    /// added when it has none
+    File(PathBuf),
+    /// Software upgrade `name` at `height`, by default a while after the voting period ends
+    Upgrade { name: String, height: Option<u64> },
+    /// Legacy x/params change of `key` in `subspace` to the JSON `value`
+    ParamChange {
+        subspace: String,
+        key: String,
+        value: String,
+    },
+    /// Arbitrary messages executed by the gov module
+    Messages { title: String, messages: Vec<Value> },
+}
+
+/// Voting period and minimum deposit of the gov module
+struct GovParams {
+    voting_period: Duration,
+    min_deposit: String,
+}
+
+/// Submit `proposal` from `from`, vote yes on it with the same key and wait until it passed,
+/// returning its id. The fork's validator holds nearly all voting power, so its vote carries it.
+pub async fn pass(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    proposal: &Proposal,
+    from: &str,
+    gas_prices: &str,
+    session: &Session,
+) -> Result<u64> {
+    let params = gov_params(osmosisd, osmosis_home, &session.endpoints)?;
+    if params.voting_period > MAX_VOTING_PERIOD {
+        return Err(eyre!(
+            "The voting period of {:?} is too long to wait for, create the fork with an osmosisd whose in-place-testnet takes {} to shorten it",
+            params.voting_period,
+            PATCH_PARAMS_FLAG
+        ));
+    }
+
+    let proposal = proposal_json(proposal, &params, &session.endpoints).await?;
+    let proposal_path =
+        std::env::temp_dir().join(format!("osmoinplace-proposal-{}.json", std::process::id()));
+    std::fs::write(&proposal_path, proposal.to_string())
+        .wrap_err("Failed to write proposal file")?;
+
+    let proposal_id = spinner! {
+        session.events,
+        "Submitting proposal...",
+        "✓ Submitted proposal.",
+        {
+            let submitted = submit(
+                osmosisd,
+                osmosis_home,
+                &proposal_path,
+                from,
+                gas_prices,
+                &session.endpoints,
+            )
+            .await;
+            let _ = std::fs::remove_file(&proposal_path);
+            submitted?
+        }
+    };
+
+    spinner! {
+        session.events,
+        &format!("Voting yes on proposal {}...", proposal_id),
+        &format!("✓ Voted yes on proposal {}.", proposal_id),
+        {
+            let txhash = inject::send(
+                osmosisd,
+                osmosis_home,
+                from,
+                ["gov", "vote", &proposal_id.to_string(), "yes"]
+                    .into_iter()
+                    .map(str::to_string),
+                gas_prices,
+                &session.endpoints,
+            )
+            .wrap_err("Failed to vote")?;
+            // a vote that failed in its block would otherwise only show once the voting period is over
+            accounts::wait_for_tx(osmosisd, osmosis_home, &txhash, &session.endpoints)
+                .await
+                .wrap_err("Failed to vote")?
+        }
+    };
+
+    spinner! {
+        session.events,
+        &format!("Waiting {:?} for proposal {} to pass...", params.voting_period, proposal_id),
+        &format!("✓ Proposal {} passed.", proposal_id),
+        wait_for_pass(
+            osmosisd,
+            osmosis_home,
+            proposal_id,
+            params.voting_period,
+            &session.endpoints,
+        )
+        .await?
+    };
+
+    Ok(proposal_id)
+}
+
+async fn proposal_json(
+    proposal: &Proposal,
+    params: &GovParams,
+    endpoints: &Endpoints,
+) -> Result<Value> {
+    let (title, messages) = match proposal {
+        Proposal::File(path) => {
+            let content = std::fs::read_to_string(path)
+                .wrap_err(format!("Failed to read proposal: {}", path.display()))?;
+            let mut proposal: Value = serde_json::from_str(&content)
+                .wrap_err(format!("Failed to parse proposal: {}", path.display()))?;
+            if proposal.get("deposit").is_none() {
+                proposal["deposit"] = json!(params.min_deposit);
+            }
+            return Ok(proposal);
+        }
+        Proposal::Messages { title, messages } => (title.clone(), messages.clone()),
+        Proposal::Upgrade { name, height } => {
+            let height = match height {
+                Some(height) => *height,
+                // one block per second at most, with a margin for the vote to land
+                None => {
+                    readiness::status(endpoints).await?.latest_block_height
+                        + params.voting_period.as_secs()
+                        + 100
+                }
+            };
+            (
+                format!("Upgrade to {}", name),
+                vec![json!({
+                    "@type": "/cosmos.upgrade.v1beta1.MsgSoftwareUpgrade",
+                    "authority": GOV_AUTHORITY,
+                    "plan": { "name": name, "height": height.to_string(), "info": "" },
+                })],
+            )
+        }
+        Proposal::ParamChange {
+            subspace,
+            key,
+            value,
+        } => (
+            format!("Set {} {} to {}", subspace, key, value),
+            vec![json!({
+                "@type": "/cosmos.gov.v1.MsgExecLegacyContent",
+                "authority": GOV_AUTHORITY,
+                "content": {
+                    "@type": "/cosmos.params.v1beta1.ParameterChangeProposal",
+                    "title": format!("Set {} {}", subspace, key),
+                    "description": format!("Set {} {} to {}", subspace, key, value),
+                    "changes": [{ "subspace": subspace, "key": key, "value": value }],
+                },
+            })],
+        ),
+    };
+
+    Ok(json!({
+        "messages": messages,
+        "metadata": "",
+        "deposit": params.min_deposit,
+        "title": title,
+        "summary": title,
+    }))
+}
+
+fn gov_params(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    endpoints: &Endpoints,
+) -> Result<GovParams> {
+    let response = query(osmosisd, osmosis_home, &["gov", "params"], endpoints)?;
+    let params = match response.get("params") {
+        Some(params) if !params.is_null() => params,
+        _ => &response["voting_params"],
+    };
+
+    let voting_period = params["voting_period"]
+        .as_str()
+        .ok_or_else(|| eyre!("Missing voting period in gov params"))?;
+    let voting_period = voting_period
+        .trim_end_matches('s')
+        .parse::<f64>()
+        .wrap_err(format!("Failed to parse voting period: {}", voting_period))?;
+
+    let min_deposit = response["params"]["min_deposit"]
+        .as_array()
+        .or_else(|| response["deposit_params"]["min_deposit"].as_array())
+        .into_iter()
+        .flatten()
+        .map(|coin| {
+            format!(
+                "{}{}",
+                coin["amount"].as_str().unwrap_or_default(),
+                coin["denom"].as_str().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    Ok(GovParams {
+        voting_period: Duration::from_secs_f64(voting_period),
+        min_deposit,
+    })
+}
+
+async fn submit(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    proposal_path: &Path,
+    from: &str,
+    gas_prices: &str,
+    endpoints: &Endpoints,
+) -> Result<u64> {
+    let txhash = inject::send(
+        osmosisd,
+        osmosis_home,
+        from,
+        [
+            "gov".to_string(),
+            "submit-proposal".to_string(),
+            proposal_path.display().to_string(),
+        ]
+        .into_iter(),
+        gas_prices,
+        endpoints,
+    )
+    .wrap_err("Failed to submit proposal")?;
+    accounts::wait_for_tx(osmosisd, osmosis_home, &txhash, endpoints)
+        .await
+        .wrap_err("Failed to submit proposal")?;
+
+    accounts::tx_attribute(osmosisd, osmosis_home, &txhash, "proposal_id", endpoints)?
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| eyre!("No proposal id in the events of tx {}", txhash))
+}
+
+async fn wait_for_pass(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    proposal_id: u64,
+    voting_period: Duration,
+    endpoints: &Endpoints,
+) -> Result<()> {
+    let deadline = tokio::time::Instant::now() + voting_period + Duration::from_secs(60);
+    while tokio::time::Instant::now() < deadline {
+        let response = query(
+            osmosisd,
+            osmosis_home,
+            &["gov", "proposal", &proposal_id.to_string()],
+            endpoints,
+        )?;
+        let proposal = response.get("proposal").unwrap_or(&response);
+        match proposal["status"].as_str().unwrap_or_default() {
+            "PROPOSAL_STATUS_PASSED" => return Ok(()),
+            status @ ("PROPOSAL_STATUS_REJECTED" | "PROPOSAL_STATUS_FAILED") => {
+                return Err(eyre!(
+                    "Proposal {} ended with {}",
+                    proposal_id,
+                    status.trim_start_matches("PROPOSAL_STATUS_")
+                ))
+            }
+            _ => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+    }
+
+    Err(eyre!(
+        "Proposal {} didn't pass within its voting period",
+        proposal_id
+    ))
+}
+
+fn query(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    args: &[&str],
+    endpoints: &Endpoints,
+) -> Result<Value> {
+    let output = Command::new(osmosisd)
+        .arg("query")
+        .args(args)
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--node", &endpoints.rpc_addr()])
+        .args(["--output", "json"])
+        .output()
+        .wrap_err(format!("Failed to query {}", args.join(" ")))?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to query {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .wrap_err(format!("Failed to parse {} query response", args.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_has_only_the_selected_modules() {
+        let args = FastParamsArgs {
+            short_unbonding: true,
+            ..FastParamsArgs::default()
+        };
+        assert_eq!(
+            patch(&args),
+            json!({ "staking": { "unbonding_time": SHORT_UNBONDING_TIME } })
+        );
+    }
+
+    #[test]
+    fn patch_keys_epoch_durations_by_identifier() {
+        let args = FastParamsArgs {
+            fast_gov: true,
+            fast_epochs: true,
+            ..FastParamsArgs::default()
+        };
+        let patch = patch(&args);
+        assert_eq!(patch["gov"]["voting_period"], FAST_VOTING_PERIOD);
+        assert_eq!(patch["epochs"], json!({ "day": "60s", "week": "300s" }));
+        assert!(patch.get("staking").is_none());
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{Context, Result};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+
+static NODE_RUNNING: AtomicBool = AtomicBool::new(false);
+static NODE_READY: AtomicBool = AtomicBool::new(false);
+static CURRENT_PHASE: Mutex<&str> = Mutex::new("idle");
+
+static PHASE_STARTED: Mutex<Option<Instant>> = Mutex::new(None);
+static PHASE_DURATIONS: Mutex<BTreeMap<&str, Duration>> = Mutex::new(BTreeMap::new());
+static DOWNLOADED_BYTES: AtomicU64 = AtomicU64::new(0);
+static NODE_STARTS: AtomicU64 = AtomicU64::new(0);
+static HOOK_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_node_running(running: bool) {
+    if running && !NODE_RUNNING.load(Ordering::SeqCst) {
+        NODE_STARTS.fetch_add(1, Ordering::SeqCst);
+    }
+    NODE_RUNNING.store(running, Ordering::SeqCst);
+    if !running {
+        NODE_READY.store(false, Ordering::SeqCst);
+    }
+}
+
+pub fn set_node_ready(ready: bool) {
+    NODE_READY.store(ready, Ordering::SeqCst);
+}
+
+pub fn set_phase(phase: &'static str) {
+    let mut current = CURRENT_PHASE.lock().unwrap();
+    let mut started = PHASE_STARTED.lock().unwrap();
+    if let Some(started) = *started {
+        *PHASE_DURATIONS.lock().unwrap().entry(*current).or_default() += started.elapsed();
+    }
+    *current = phase;
+    *started = Some(Instant::now());
+}
+
+pub fn add_downloaded_bytes(bytes: u64) {
+    DOWNLOADED_BYTES.fetch_add(bytes, Ordering::SeqCst);
+}
+
+pub fn record_hook_failure() {
+    HOOK_FAILURES.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Serve `/healthz` (the node process is running and ready), `/readyz` (the node is producing
+/// blocks) and `/metrics` (Prometheus metrics about the tool's
+/// own operations) on `addr` in the background
+pub async fn serve(addr: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .wrap_err(format!("Failed to bind health endpoint to {}", addr))?;
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                continue;
+            };
+
+            tokio::spawn(async move {
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(&mut stream);
+                if reader.read_line(&mut request_line).await.is_err() {
+                    return;
+                }
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let (ok, body) = match path {
+                    "/healthz" => (
+                        NODE_RUNNING.load(Ordering::SeqCst) && NODE_READY.load(Ordering::SeqCst),
+                        status_body(),
+                    ),
+                    "/readyz" => (NODE_READY.load(Ordering::SeqCst), status_body()),
+                    "/metrics" => (true, metrics_body()),
+                    _ => (false, "not found".to_string()),
+                };
+                let status = match (path, ok) {
+                    ("/healthz" | "/readyz" | "/metrics", true) => "200 OK",
+                    ("/healthz" | "/readyz", false) => "503 Service Unavailable",
+                    _ => "404 Not Found",
+                };
+                let content_type = match path {
+                    "/metrics" => "text/plain; version=0.0.4",
+                    _ => "application/json",
+                };
+
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    content_type,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    Ok(())
+}
+
+fn status_body() -> String {
+    serde_json::json!({
+        "phase": *CURRENT_PHASE.lock().unwrap(),
+        "node_running": NODE_RUNNING.load(Ordering::SeqCst),
+        "node_ready": NODE_READY.load(Ordering::SeqCst),
+    })
+    .to_string()
+}
+
+fn metrics_body() -> String {
+    let current = *CURRENT_PHASE.lock().unwrap();
+    let mut durations = PHASE_DURATIONS.lock().unwrap().clone();
+    if let Some(started) = *PHASE_STARTED.lock().unwrap() {
+        *durations.entry(current).or_default() += started.elapsed();
+    }
+
+    let mut body = String::new();
+
+    let _ = writeln!(
+        body,
+        "# HELP osmoinplace_phase Phase the tool is currently in"
+    );
+    let _ = writeln!(body, "# TYPE osmoinplace_phase gauge");
+    let _ = writeln!(body, "osmoinplace_phase{{phase=\"{}\"}} 1", current);
+
+    let _ = writeln!(
+        body,
+        "# HELP osmoinplace_phase_duration_seconds Total time spent in each phase"
+    );
+    let _ = writeln!(body, "# TYPE osmoinplace_phase_duration_seconds counter");
+    for (phase, duration) in durations {
+        let _ = writeln!(
+            body,
+            "osmoinplace_phase_duration_seconds{{phase=\"{}\"}} {}",
+            phase,
+            duration.as_secs_f64()
+        );
+    }
+
+    for (name, help, value) in [
+        (
+            "osmoinplace_downloaded_bytes_total",
+            "Bytes of snapshots downloaded",
+            DOWNLOADED_BYTES.load(Ordering::SeqCst),
+        ),
+        (
+            "osmoinplace_node_starts_total",
+            "Times the node process has been started",
+            NODE_STARTS.load(Ordering::SeqCst),
+        ),
+        (
+            "osmoinplace_hook_failures_total",
+            "Failed hook command attempts",
+            HOOK_FAILURES.load(Ordering::SeqCst),
+        ),
+    ] {
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} counter", name);
+        let _ = writeln!(body, "{} {}", name, value);
+    }
+
+    for (name, help, value) in [
+        (
+            "osmoinplace_node_running",
+            "Whether the node process is running",
+            NODE_RUNNING.load(Ordering::SeqCst),
+        ),
+        (
+            "osmoinplace_node_ready",
+            "Whether the node is producing blocks",
+            NODE_READY.load(Ordering::SeqCst),
+        ),
+    ] {
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} gauge", name);
+        let _ = writeln!(body, "{} {}", name, value as u8);
+    }
+
+    body
+}
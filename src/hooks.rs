@@ -0,0 +1,268 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use color_eyre::eyre::{Context, Result};
+use colored::Colorize;
+
+use crate::{
+    accounts::Funding, changes, contracts::Deployment, gov::FastParams, output, policy::Policy,
+    profile::Profile, readiness, session::Session, supervisor::Supervisor, upgrade::NextUpgrade,
+    webhook,
+};
+
+/// Shell commands run at points of the node's lifecycle, with the fork's endpoints in their
+/// environment
+#[derive(Args, Debug, Clone, Default)]
+pub struct Hooks {
+    /// Command to run on first indexed block events
+    #[arg(long)]
+    pub on_ready: Option<String>,
+
+    /// Timeout in seconds for the on_ready command, defaults to --hook-timeout-secs
+    #[arg(long)]
+    pub on_ready_timeout: Option<u64>,
+
+    /// Number of times to retry a failed on_ready command, defaults to --hook-retries
+    #[arg(long)]
+    pub on_ready_retries: Option<u32>,
+
+    /// Command to run when the node hits a consensus failure other than halting for the upgrade,
+    /// gets the failing log line as OSMOINPLACE_FAILURE
+    #[arg(long)]
+    pub on_failure: Option<String>,
+
+    /// Command to run once the node is stopped, whether the run succeeded or not, gets `ok` or
+    /// `error` as OSMOINPLACE_EXIT
+    #[arg(long)]
+    pub on_exit: Option<String>,
+
+    /// Command to run once the new binary is ready after the upgrade
+    #[arg(long)]
+    pub on_upgrade_complete: Option<String>,
+
+    /// Changes manifest (see assert-changes) to check once the new binary is ready after the
+    /// upgrade, failing the run if any change doesn't hold
+    #[arg(long)]
+    pub expect_changes: Option<PathBuf>,
+}
+
+impl Hooks {
+    /// Fill hooks not given on the command line from `profile`
+    pub fn apply_profile(&mut self, profile: &Profile) {
+        for (hook, default) in [
+            (&mut self.on_ready, &profile.on_ready),
+            (&mut self.on_failure, &profile.on_failure),
+            (&mut self.on_exit, &profile.on_exit),
+            (&mut self.on_upgrade_complete, &profile.on_upgrade_complete),
+        ] {
+            if hook.is_none() {
+                *hook = default.clone();
+            }
+        }
+        if self.on_ready_timeout.is_none() {
+            self.on_ready_timeout = profile.on_ready_timeout;
+        }
+        if self.on_ready_retries.is_none() {
+            self.on_ready_retries = profile.on_ready_retries;
+        }
+        if self.expect_changes.is_none() {
+            self.expect_changes = profile.expect_changes.clone();
+        }
+    }
+
+    /// `policy` with the timeout and retries of the on_ready command
+    pub fn on_ready_policy(&self, policy: &Policy) -> Policy {
+        Policy {
+            hook_timeout_secs: self.on_ready_timeout.unwrap_or(policy.hook_timeout_secs),
+            hook_retries: self.on_ready_retries.unwrap_or(policy.hook_retries),
+            ..policy.clone()
+        }
+    }
+
+    /// Hooks for running a node that isn't completing an upgrade
+    pub fn without_upgrade(&self) -> Hooks {
+        Hooks {
+            on_upgrade_complete: None,
+            expect_changes: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Preparation of the fork once it's ready, done before the on_ready hook runs
+#[derive(Debug, Clone, Default)]
+pub struct Setup {
+    pub fast_params: Option<FastParams>,
+    pub funding: Option<Funding>,
+    pub contracts: Option<Deployment>,
+    /// Chained upgrade to propose last, which the node then halts for
+    pub next_upgrade: Option<NextUpgrade>,
+}
+
+impl Setup {
+    pub fn is_empty(&self) -> bool {
+        self.fast_params.is_none()
+            && self.funding.is_none()
+            && self.contracts.is_none()
+            && self.next_upgrade.is_none()
+    }
+}
+
+/// Environment of hooks run while the node is up, with its current height
+async fn env(osmosis_home: &Path, session: &Session) -> Vec<(&'static str, String)> {
+    let height = readiness::status(&session.endpoints)
+        .await
+        .ok()
+        .map(|status| status.latest_block_height);
+    session.endpoints.hook_env(Some(osmosis_home), height)
+}
+
+/// Run hook `name` with the node's environment and `extra` added to it
+async fn run(
+    hook: &str,
+    name: &str,
+    osmosis_home: &Path,
+    extra: &[(&'static str, String)],
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    let mut env = env(osmosis_home, session).await;
+    env.extend_from_slice(extra);
+
+    policy
+        .run_hook(hook, &env)
+        .wrap_err(format!("Failed to execute {} command", name))
+}
+
+/// Start hook `name` on `node` like `run`, without holding up the processing of its output while
+/// the hook runs. Its result comes back as `Event::HookFinished`.
+pub async fn spawn(
+    node: &mut Supervisor,
+    hook: &str,
+    name: &'static str,
+    osmosis_home: &Path,
+    extra: &[(&'static str, String)],
+    policy: &Policy,
+    session: &Session,
+) {
+    let mut env = env(osmosis_home, session).await;
+    env.extend_from_slice(extra);
+    let hook = hook.to_string();
+    let policy = policy.clone();
+    node.spawn_hook(move || {
+        policy
+            .run_hook(&hook, &env)
+            .wrap_err(format!("Failed to execute {} command", name))
+    });
+}
+
+/// Start checking the changes of `manifest` against the upgraded node on `node` like `spawn`
+pub fn spawn_changes(node: &mut Supervisor, manifest: &Path, osmosisd: &Path, osmosis_home: &Path) {
+    let manifest = manifest.to_path_buf();
+    let (osmosisd, osmosis_home) = (osmosisd.to_path_buf(), osmosis_home.to_path_buf());
+    node.spawn_hook(move || {
+        let manifest = changes::load(&manifest)?;
+        changes::check(&osmosisd, &osmosis_home, &manifest)
+            .wrap_err("The upgrade didn't make the expected changes")
+    });
+}
+
+/// Start the `setup` of the fork and then the `on_ready` hook on `node` like `spawn`, so the hook
+/// finds the params shortened, the accounts funded and the contracts deployed
+pub async fn spawn_ready(
+    node: &mut Supervisor,
+    hooks: &Hooks,
+    setup: &Setup,
+    osmosisd: &Path,
+    osmosis_home: &Path,
+    policy: &Policy,
+    session: &Session,
+) {
+    let env = env(osmosis_home, session).await;
+    let on_ready = hooks.on_ready.clone();
+    let policy = hooks.on_ready_policy(policy);
+    let setup = setup.clone();
+    let session = session.clone();
+    let (osmosisd, osmosis_home) = (osmosisd.to_path_buf(), osmosis_home.to_path_buf());
+    let runtime = tokio::runtime::Handle::current();
+    node.spawn_hook(move || {
+        if let Some(fast_params) = setup.fast_params {
+            runtime
+                .block_on(fast_params.apply(&osmosisd, &osmosis_home, &session))
+                .wrap_err("Failed to shorten params")?;
+        }
+        if let Some(funding) = setup.funding {
+            runtime
+                .block_on(funding.fund(&osmosisd, &osmosis_home, &session.endpoints))
+                .wrap_err("Failed to fund test accounts")?;
+        }
+        if let Some(contracts) = setup.contracts {
+            runtime
+                .block_on(contracts.deploy(&osmosisd, &osmosis_home, &session))
+                .wrap_err("Failed to deploy contracts")?;
+        }
+        if let Some(next_upgrade) = setup.next_upgrade {
+            runtime.block_on(next_upgrade.propose(&osmosisd, &osmosis_home, &session))?;
+        }
+        if let Some(on_ready) = on_ready {
+            policy
+                .run_hook(&on_ready, &env)
+                .wrap_err("Failed to execute on_ready command")?;
+        }
+        Ok(())
+    });
+}
+
+/// Notify the webhook and start the `on_failure` hook on `node` for consensus failure `line`. A
+/// failing hook only warns so the node can still be restarted.
+pub async fn on_failure(
+    node: &mut Supervisor,
+    hooks: &Hooks,
+    osmosis_home: &Path,
+    line: &str,
+    policy: &Policy,
+    session: &Session,
+) {
+    webhook::notify(session, webhook::Event::Failure, Some(line.to_string()));
+    session.events.emit(output::Event::Failure { error: line });
+    let Some(on_failure) = hooks.on_failure.clone() else {
+        return;
+    };
+
+    let mut env = env(osmosis_home, session).await;
+    env.push(("OSMOINPLACE_FAILURE", line.to_string()));
+    let policy = policy.clone();
+    node.spawn_hook(move || {
+        if let Err(e) = policy
+            .run_hook(&on_failure, &env)
+            .wrap_err("Failed to execute on_failure command")
+        {
+            eprintln!("{}", format!("{:?}", e).yellow());
+        }
+        Ok(())
+    });
+}
+
+/// Run the `on_exit` hook and notify the webhook once a node run finished with `result`, passing
+/// the result through. A failing hook only warns so it can't mask the result of the run.
+pub async fn on_exit<T>(
+    hooks: &Hooks,
+    osmosis_home: &Path,
+    policy: &Policy,
+    session: &Session,
+    result: Result<T>,
+) -> Result<T> {
+    if let Some(on_exit) = &hooks.on_exit {
+        let status = match result {
+            Ok(_) => "ok",
+            Err(_) => "error",
+        };
+        let extra = [("OSMOINPLACE_EXIT", status.to_string())];
+        if let Err(e) = run(on_exit, "on_exit", osmosis_home, &extra, policy, session).await {
+            eprintln!("{}", format!("{:?}", e).yellow());
+        }
+    }
+    let error = result.as_ref().err().map(|e| e.to_string());
+    webhook::send(session, webhook::Event::Exit, error.as_deref()).await;
+    result
+}
@@ -0,0 +1,145 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use serde_json::Value;
+
+use crate::{accounts, inject, session::Session, spinner};
+
+/// `osmosisd tx` subcommand of the builds that execute messages as an account they hold no key
+/// to, `tx impersonate <address> <messages.json>`, with the signature of --from standing in for
+/// the account's. Stock builds verify every signature against the account's public key.
+const IMPERSONATE_COMMAND: &str = "impersonate";
+
+/// Whether `osmosisd` has `tx impersonate`
+pub fn supported(osmosisd: &Path) -> bool {
+    Command::new(osmosisd)
+        .args(["tx", IMPERSONATE_COMMAND, "--help"])
+        .output()
+        .is_ok_and(|output| is_impersonate_help(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Whether `help` is the usage of `tx impersonate`, cobra prints the usage of `tx` for
+/// subcommands it doesn't know
+fn is_impersonate_help(help: &str) -> bool {
+    help.lines()
+        .any(|line| line.trim_start().starts_with("osmosisd tx impersonate"))
+}
+
+/// Messages of `content`, a single message or a list of them, each with its `@type`
+fn messages(content: &str, path: &Path) -> Result<Vec<Value>> {
+    let messages = match serde_json::from_str(content)
+        .wrap_err(format!("Failed to parse {}", path.display()))?
+    {
+        Value::Array(messages) => messages,
+        message => vec![message],
+    };
+    if messages.is_empty() {
+        return Err(eyre!("No messages in {}", path.display()));
+    }
+    if let Some(message) = messages.iter().find(|message| message["@type"].is_null()) {
+        return Err(eyre!(
+            "Message without @type in {}: {}",
+            path.display(),
+            message
+        ));
+    }
+    Ok(messages)
+}
+
+/// Execute the messages of `msg` as `address` on the fork, signed and paid for by `from`, a key
+/// name or address of the test keyring, returning the tx hash
+pub async fn run(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    address: &str,
+    msg: &Path,
+    from: &str,
+    gas_prices: &str,
+    session: &Session,
+) -> Result<String> {
+    if !supported(osmosisd) {
+        return Err(eyre!(
+            "{} can't impersonate accounts, stock osmosisd verifies every signature against the account's public key. Use a build with `osmosisd tx {} <address> <messages.json>`.",
+            osmosisd.display(),
+            IMPERSONATE_COMMAND
+        ));
+    }
+
+    let content =
+        std::fs::read_to_string(msg).wrap_err(format!("Failed to read {}", msg.display()))?;
+    let messages = messages(&content, msg)?;
+    let from = accounts::operator_key(osmosisd, osmosis_home, from)?;
+
+    let messages_path = std::env::temp_dir().join(format!(
+        "osmoinplace-impersonate-{}.json",
+        std::process::id()
+    ));
+    std::fs::write(&messages_path, Value::Array(messages).to_string())
+        .wrap_err("Failed to write messages file")?;
+
+    let txhash = spinner! {
+        session.events,
+        &format!("Executing messages as {}...", address),
+        &format!("✓ Executed messages as {}.", address),
+        {
+            let sent = inject::send(
+                osmosisd,
+                osmosis_home,
+                &from,
+                [
+                    IMPERSONATE_COMMAND.to_string(),
+                    address.to_string(),
+                    messages_path.display().to_string(),
+                ]
+                .into_iter(),
+                gas_prices,
+                &session.endpoints,
+            );
+            let _ = std::fs::remove_file(&messages_path);
+            let txhash = sent.wrap_err(format!("Failed to execute messages as {}", address))?;
+            accounts::wait_for_tx(osmosisd, osmosis_home, &txhash, &session.endpoints)
+                .await
+                .wrap_err(format!("Failed to execute messages as {}", address))?;
+            txhash
+        }
+    };
+    println!("{} {}", "Tx hash:".cyan(), txhash);
+    Ok(txhash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_only_the_impersonate_usage() {
+        let own = "Usage:\n  osmosisd tx impersonate [address] [messages-file] [flags]\n";
+        let tx = "Usage:\n  osmosisd tx [flags]\n  osmosisd tx [command]\n";
+        assert!(is_impersonate_help(own));
+        assert!(!is_impersonate_help(tx));
+    }
+
+    #[test]
+    fn takes_a_single_message_or_a_list() {
+        let path = Path::new("msg.json");
+        let send = r#"{"@type":"/cosmos.bank.v1beta1.MsgSend","from_address":"osmo1whale"}"#;
+        assert_eq!(messages(send, path).unwrap().len(), 1);
+        assert_eq!(
+            messages(&format!("[{},{}]", send, send), path)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn rejects_messages_without_a_type() {
+        let path = Path::new("msg.json");
+        assert!(messages("[]", path).is_err());
+        assert!(messages(r#"{"from_address":"osmo1whale"}"#, path).is_err());
+    }
+}
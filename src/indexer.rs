@@ -0,0 +1,155 @@
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+
+use crate::{
+    node_config::{self, ConfigFile},
+    policy::Policy,
+    session::Session,
+    spinner,
+};
+
+const SCHEMA_URL: &str =
+    "https://raw.githubusercontent.com/cometbft/cometbft/v0.38.x/state/indexer/sink/psql/schema.sql";
+
+const POSTGRES_IMAGE: &str = "postgres:16";
+const POSTGRES_USER: &str = "osmoinplace";
+const POSTGRES_PASSWORD: &str = "osmoinplace";
+const POSTGRES_DB: &str = "indexer";
+
+/// Launch PostgreSQL in docker (unless `dsn` is given), apply the CometBFT psql indexer schema
+/// and configure the node to index into it
+pub async fn up(
+    osmosis_home: &Path,
+    dsn: &Option<String>,
+    container_name: &str,
+    port: u16,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    let schema = spinner! {
+        session.events,
+        "Downloading psql indexer schema...",
+        "✓ Downloaded psql indexer schema.",
+        {
+            let client = &policy.client()?;
+            policy
+                .network("Downloading psql indexer schema", || async move {
+                    Ok(client.get(SCHEMA_URL).send().await?.error_for_status()?.text().await?)
+                })
+                .await?
+        }
+    };
+
+    let dsn = match dsn {
+        Some(dsn) => {
+            spinner! {
+                session.events,
+                "Applying psql indexer schema...",
+                "✓ Applied psql indexer schema.",
+                run_with_stdin(Command::new("psql").arg(dsn), &schema)
+                    .wrap_err("Failed to apply schema, is psql installed?")?
+            };
+            dsn.clone()
+        }
+        None => {
+            spinner! {
+                session.events,
+                &format!("Starting PostgreSQL container {}...", container_name),
+                &format!("✓ Started PostgreSQL container {}.", container_name),
+                start_container(container_name, port).await?
+            };
+            spinner! {
+                session.events,
+                "Applying psql indexer schema...",
+                "✓ Applied psql indexer schema.",
+                run_with_stdin(
+                    Command::new("docker")
+                        .args(["exec", "-i", container_name])
+                        .args(["psql", "-U", POSTGRES_USER, "-d", POSTGRES_DB]),
+                    &schema,
+                )?
+            };
+            format!(
+                "postgresql://{}:{}@localhost:{}/{}?sslmode=disable",
+                POSTGRES_USER, POSTGRES_PASSWORD, port, POSTGRES_DB
+            )
+        }
+    };
+
+    spinner! {
+        session.events,
+        "Configuring node to index into PostgreSQL...",
+        "✓ Configured node to index into PostgreSQL.",
+        {
+            node_config::set(osmosis_home, ConfigFile::Config, "tx_index.indexer", "psql")?;
+            node_config::set(osmosis_home, ConfigFile::Config, "tx_index.psql-conn", dsn.as_str())?;
+        }
+    };
+
+    println!("{} {}", "Indexer DSN:".green(), dsn);
+
+    Ok(())
+}
+
+async fn start_container(container_name: &str, port: u16) -> Result<()> {
+    let status = Command::new("docker")
+        .args(["run", "-d", "--name", container_name])
+        .args(["-e", &format!("POSTGRES_USER={}", POSTGRES_USER)])
+        .args(["-e", &format!("POSTGRES_PASSWORD={}", POSTGRES_PASSWORD)])
+        .args(["-e", &format!("POSTGRES_DB={}", POSTGRES_DB)])
+        .args(["-p", &format!("{}:5432", port)])
+        .arg(POSTGRES_IMAGE)
+        .stdout(Stdio::null())
+        .status()
+        .wrap_err("Failed to run docker, is it installed?")?;
+
+    if !status.success() {
+        return Err(eyre!(
+            "Failed to start PostgreSQL container {}",
+            container_name
+        ));
+    }
+
+    for _ in 0..60 {
+        let ready = Command::new("docker")
+            .args(["exec", container_name, "pg_isready", "-U", POSTGRES_USER])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?
+            .success();
+
+        if ready {
+            return Ok(());
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    Err(eyre!(
+        "Timed out waiting for PostgreSQL container {} to be ready",
+        container_name
+    ))
+}
+
+fn run_with_stdin(cmd: &mut Command, input: &str) -> Result<()> {
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::null()).spawn()?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| eyre!("Failed to open stdin"))?
+        .write_all(input.as_bytes())?;
+
+    if !child.wait()?.success() {
+        return Err(eyre!("Command exited with failure"));
+    }
+
+    Ok(())
+}
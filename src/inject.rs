@@ -0,0 +1,417 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use serde::Deserialize;
+
+use crate::{endpoints::Endpoints, readiness};
+
+/// Transactions to inject into the running fork, read from a TOML file with `[[steps]]` tables
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    pub steps: Vec<Step>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Step {
+    /// Label used in the output, defaults to the step's position in the schedule
+    pub name: Option<String>,
+    /// Send once the chain reaches this height
+    pub at_height: Option<u64>,
+    /// Send at every height divisible by this
+    pub every_n_blocks: Option<u64>,
+    /// Key in the test keyring to sign with
+    pub from: String,
+    #[serde(flatten)]
+    pub tx: Tx,
+}
+
+/// Transaction template, `{from}` and `{height}` in any field are replaced with the signing key
+/// and the height the transaction is sent at
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Tx {
+    BankSend {
+        to: String,
+        amount: String,
+    },
+    Swap {
+        token_in: String,
+        token_out_min_amount: String,
+        pool_ids: Vec<u64>,
+        denoms: Vec<String>,
+    },
+    WasmExecute {
+        contract: String,
+        msg: String,
+        amount: Option<String>,
+    },
+    /// Arbitrary `osmosisd tx` arguments
+    Custom {
+        args: Vec<String>,
+    },
+}
+
+impl Tx {
+    fn args(&self) -> Vec<String> {
+        let join = |items: &[String]| items.join(",");
+        match self {
+            Tx::BankSend { to, amount } => {
+                vec![
+                    "bank".into(),
+                    "send".into(),
+                    "{from}".into(),
+                    to.clone(),
+                    amount.clone(),
+                ]
+            }
+            Tx::Swap {
+                token_in,
+                token_out_min_amount,
+                pool_ids,
+                denoms,
+            } => vec![
+                "poolmanager".into(),
+                "swap-exact-amount-in".into(),
+                token_in.clone(),
+                token_out_min_amount.clone(),
+                "--swap-route-pool-ids".into(),
+                join(&pool_ids.iter().map(u64::to_string).collect::<Vec<_>>()),
+                "--swap-route-denoms".into(),
+                join(denoms),
+            ],
+            Tx::WasmExecute {
+                contract,
+                msg,
+                amount,
+            } => {
+                let mut args = vec![
+                    "wasm".into(),
+                    "execute".into(),
+                    contract.clone(),
+                    msg.clone(),
+                ];
+                if let Some(amount) = amount {
+                    args.extend(["--amount".into(), amount.clone()]);
+                }
+                args
+            }
+            Tx::Custom { args } => args.clone(),
+        }
+    }
+}
+
+impl Step {
+    fn is_due(&self, height: u64) -> bool {
+        self.at_height == Some(height)
+            || self
+                .every_n_blocks
+                .is_some_and(|n| n > 0 && height.is_multiple_of(n))
+    }
+
+    /// `osmosisd tx` arguments of the step sent at `height`
+    fn args(&self, height: u64) -> Vec<String> {
+        self.tx
+            .args()
+            .into_iter()
+            .map(|arg| {
+                arg.replace("{from}", &self.from)
+                    .replace("{height}", &height.to_string())
+            })
+            .collect()
+    }
+}
+
+pub fn load(path: &PathBuf) -> Result<Schedule> {
+    let content = std::fs::read_to_string(path)
+        .wrap_err(format!("Failed to read schedule: {}", path.display()))?;
+    parse(&content, path)
+}
+
+fn parse(content: &str, path: &Path) -> Result<Schedule> {
+    let schedule: Schedule = toml::from_str(content)
+        .wrap_err(format!("Failed to parse schedule: {}", path.display()))?;
+
+    for (i, step) in schedule.steps.iter().enumerate() {
+        if step.at_height.is_none() && step.every_n_blocks.is_none() {
+            return Err(eyre!(
+                "Step {} in {} needs at_height or every_n_blocks",
+                i + 1,
+                path.display()
+            ));
+        }
+    }
+
+    Ok(schedule)
+}
+
+/// Send the scheduled transactions as the chain reaches their heights, until the last one-off
+/// step is sent or forever if there are repeating steps
+pub async fn run(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    schedule: &Schedule,
+    gas_prices: &str,
+    endpoints: &Endpoints,
+) -> Result<()> {
+    let repeating = schedule
+        .steps
+        .iter()
+        .any(|step| step.every_n_blocks.is_some());
+    let last_height = schedule
+        .steps
+        .iter()
+        .filter_map(|step| step.at_height)
+        .max();
+
+    let mut next_height = current_height(endpoints).await?;
+    println!(
+        "{} {}",
+        "Injecting transactions from height".cyan(),
+        next_height
+    );
+
+    loop {
+        if !repeating && last_height.is_none_or(|last| next_height > last) {
+            break;
+        }
+
+        let height = current_height(endpoints).await?;
+        while next_height <= height {
+            for (i, step) in schedule.steps.iter().enumerate() {
+                if !step.is_due(next_height) {
+                    continue;
+                }
+
+                let label = step
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("step {}", i + 1));
+                match send(
+                    osmosisd,
+                    osmosis_home,
+                    &step.from,
+                    step.args(next_height).into_iter(),
+                    gas_prices,
+                    endpoints,
+                ) {
+                    Ok(txhash) => println!(
+                        "{}",
+                        format!("✓ [{}] {} sent: {}", next_height, label, txhash).green()
+                    ),
+                    // background activity shouldn't take the rehearsal down
+                    Err(e) => eprintln!(
+                        "{}",
+                        format!("[{}] {} failed: {}", next_height, label, e).yellow()
+                    ),
+                }
+            }
+            next_height += 1;
+        }
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    Ok(())
+}
+
+async fn current_height(endpoints: &Endpoints) -> Result<u64> {
+    Ok(readiness::status(endpoints).await?.latest_block_height)
+}
+
+/// `osmosisd tx` with `args` signed by `from` with its test keyring key, on the fork's chain
+pub fn tx_command(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    from: &str,
+    args: impl Iterator<Item = String>,
+    endpoints: &Endpoints,
+) -> Command {
+    let mut cmd = Command::new(osmosisd);
+    cmd.arg("tx")
+        .args(args)
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--from", from])
+        .args(["--chain-id", endpoints.chain_id()])
+        .args(["--node", &endpoints.rpc_addr()])
+        .args(["--keyring-backend", "test"]);
+    cmd
+}
+
+/// Send `osmosisd tx` with `args` signed by `from`, returning the hash of the accepted tx
+pub fn send(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    from: &str,
+    args: impl Iterator<Item = String>,
+    gas_prices: &str,
+    endpoints: &Endpoints,
+) -> Result<String> {
+    let output = tx_command(osmosisd, osmosis_home, from, args, endpoints)
+        .args(["--gas", "auto", "--gas-adjustment", "1.5"])
+        .args(["--gas-prices", gas_prices])
+        .args(["--output", "json", "--yes"])
+        .output()
+        .wrap_err("Failed to send tx")?;
+
+    if !output.status.success() {
+        return Err(eyre!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let response: serde_json::Value =
+        serde_json::from_slice(&output.stdout).wrap_err("Failed to parse tx response")?;
+
+    if response["code"].as_u64().unwrap_or_default() != 0 {
+        return Err(eyre!(
+            "{}",
+            response["raw_log"].as_str().unwrap_or_default()
+        ));
+    }
+
+    response["txhash"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| eyre!("Missing txhash in tx response"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(content: &str) -> Result<Schedule> {
+        parse(content, Path::new("schedule.toml"))
+    }
+
+    #[test]
+    fn parses_every_transaction_type() {
+        let schedule = schedule(
+            r#"
+            [[steps]]
+            name = "whale send"
+            at_height = 100
+            from = "lo-test1"
+            type = "bank_send"
+            to = "osmo1recipient"
+            amount = "1000uosmo"
+
+            [[steps]]
+            every_n_blocks = 5
+            from = "lo-test2"
+            type = "swap"
+            token_in = "100uosmo"
+            token_out_min_amount = "1"
+            pool_ids = [1, 1400]
+            denoms = ["uion", "uosmo"]
+
+            [[steps]]
+            at_height = 120
+            from = "lo-test1"
+            type = "wasm_execute"
+            contract = "osmo1contract"
+            msg = '{"ping":{"height":{height}}}'
+
+            [[steps]]
+            at_height = 130
+            from = "lo-test1"
+            type = "custom"
+            args = ["gamm", "join-pool", "--from-key", "{from}"]
+            "#,
+        )
+        .unwrap();
+
+        let args = schedule
+            .steps
+            .iter()
+            .map(|step| step.args(130))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args[0],
+            ["bank", "send", "lo-test1", "osmo1recipient", "1000uosmo"]
+        );
+        assert_eq!(
+            args[1],
+            [
+                "poolmanager",
+                "swap-exact-amount-in",
+                "100uosmo",
+                "1",
+                "--swap-route-pool-ids",
+                "1,1400",
+                "--swap-route-denoms",
+                "uion,uosmo",
+            ]
+        );
+        assert_eq!(
+            args[2],
+            [
+                "wasm",
+                "execute",
+                "osmo1contract",
+                r#"{"ping":{"height":130}}"#
+            ]
+        );
+        assert_eq!(args[3], ["gamm", "join-pool", "--from-key", "lo-test1"]);
+    }
+
+    #[test]
+    fn rejects_malformed_schedules() {
+        for (content, error) in [
+            ("", "Failed to parse schedule"),
+            ("steps = []\nsteps_typo = 1", "Failed to parse schedule"),
+            (
+                "[[steps]]\nat_height = 1\nfrom = \"a\"\ntype = \"teleport\"",
+                "Failed to parse schedule",
+            ),
+            (
+                "[[steps]]\nat_height = 1\ntype = \"custom\"\nargs = []",
+                "Failed to parse schedule",
+            ),
+            (
+                "[[steps]]\nat_height = 1\nfrom = \"a\"\ntype = \"custom\"\nargs = []\n\n[[steps]]\nfrom = \"a\"\ntype = \"custom\"\nargs = []",
+                "Step 2 in schedule.toml needs at_height or every_n_blocks",
+            ),
+        ] {
+            let report = schedule(content).unwrap_err().to_string();
+            assert!(report.contains(error), "{:?}: {}", content, report);
+        }
+
+        assert!(schedule("steps = []").unwrap().steps.is_empty());
+    }
+
+    #[test]
+    fn steps_are_due_at_their_height_or_every_n_blocks() {
+        let step = |at_height, every_n_blocks| Step {
+            name: None,
+            at_height,
+            every_n_blocks,
+            from: "lo-test1".to_string(),
+            tx: Tx::Custom { args: vec![] },
+        };
+
+        let once = step(Some(100), None);
+        assert!(!once.is_due(99));
+        assert!(once.is_due(100));
+        assert!(!once.is_due(101));
+
+        let every_5 = step(None, Some(5));
+        assert!(every_5.is_due(0));
+        assert!(!every_5.is_due(4));
+        assert!(every_5.is_due(5));
+        assert!(every_5.is_due(10));
+
+        // a zero interval never fires rather than dividing by zero
+        let never = step(None, Some(0));
+        assert!((0..10).all(|height| !never.is_due(height)));
+
+        let both = step(Some(7), Some(5));
+        assert!(both.is_due(5));
+        assert!(both.is_due(7));
+        assert!(!both.is_due(8));
+    }
+}
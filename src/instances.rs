@@ -0,0 +1,146 @@
+use std::{path::PathBuf, time::Duration};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    endpoints::{self, Endpoints},
+    platform, readiness,
+};
+
+/// Port offsets of instances are this far apart, leaving room for all of a node's ports
+const OFFSET_STEP: u16 = 10;
+
+/// Named fork with its own home and ports, so several run side by side
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Instance {
+    pub name: String,
+    pub home: PathBuf,
+    pub port_offset: u16,
+}
+
+fn dir() -> PathBuf {
+    platform::home_dir().join(".cache/osmoinplace/instances")
+}
+
+fn path(name: &str) -> PathBuf {
+    dir().join(format!("{}.json", name))
+}
+
+fn all() -> Result<Vec<Instance>> {
+    let Ok(entries) = std::fs::read_dir(dir()) else {
+        return Ok(vec![]);
+    };
+
+    let mut instances = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .map(|entry| {
+            let content = std::fs::read_to_string(entry.path())?;
+            serde_json::from_str(&content)
+                .wrap_err(format!("Invalid instance file: {}", entry.path().display()))
+        })
+        .collect::<Result<Vec<Instance>>>()?;
+    instances.sort_by_key(|instance| instance.port_offset);
+    Ok(instances)
+}
+
+/// Instance `name` as recorded by an earlier run, with `home_dir` and `port_offset` overriding
+/// it when given. New instances default to `~/.osmosisd-<name>` and the lowest port offset no
+/// other instance uses.
+pub fn resolve(
+    name: &str,
+    home_dir: Option<PathBuf>,
+    port_offset: Option<u16>,
+) -> Result<Instance> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(eyre!(
+            "Invalid instance name {:?}, use letters, digits, - and _",
+            name
+        ));
+    }
+
+    let instances = all()?;
+    let recorded = instances.iter().find(|instance| instance.name == name);
+    let port_offset = port_offset
+        .or(recorded.map(|instance| instance.port_offset))
+        .or_else(|| {
+            (1..=endpoints::MAX_PORT_OFFSET / OFFSET_STEP)
+                .map(|i| i * OFFSET_STEP)
+                .find(|offset| instances.iter().all(|other| other.port_offset != *offset))
+        })
+        .ok_or_else(|| eyre!("No free port offset left for instance {}", name))?;
+    if port_offset > endpoints::MAX_PORT_OFFSET {
+        return Err(eyre!(
+            "Port offset {} of instance {} is too large, the highest is {}",
+            port_offset,
+            name,
+            endpoints::MAX_PORT_OFFSET
+        ));
+    }
+    let instance = Instance {
+        name: name.to_string(),
+        home: home_dir
+            .or(recorded.map(|instance| instance.home.clone()))
+            .unwrap_or_else(|| platform::home_dir().join(format!(".osmosisd-{}", name))),
+        port_offset,
+    };
+
+    if let Some(other) = instances
+        .iter()
+        .find(|other| other.name != name && other.port_offset == instance.port_offset)
+    {
+        return Err(eyre!(
+            "Port offset {} is taken by instance {}",
+            instance.port_offset,
+            other.name
+        ));
+    }
+
+    std::fs::create_dir_all(dir()).wrap_err("Failed to create the instances directory")?;
+    std::fs::write(path(name), serde_json::to_string_pretty(&instance)?)
+        .wrap_err(format!("Failed to record instance {}", name))?;
+
+    Ok(instance)
+}
+
+/// Print the known instances with their ports and whether their node answers
+pub async fn list() -> Result<()> {
+    let instances = all()?;
+    if instances.is_empty() {
+        println!("No instances, start one with `osmoinplace --name <name> magic-start`");
+        return Ok(());
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()?;
+    println!(
+        "{:<16} {:>6}  {:<24} {:<24} HOME",
+        "NAME", "OFFSET", "RPC", "STATE"
+    );
+    for instance in instances {
+        let rpc = Endpoints::new(endpoints::DEFAULT_CHAIN_ID, Some(instance.port_offset)).rpc_url();
+        let state = match readiness::status_of(&client, &rpc).await {
+            Ok(status) => format!("{:<24}", format!("height {}", status.latest_block_height))
+                .green()
+                .to_string(),
+            Err(_) => format!("{:<24}", "stopped").dimmed().to_string(),
+        };
+        println!(
+            "{:<16} {:>6}  {:<24} {} {}",
+            instance.name,
+            instance.port_offset,
+            rpc,
+            state,
+            instance.home.display()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+
+use crate::{session::Session, spinner};
+
+/// Mnemonics of the LocalOsmosis test accounts, seeded when no mnemonics file is given
+const DEFAULT_MNEMONICS: &[(&str, &str)] = &[
+    (
+        "lo-test1",
+        "notice oak worry limit wrap speak medal online prefer cluster roof addict wrist behave \
+         treat actual wasp year salad speed social layer crew genius",
+    ),
+    (
+        "lo-test2",
+        "quality vacuum heart guard buzz spike sight swarm shove special gym robust assume sudden \
+         deposit grid alcohol choice devote leader tilt noodle tide penalty",
+    ),
+];
+
+/// Import test mnemonics into the home's test keyring, from the TOML file at `mnemonics` mapping
+/// key names to mnemonics or else the LocalOsmosis test accounts. Keys already in the keyring are
+/// left alone.
+pub fn seed(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    mnemonics: &Option<PathBuf>,
+    session: &Session,
+) -> Result<()> {
+    let mnemonics = match mnemonics {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)
+                .wrap_err(format!("Failed to read mnemonics file: {}", path.display()))?;
+            toml::from_str::<BTreeMap<String, String>>(&content).wrap_err(format!(
+                "Failed to parse mnemonics file: {}",
+                path.display()
+            ))?
+        }
+        None => DEFAULT_MNEMONICS
+            .iter()
+            .map(|(name, mnemonic)| (name.to_string(), mnemonic.to_string()))
+            .collect(),
+    };
+
+    for (name, mnemonic) in &mnemonics {
+        if has_key(osmosisd, osmosis_home, name)? {
+            println!(
+                "{}",
+                format!("✓ {} is already in the keyring.", name).green()
+            );
+            continue;
+        }
+
+        spinner! {
+            session.events,
+            &format!("Importing key {}...", name),
+            &format!("✓ Imported key {}.", name),
+            import(osmosisd, osmosis_home, name, mnemonic)?
+        };
+    }
+
+    Ok(())
+}
+
+fn has_key(osmosisd: &PathBuf, osmosis_home: &PathBuf, name: &str) -> Result<bool> {
+    let status = Command::new(osmosisd)
+        .args(["keys", "show", name])
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--keyring-backend", "test"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .wrap_err("Failed to list keys")?;
+    Ok(status.success())
+}
+
+fn import(osmosisd: &PathBuf, osmosis_home: &PathBuf, name: &str, mnemonic: &str) -> Result<()> {
+    let mut child = Command::new(osmosisd)
+        .args(["keys", "add", name, "--recover"])
+        .arg("--home")
+        .arg(osmosis_home)
+        .args(["--keyring-backend", "test"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .wrap_err(format!("Failed to import key {}", name))?;
+
+    // the mnemonic is prompted for on stdin so it doesn't show up in the process list
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{}", mnemonic)?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to import key {}: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(())
+}
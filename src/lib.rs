@@ -0,0 +1,3136 @@
+#![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.MD"))]
+
+pub mod accounts;
+pub mod backup;
+pub mod binaries;
+pub mod changes;
+pub mod contracts;
+pub mod cosmovisor;
+pub mod daemon;
+pub mod diagnostics;
+pub mod disk;
+pub mod du;
+pub mod endpoints;
+pub mod faucet;
+pub mod fees;
+pub mod follow;
+pub mod gas_report;
+pub mod genesis;
+pub mod gha;
+pub mod gov;
+pub mod health;
+pub mod hooks;
+pub mod impersonate;
+pub mod indexer;
+pub mod inject;
+pub mod instances;
+pub mod keys;
+pub mod maintenance;
+#[cfg(feature = "mock")]
+mod mock;
+pub mod node_config;
+pub mod node_log;
+pub mod notify;
+pub mod object_store;
+mod offline;
+pub mod output;
+pub mod phase;
+mod plan;
+pub mod platform;
+pub mod policy;
+pub mod preserve;
+pub mod profile;
+pub mod proxy;
+pub mod readiness;
+pub mod remote;
+pub mod safe_mode;
+pub mod session;
+pub mod shutdown;
+pub mod snapshot;
+pub mod state_sync;
+pub mod supervisor;
+pub mod sync;
+pub mod testnet;
+pub mod upgrade;
+pub mod webhook;
+
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use color_eyre::eyre::{eyre, Context, Ok, Result};
+use colored::Colorize;
+use endpoints::Endpoints;
+use fees::FeesPreset;
+use hooks::{Hooks, Setup};
+use maintenance::Maintenance;
+use notify::Notify;
+use phase::Phase;
+use policy::Policy;
+use safe_mode::SafeMode;
+pub use session::Session;
+use snapshot::DownloadArgs;
+use state_sync::StateSyncArgs;
+use supervisor::{Event, RestartArgs, Supervisor};
+use sync::SyncArgs;
+pub use testnet::Testnet;
+use upgrade::NextUpgrade;
+
+// used by `spinner!` in other crates
+#[doc(hidden)]
+pub use colored;
+#[doc(hidden)]
+pub use indicatif;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    /// osmosis home directory, defaulted to ~/.osmosisd (/mnt/.osmosisd on GitHub Actions)
+    #[arg(long)]
+    home_dir: Option<PathBuf>,
+
+    /// osmosis binary
+    #[arg(long, default_value = "osmosisd")]
+    osmosisd_bin: PathBuf,
+
+    /// Chain id of the fork, defaults to the one the home was last forked with or edgenet
+    #[arg(long)]
+    chain_id: Option<String>,
+
+    /// Send a notification when long running phases (download, sync, fork) complete or fail
+    #[arg(long, value_enum, default_value_t = Notify::Off)]
+    notify: Notify,
+
+    /// URL to POST a JSON payload to when the node is ready, hits a consensus failure or exits,
+    /// e.g. a Slack or Discord incoming webhook
+    #[arg(long)]
+    notify_url: Option<String>,
+
+    /// Report progress as newline delimited JSON events on stdout instead, for orchestrators and
+    /// dashboards. Anything else is written to stderr then.
+    #[arg(long, value_enum, default_value_t = output::Output::Human)]
+    output: output::Output,
+
+    #[command(flatten)]
+    safe_mode: SafeMode,
+
+    /// Address to serve /healthz and /readyz probes and /metrics on, e.g. 127.0.0.1:8080
+    #[arg(long)]
+    health_addr: Option<SocketAddr>,
+
+    #[command(flatten)]
+    grpc_tls: proxy::GrpcTlsArgs,
+
+    #[command(flatten)]
+    policy: Policy,
+
+    #[command(flatten)]
+    maintenance: Maintenance,
+
+    #[command(flatten)]
+    node_log: node_log::NodeLogArgs,
+
+    /// Run on a remote host instead, e.g. ssh://user@bighost. The tool is copied there and its
+    /// output, the fork's ports and the --on-ready hook stay local.
+    #[arg(long)]
+    target: Option<remote::Target>,
+
+    /// Configure gas prices and the fee market of the fork, and the default fees of the tx helpers
+    #[arg(long, value_enum)]
+    fees_preset: Option<FeesPreset>,
+
+    #[command(flatten)]
+    node_overrides: node_config::OverrideArgs,
+
+    #[command(flatten)]
+    frontend: node_config::FrontendArgs,
+
+    /// Name of an instance to run side by side with others, with its own home (~/.osmosisd-<name>
+    /// unless --home-dir is given) and ports, see `osmoinplace instances list`
+    #[arg(long = "name")]
+    instance: Option<String>,
+
+    /// Shift all ports of the node (RPC 26657, gRPC 9090, REST 1317, P2P 26656, ...) by this much,
+    /// defaults to a free offset for a new --name
+    #[arg(long, value_parser = endpoints::parse_port_offset)]
+    port_offset: Option<u16>,
+
+    /// Config file defining profiles
+    #[arg(long, global = true, default_value = "osmoinplace.toml")]
+    config: PathBuf,
+
+    /// Profile from the config file to take defaults from
+    #[arg(long, global = true)]
+    profile: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Commands {
+    /// Download mainnet state
+    DownloadMainnetState {
+        #[command(flatten)]
+        download_args: DownloadArgs,
+    },
+
+    /// Backup current osmosis state
+    #[command(args_conflicts_with_subcommands = true)]
+    Backup {
+        #[command(subcommand)]
+        command: Option<BackupCommands>,
+
+        /// Path to backup directory or archive, defaults to $HOME/.osmosisd_bak
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// Keep the backup under this name in $HOME/.osmosisd_backups instead of at --path
+        #[arg(long, conflicts_with = "path", value_parser = backup::parse_name)]
+        name: Option<String>,
+
+        #[command(flatten)]
+        backup_args: backup::BackupArgs,
+
+        /// Prune named backups after taking this one
+        #[command(flatten)]
+        retention: backup::Retention,
+    },
+
+    /// Restore osmosis state from a backup
+    Restore {
+        /// Path to backup directory or archive, defaults to $HOME/.osmosisd_bak
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// Restore the backup with this name in $HOME/.osmosisd_backups instead of --path
+        #[arg(long, conflicts_with = "path", value_parser = backup::parse_name)]
+        name: Option<String>,
+
+        /// Don't check the backup against its manifest before restoring it
+        #[arg(long)]
+        skip_verify: bool,
+
+        /// Only restore the chain state (data/ and wasm/), keeping the current config and keys
+        #[arg(long)]
+        data_only: bool,
+    },
+
+    /// Start the node and sync to the latest block
+    StartSync {
+        /// Stop the node on first indexed block events
+        #[arg(short, long)]
+        stop_on_first_indexed_block_events: bool,
+
+        /// Run in the background, see `osmoinplace status`, `logs` and `stop`
+        #[arg(long)]
+        detach: bool,
+
+        #[command(flatten)]
+        sync_args: SyncArgs,
+
+        #[command(flatten)]
+        log_patterns: readiness::LogPatterns,
+    },
+
+    /// Start osmosis in place testnet
+    StartInPlaceTestnet {
+        /// Optional upgrade handler, if set, the chain will be marked to run the upgrade handler when running with the right binary
+        #[arg(long)]
+        upgrade_handler: Option<String>,
+
+        /// New osmosisd binary to use to run the upgrade, a release version like v26.0.0 or
+        /// `git:owner/repo#ref` to build it from source
+        #[arg(long)]
+        new_osmosisd_bin: Option<PathBuf>,
+
+        /// Address to control the fork with, or the name of a key in the home's test keyring,
+        /// defaults to osmo12smx2wdlyttvyzvzg54y2vnqwq2qjateuf7thj
+        #[arg(long)]
+        operator_address: Option<String>,
+
+        #[command(flatten)]
+        fund_args: accounts::FundArgs,
+
+        #[command(flatten)]
+        fast_params: gov::FastParamsArgs,
+
+        #[command(flatten)]
+        deploy_args: contracts::DeployArgs,
+
+        #[command(flatten)]
+        upgrade_chain: upgrade::ChainArgs,
+
+        #[command(flatten)]
+        cosmovisor: cosmovisor::CosmovisorArgs,
+
+        #[command(flatten)]
+        hooks: Hooks,
+
+        /// Run in the background, see `osmoinplace status`, `logs` and `stop`
+        #[arg(long)]
+        detach: bool,
+
+        #[command(flatten)]
+        log_patterns: readiness::LogPatterns,
+
+        #[command(flatten)]
+        restart_args: RestartArgs,
+    },
+
+    /// Start a standalone node
+    StartStandalone {
+        #[command(flatten)]
+        hooks: Hooks,
+
+        /// Target block time, e.g. `1s` or `500ms`, set as the consensus timeouts in config.toml
+        /// so tests needing many blocks don't wait on mainnet's cadence
+        #[arg(long, value_parser = node_config::parse_duration)]
+        block_time: Option<Duration>,
+
+        #[command(flatten)]
+        deploy_args: contracts::DeployArgs,
+
+        /// Run in the background, see `osmoinplace status`, `logs` and `stop`
+        #[arg(long)]
+        detach: bool,
+
+        #[command(flatten)]
+        log_patterns: readiness::LogPatterns,
+
+        #[command(flatten)]
+        restart_args: RestartArgs,
+    },
+
+    /// Resume an in-place testnet that was interrupted after halting for its upgrade
+    Resume {
+        /// New osmosisd binary to run the pending upgrade with
+        #[arg(long)]
+        new_osmosisd_bin: PathBuf,
+
+        #[command(flatten)]
+        hooks: Hooks,
+
+        /// Run in the background, see `osmoinplace status`, `logs` and `stop`
+        #[arg(long)]
+        detach: bool,
+    },
+
+    /// Keep an un-forked copy of mainnet state synced as a warm source for forking
+    FollowMainnet {
+        /// Workspace to keep synced state in, defaults to $HOME/.osmosisd_follower
+        #[arg(long)]
+        workspace: Option<PathBuf>,
+
+        /// Path to copy the synced state to periodically, defaults to $HOME/.osmosisd_follower_bak
+        #[arg(long)]
+        snapshot_path: Option<PathBuf>,
+
+        /// Hours between snapshots of the synced state
+        #[arg(long, default_value_t = 6)]
+        snapshot_interval_hours: u64,
+
+        /// Stop following when free disk space drops below this many GB
+        #[arg(long, default_value_t = 50)]
+        min_free_gb: u64,
+    },
+
+    /// Initialize the home to bootstrap with CometBFT state sync on the next start instead of
+    /// downloading a snapshot, run start-sync afterwards to sync it
+    BootstrapStateSync {
+        #[command(flatten)]
+        state_sync_args: StateSyncArgs,
+
+        /// Genesis url to download instead of the one in the osmosis repo, repeat to add mirrors
+        /// that are tried in order. A `file://` url points to a local genesis file.
+        #[arg(long)]
+        genesis_url: Vec<String>,
+
+        /// JSON file to deep merge on top of the downloaded genesis
+        #[arg(long)]
+        genesis_overrides: Option<PathBuf>,
+    },
+
+    /// Magic start command to perform all setup at once
+    MagicStart {
+        /// Determine whether to download new snapshot or restore from backup
+        #[arg(long, default_value = "false")]
+        download_mainnet_state: bool,
+
+        /// Only used with --download-mainnet-state
+        #[command(flatten)]
+        download_args: DownloadArgs,
+
+        /// Bootstrap the state with CometBFT state sync instead of downloading a snapshot or
+        /// restoring the backup
+        #[arg(long, conflicts_with = "download_mainnet_state")]
+        state_sync: bool,
+
+        /// Only used with --state-sync
+        #[command(flatten)]
+        state_sync_args: StateSyncArgs,
+
+        /// Path to backup directory, defaults to $HOME/.osmosisd_bak
+        #[arg(long)]
+        backup_path: Option<PathBuf>,
+
+        /// Don't check the backup against its manifest before restoring it
+        #[arg(long)]
+        skip_backup_verify: bool,
+
+        /// Optional upgrade handler, if set, the chain will be marked to run the upgrade handler when running with the right binary
+        #[arg(long)]
+        upgrade_handler: Option<String>,
+
+        /// New osmosisd binary to use to run the upgrade, a release version like v26.0.0 or
+        /// `git:owner/repo#ref` to build it from source
+        #[arg(long)]
+        new_osmosisd_bin: Option<PathBuf>,
+
+        /// Address to control the fork with, or the name of a key in the home's test keyring,
+        /// defaults to osmo12smx2wdlyttvyzvzg54y2vnqwq2qjateuf7thj
+        #[arg(long)]
+        operator_address: Option<String>,
+
+        #[command(flatten)]
+        fund_args: accounts::FundArgs,
+
+        #[command(flatten)]
+        fast_params: gov::FastParamsArgs,
+
+        #[command(flatten)]
+        deploy_args: contracts::DeployArgs,
+
+        #[command(flatten)]
+        upgrade_chain: upgrade::ChainArgs,
+
+        #[command(flatten)]
+        cosmovisor: cosmovisor::CosmovisorArgs,
+
+        #[command(flatten)]
+        hooks: Hooks,
+
+        /// Run in the background, see `osmoinplace status`, `logs` and `stop`
+        #[arg(long)]
+        detach: bool,
+
+        /// Print what would be deleted, downloaded and run, without doing any of it
+        #[arg(long, conflicts_with = "detach")]
+        dry_run: bool,
+
+        /// Only run these phases, must be contiguous, defaults to all
+        #[arg(long, value_enum, value_delimiter = ',')]
+        phases: Vec<Phase>,
+
+        /// Skip these phases, the remaining ones must be contiguous
+        #[arg(long, value_enum, value_delimiter = ',')]
+        skip_phase: Vec<Phase>,
+
+        /// Only used in the sync phase
+        #[command(flatten)]
+        sync_args: SyncArgs,
+    },
+
+    /// Write a GitHub Actions composite action that runs the in-place testnet
+    GhaSetup {
+        /// Path to write the composite action to
+        #[arg(long, default_value = ".github/actions/osmoinplace/action.yml")]
+        path: PathBuf,
+    },
+
+    /// Simulate a fixed set of representative transactions and report gas used per message type
+    GasReport {
+        /// Key name in the test keyring to simulate transactions from
+        #[arg(long)]
+        from: String,
+
+        /// Path to write the gas report to
+        #[arg(short, long, default_value = "gas_report.json")]
+        output: PathBuf,
+
+        /// Gas report to compare against, e.g. one taken before the upgrade or with another binary
+        #[arg(long)]
+        compare: Option<PathBuf>,
+    },
+
+    /// Manage a PostgreSQL event indexer for the node
+    Indexer {
+        #[command(subcommand)]
+        command: IndexerCommands,
+    },
+
+    /// Create and manage local snapshots
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+
+    /// Send scheduled transactions to the running fork to generate background activity
+    Inject {
+        /// TOML file with `[[steps]]` to send `at_height` or `every_n_blocks`
+        schedule: PathBuf,
+
+        /// Gas prices for the injected transactions, defaults to the --fees-preset minimum or
+        /// 0.025uosmo
+        #[arg(long)]
+        gas_prices: Option<String>,
+    },
+
+    /// Execute messages as any account on the fork, e.g. a whale or a DAO, needs an osmosisd
+    /// build with `tx impersonate`
+    Impersonate {
+        /// Account to execute the messages as
+        address: String,
+
+        /// JSON file with the message to execute, or a list of them
+        #[arg(long)]
+        msg: PathBuf,
+
+        /// Key in the test keyring, or its address, signing and paying for the tx, defaults to
+        /// the operator's
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Gas prices for the tx, defaults to the --fees-preset minimum or 0.025uosmo
+        #[arg(long)]
+        gas_prices: Option<String>,
+    },
+
+    /// Print the fork's endpoints and chain-id as an env file, e.g. `osmoinplace env > .env.edgenet`
+    Env,
+
+    /// Run osmosisd against the fork, with its home, chain-id, node and test keyring filled in,
+    /// e.g. `osmoinplace exec -- tx bank send lo-test1 osmo1... 1uosmo`
+    Exec {
+        /// Arguments to osmosisd
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    /// Assert the changes an upgrade claims to make against the running node, rehearsals check
+    /// them on their own with --expect-changes
+    AssertChanges {
+        /// TOML manifest of `added_modules`, `removed_modules` and `[[params]]` changes
+        manifest: PathBuf,
+    },
+
+    /// Show disk usage by workspace, component and age with suggestions for what to prune
+    Du,
+
+    /// Stop the instance started with --detach, letting osmosisd close its databases cleanly
+    Stop,
+
+    /// Show whether a node is running for the home, with its height, sync state, chain id,
+    /// osmosisd version and endpoints, and the uptime of an instance started with --detach, as a
+    /// single JSON object with --output json
+    Status,
+
+    /// Wait for the fork's node, failing on timeout, e.g. to script around a detached instance
+    Wait {
+        /// Wait until the node reaches this height
+        #[arg(long, required_unless_present = "ready")]
+        height: Option<u64>,
+
+        /// Wait until the node commits new blocks and is no longer catching up
+        #[arg(long)]
+        ready: bool,
+
+        /// Give up after this many seconds
+        #[arg(long, default_value_t = 300)]
+        timeout_secs: u64,
+    },
+
+    /// Print the logs of the instance started with --detach
+    Logs {
+        /// Keep printing new lines as they are written
+        #[arg(short, long)]
+        follow: bool,
+    },
+
+    /// Manage the local snapshot archive cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+
+    /// Snapshot and restore account balances on a running testnet
+    Accounts {
+        #[command(subcommand)]
+        command: AccountsCommands,
+    },
+
+    /// Send tokens from a key in the test keyring to an address on the running testnet, or serve
+    /// a faucet doing so over HTTP
+    Faucet {
+        /// Address to send to
+        #[arg(required_unless_present = "serve")]
+        address: Option<String>,
+
+        /// Coins to send
+        #[arg(default_value = "100000000uosmo")]
+        amount: String,
+
+        /// Key in the test keyring to send from
+        #[arg(long)]
+        from: String,
+
+        /// Gas prices for the transactions, defaults to the --fees-preset minimum or 0.025uosmo
+        #[arg(long)]
+        gas_prices: Option<String>,
+
+        /// Serve `GET /fund?address=<address>` on this address instead, e.g. 127.0.0.1:8000
+        #[arg(long, conflicts_with = "address")]
+        serve: Option<SocketAddr>,
+
+        /// Coins to send per request with --serve
+        #[arg(long, default_value = "100000000uosmo")]
+        drip: String,
+    },
+
+    /// Manage official osmosisd release binaries, usable as e.g. `--osmosisd-bin v25.0.0`
+    Bin {
+        #[command(subcommand)]
+        command: BinCommands,
+    },
+
+    /// Manage the instances started with --name
+    Instances {
+        #[command(subcommand)]
+        command: InstancesCommands,
+    },
+
+    /// Manage the test keyring of the home
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommands,
+    },
+
+    /// Pass governance proposals on the running fork: submit, vote yes with the validator and
+    /// wait for the voting period to end
+    Gov {
+        #[command(subcommand)]
+        command: GovCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GovCommands {
+    /// Pass a proposal from a JSON file in the `osmosisd tx gov submit-proposal` format
+    Submit {
+        /// Path to the proposal JSON, the minimum deposit is added when it has none
+        proposal: PathBuf,
+
+        #[command(flatten)]
+        gov_args: gov::GovArgs,
+    },
+    /// Pass a software upgrade proposal
+    Upgrade {
+        /// Name of the upgrade, matching the new binary's upgrade handler
+        name: String,
+
+        /// Height to upgrade at, defaults to a safe margin after the voting period ends
+        #[arg(long)]
+        height: Option<u64>,
+
+        #[command(flatten)]
+        gov_args: gov::GovArgs,
+    },
+    /// Pass a legacy x/params change proposal
+    ParamChange {
+        /// Params subspace, e.g. `gamm`
+        subspace: String,
+
+        /// Param key in the subspace
+        key: String,
+
+        /// New value as JSON, e.g. '"10s"' for a duration
+        value: String,
+
+        #[command(flatten)]
+        gov_args: gov::GovArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum InstancesCommands {
+    /// List the instances with their port offset, RPC endpoint, state and home
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+enum BinCommands {
+    /// Download the official release of a version for this platform, verifying its checksum
+    Install {
+        /// Release version, e.g. v25.0.0
+        version: String,
+    },
+    /// List the installed releases, marking the default one
+    List,
+    /// Make an installed release the default osmosisd, used unless --osmosisd-bin names another
+    /// binary
+    Use {
+        /// Release version, e.g. v25.0.0
+        version: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum KeysCommands {
+    /// Import test mnemonics into the test keyring, the LocalOsmosis test accounts by default
+    Seed {
+        /// TOML file mapping key names to mnemonics to import instead
+        #[arg(long)]
+        mnemonics: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum IndexerCommands {
+    /// Launch PostgreSQL, apply the CometBFT psql indexer schema and configure the node to index into it
+    Up {
+        /// Use an existing PostgreSQL database instead of launching one in docker
+        #[arg(long)]
+        dsn: Option<String>,
+
+        /// Name of the PostgreSQL docker container
+        #[arg(long, default_value = "osmoinplace-indexer")]
+        container_name: String,
+
+        /// Host port to expose PostgreSQL on
+        #[arg(long, default_value_t = 5432)]
+        port: u16,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SnapshotCommands {
+    /// Create a snapshot of the current (stopped) fork with a sha256 checksum file, registered in
+    /// the local cache under its height or written to --output to be shared and used with
+    /// --snapshot-file
+    Create {
+        /// Write the snapshot here instead of the local cache, the checksum goes to
+        /// <output>.sha256
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Compression of the archive, the local cache only holds lz4
+        #[arg(long, value_enum, default_value_t = snapshot::Format::Lz4, requires = "output")]
+        format: snapshot::Format,
+
+        /// Pack the whole home including config and keys instead of only data/ and wasm/
+        #[arg(long)]
+        full_home: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BackupCommands {
+    /// List named backups with their creation time, source height and size
+    List,
+
+    /// Delete a named backup
+    Delete {
+        /// Name of the backup
+        #[arg(value_parser = backup::parse_name)]
+        name: String,
+    },
+
+    /// Remove the oldest named backups beyond --keep or older than --max-age-days
+    Prune {
+        #[command(flatten)]
+        retention: backup::Retention,
+
+        /// Only list the backups that would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Check every file of a backup against its manifest, recording the checksums of the files
+    /// not hashed yet
+    Verify {
+        /// Path to backup directory or archive, defaults to $HOME/.osmosisd_bak
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// Verify the backup with this name in $HOME/.osmosisd_backups instead of --path
+        #[arg(long, conflicts_with = "path", value_parser = backup::parse_name)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommands {
+    /// List cached snapshot archives
+    List,
+
+    /// Remove cached snapshot archives
+    Clean {
+        /// Heights of the snapshots to remove, all of them if none are given
+        height: Vec<u64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AccountsCommands {
+    /// Record current balances of the given addresses
+    Snapshot {
+        /// Addresses to record balances of
+        #[arg(required = true)]
+        addresses: Vec<String>,
+
+        /// Path to snapshot file, defaults to $OSMOSIS_HOME/accounts_snapshot.json
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+
+    /// Re-fund snapshotted addresses back to their recorded balances
+    Restore {
+        /// Path to snapshot file, defaults to $OSMOSIS_HOME/accounts_snapshot.json
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// Key name in the test keyring to fund accounts from
+        #[arg(long)]
+        from: String,
+
+        /// Gas prices for the funding transactions, defaults to the --fees-preset minimum or
+        /// 0.025uosmo
+        #[arg(long)]
+        gas_prices: Option<String>,
+    },
+}
+
+const GENESIS_URL: &str =
+    "https://github.com/osmosis-labs/osmosis/raw/main/networks/osmosis-1/genesis.json";
+
+/// Run the osmoinplace CLI on the arguments of the process
+pub async fn run() -> Result<()> {
+    #[cfg(feature = "mock")]
+    if mock::is_mock_invocation() {
+        return mock::run(std::env::args().skip(1).collect());
+    }
+
+    let matches = with_env(Cli::command()).get_matches();
+    let mut cli = Cli::from_arg_matches(&matches)?;
+
+    if let Some(name) = &cli.profile {
+        let profile = profile::load(&cli.config, name)?;
+        apply_profile(&mut cli, &matches, profile)?;
+    }
+
+    if let Some(target) = &cli.target {
+        let on_ready = match &cli.command {
+            Commands::StartInPlaceTestnet { hooks, .. }
+            | Commands::StartStandalone { hooks, .. }
+            | Commands::Resume { hooks, .. }
+            | Commands::MagicStart { hooks, .. } => hooks.on_ready.clone(),
+            _ => None,
+        };
+        // the remote run gets the offset resolved here, so it listens on the forwarded ports
+        let port_offset = match &cli.instance {
+            Some(name) => Some(instances::resolve(name, None, cli.port_offset)?.port_offset),
+            None => cli.port_offset,
+        };
+        let profile = match &cli.profile {
+            Some(name) => Some((name.clone(), profile::load_table(&cli.config, name)?)),
+            None => None,
+        };
+        let endpoints = Endpoints::resolve(cli.chain_id.as_deref(), None, port_offset);
+        return cli.notify.phase(
+            "Remote run",
+            remote::run(target, on_ready, profile, &cli.policy, &endpoints),
+        );
+    }
+
+    let events = output::Events::init(cli.output)?;
+    shutdown::listen().wrap_err("Failed to install signal handlers")?;
+    let result = run_cmd(cli, events.clone()).await;
+    shutdown::remove_temp_files();
+    events.finish(&result);
+    result
+}
+
+/// Failure of a run that should exit the process with its code, e.g. osmosisd's code passed
+/// through by `exec`. Only `main` exits, once the run cleaned up after itself.
+#[derive(Debug)]
+pub struct ExitCode(pub i32);
+
+impl std::fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Exited with code {}", self.0)
+    }
+}
+
+impl std::error::Error for ExitCode {}
+
+/// Give every flag an environment variable counterpart named after its field, e.g.
+/// OSMOINPLACE_HOME_DIR for --home-dir, and prefixed with the subcommand for the flags of one,
+/// e.g. OSMOINPLACE_MAGIC_START_UPGRADE_HANDLER. Flags given on the command line win over the
+/// environment.
+fn with_env(cmd: clap::Command) -> clap::Command {
+    with_env_prefix(cmd, "OSMOINPLACE")
+}
+
+fn with_env_prefix(cmd: clap::Command, prefix: &str) -> clap::Command {
+    let subcommands = cmd
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect::<Vec<_>>();
+
+    let mut cmd = cmd.mut_args(|arg| {
+        if arg.get_long().is_none() || matches!(arg.get_id().as_str(), "help" | "version") {
+            return arg;
+        }
+        let name = format!("{}_{}", prefix, arg.get_id().as_str().to_uppercase());
+        // set for hooks, which would otherwise leak into osmoinplace commands they run
+        if name == "OSMOINPLACE_CHAIN_ID" {
+            return arg;
+        }
+        arg.env(name)
+    });
+    for name in subcommands {
+        let prefix = format!("{}_{}", prefix, name.to_uppercase().replace('-', "_"));
+        cmd = cmd.mut_subcommand(&name, |subcommand| with_env_prefix(subcommand, &prefix));
+    }
+    cmd
+}
+
+/// Fill in options that were not given on the command line from the profile
+fn apply_profile(cli: &mut Cli, matches: &ArgMatches, profile: profile::Profile) -> Result<()> {
+    // flags set in the environment win over the profile too
+    let from_cli = |id: &str| {
+        matches!(
+            matches.value_source(id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+    };
+
+    // the flags of the subcommand are in its own matches
+    let from_subcommand_cli = |id: &str| {
+        matches.subcommand().is_some_and(|(_, matches)| {
+            matches!(
+                matches.value_source(id),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            )
+        })
+    };
+
+    // borrows the whole profile, so it goes before the fields are moved out of it
+    match &mut cli.command {
+        Commands::StartInPlaceTestnet {
+            upgrade_handler,
+            new_osmosisd_bin,
+            operator_address,
+            fund_args,
+            fast_params,
+            deploy_args,
+            upgrade_chain,
+            cosmovisor,
+            hooks,
+            ..
+        }
+        | Commands::MagicStart {
+            upgrade_handler,
+            new_osmosisd_bin,
+            operator_address,
+            fund_args,
+            fast_params,
+            deploy_args,
+            upgrade_chain,
+            cosmovisor,
+            hooks,
+            ..
+        } => {
+            if upgrade_handler.is_none() {
+                *upgrade_handler = profile.upgrade_handler.clone();
+            }
+            if new_osmosisd_bin.is_none() {
+                *new_osmosisd_bin = profile.new_osmosisd_bin.clone();
+            }
+            if operator_address.is_none() {
+                *operator_address = profile.operator_address.clone();
+            }
+            if upgrade_chain.then_upgrade.is_empty() {
+                for upgrade in profile.then_upgrade.iter().flatten() {
+                    let upgrade = upgrade::parse_chained_upgrade(upgrade)
+                        .map_err(|e| eyre!("Invalid then_upgrade in profile: {}", e))?;
+                    upgrade_chain.then_upgrade.push(upgrade);
+                }
+            }
+            if let Some(enabled) = profile
+                .cosmovisor
+                .filter(|_| !from_subcommand_cli("cosmovisor"))
+            {
+                cosmovisor.cosmovisor = enabled;
+            }
+            if fund_args.fund.is_empty() {
+                for funding in profile.fund.iter().flatten() {
+                    let funding = accounts::parse_funding(funding)
+                        .map_err(|e| eyre!("Invalid fund in profile: {}", e))?;
+                    fund_args.fund.push(funding);
+                }
+            }
+            if fund_args.accounts_file.is_none() {
+                fund_args.accounts_file = profile.accounts_file.clone();
+            }
+            if fund_args.fund_from.is_none() {
+                fund_args.fund_from = profile.fund_from.clone();
+            }
+            if let Some(fast_gov) = profile
+                .fast_gov
+                .filter(|_| !from_subcommand_cli("fast_gov"))
+            {
+                fast_params.fast_gov = fast_gov;
+            }
+            if let Some(short_unbonding) = profile
+                .short_unbonding
+                .filter(|_| !from_subcommand_cli("short_unbonding"))
+            {
+                fast_params.short_unbonding = short_unbonding;
+            }
+            if let Some(fast_epochs) = profile
+                .fast_epochs
+                .filter(|_| !from_subcommand_cli("fast_epochs"))
+            {
+                fast_params.fast_epochs = fast_epochs;
+            }
+            if deploy_args.deploy_contracts.is_none() {
+                deploy_args.deploy_contracts = profile.deploy_contracts.clone();
+            }
+            hooks.apply_profile(&profile);
+        }
+        Commands::StartStandalone { hooks, .. } | Commands::Resume { hooks, .. } => {
+            hooks.apply_profile(&profile);
+        }
+        _ => {}
+    }
+
+    if cli.home_dir.is_none() {
+        cli.home_dir = profile.home_dir;
+    }
+    if cli.chain_id.is_none() {
+        cli.chain_id = profile.chain_id;
+    }
+    if let Some(osmosisd_bin) = profile.osmosisd_bin.filter(|_| !from_cli("osmosisd_bin")) {
+        cli.osmosisd_bin = osmosisd_bin;
+    }
+    if let Some(notify) = profile.notify.filter(|_| !from_cli("notify")) {
+        cli.notify = notify;
+    }
+    if cli.notify_url.is_none() {
+        cli.notify_url = profile.notify_url;
+    }
+    if let Some(safe) = profile.safe.filter(|_| !from_cli("safe")) {
+        cli.safe_mode.safe = safe;
+    }
+    if let Some(offline) = profile.offline.filter(|_| !from_cli("offline")) {
+        cli.policy.offline = offline;
+    }
+    if cli.fees_preset.is_none() {
+        cli.fees_preset = profile.fees_preset;
+    }
+    if let Some(strict) = profile.strict.filter(|_| !from_cli("strict")) {
+        cli.policy.strict = strict;
+    }
+    if let Some(patterns) = profile
+        .strict_pattern
+        .filter(|_| !from_cli("strict_pattern"))
+    {
+        cli.policy.strict_pattern = patterns;
+    }
+    if let Some(secs) = profile
+        .network_timeout_secs
+        .filter(|_| !from_cli("network_timeout_secs"))
+    {
+        cli.policy.network_timeout_secs = secs;
+    }
+    if let Some(retries) = profile
+        .network_retries
+        .filter(|_| !from_cli("network_retries"))
+    {
+        cli.policy.network_retries = retries;
+    }
+    if let Some(secs) = profile
+        .process_timeout_secs
+        .filter(|_| !from_cli("process_timeout_secs"))
+    {
+        cli.policy.process_timeout_secs = secs;
+    }
+    if let Some(secs) = profile
+        .shutdown_timeout_secs
+        .filter(|_| !from_cli("shutdown_timeout_secs"))
+    {
+        cli.policy.shutdown_timeout_secs = secs;
+    }
+    if let Some(secs) = profile
+        .hook_timeout_secs
+        .filter(|_| !from_cli("hook_timeout_secs"))
+    {
+        cli.policy.hook_timeout_secs = secs;
+    }
+    if let Some(delay) = profile
+        .network_retry_delay_secs
+        .filter(|_| !from_cli("network_retry_delay_secs"))
+    {
+        cli.policy.network_retry_delay_secs = delay;
+    }
+    if cli.policy.proxy.is_none() {
+        cli.policy.proxy = profile.proxy;
+    }
+    if let Some(retries) = profile.hook_retries.filter(|_| !from_cli("hook_retries")) {
+        cli.policy.hook_retries = retries;
+    }
+    if let Some(hours) = profile
+        .maintenance_interval_hours
+        .filter(|_| !from_cli("maintenance_interval_hours"))
+    {
+        cli.maintenance.maintenance_interval_hours = hours;
+    }
+    if let Some(keep_recent) = profile
+        .maintenance_keep_recent
+        .filter(|_| !from_cli("maintenance_keep_recent"))
+    {
+        cli.maintenance.maintenance_keep_recent = keep_recent;
+    }
+
+    match &mut cli.command {
+        Commands::Backup { path, .. } | Commands::Restore { path, .. } if path.is_none() => {
+            *path = profile.backup_path;
+        }
+        Commands::MagicStart { backup_path, .. } if backup_path.is_none() => {
+            *backup_path = profile.backup_path;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+async fn run_cmd(mut cli: Cli, events: output::Events) -> Result<()> {
+    // managing the binaries doesn't need one to be installed
+    if let Commands::Bin { command } = &cli.command {
+        return match command {
+            BinCommands::Install { version } => binaries::install(version, &cli.policy).await,
+            BinCommands::List => binaries::list(),
+            BinCommands::Use { version } => binaries::use_version(version),
+        };
+    }
+    if let Commands::Instances { command } = &cli.command {
+        return match command {
+            InstancesCommands::List => instances::list().await,
+        };
+    }
+
+    // versions like v25.0.0 name managed binaries, git:owner/repo#ref is built from source
+    let osmosisd = binaries::resolve(&cli.osmosisd_bin)?;
+    // a dry run only lists the builds
+    let dry_run = matches!(cli.command, Commands::MagicStart { dry_run: true, .. });
+    match &mut cli.command {
+        Commands::StartInPlaceTestnet {
+            new_osmosisd_bin,
+            upgrade_chain,
+            ..
+        }
+        | Commands::MagicStart {
+            new_osmosisd_bin,
+            upgrade_chain,
+            ..
+        } => {
+            for binary in new_osmosisd_bin
+                .iter_mut()
+                .chain(
+                    upgrade_chain
+                        .then_upgrade
+                        .iter_mut()
+                        .map(|(_, binary)| binary),
+                )
+                .filter(|binary| !(dry_run && binaries::is_git(binary)))
+            {
+                *binary = binaries::resolve_or_build(binary, &cli.policy, &events).await?;
+            }
+        }
+        Commands::Resume {
+            new_osmosisd_bin, ..
+        } => {
+            *new_osmosisd_bin =
+                binaries::resolve_or_build(new_osmosisd_bin, &cli.policy, &events).await?
+        }
+        _ => {}
+    }
+
+    // Check if osmosisd exists
+    #[cfg(feature = "mock")]
+    let osmosisd = if osmosisd == Path::new("mock") {
+        mock::mock_bin()?
+    } else {
+        osmosisd
+    };
+    if which::which(osmosisd.as_os_str()).is_err() {
+        return Err(eyre!("osmosisd not found in PATH"));
+    }
+
+    // Check that binaries are built for this platform before spending hours on earlier phases
+    platform::check_binary(&osmosisd)?;
+    if let Commands::StartInPlaceTestnet {
+        new_osmosisd_bin: Some(new_osmosisd_bin),
+        ..
+    }
+    | Commands::MagicStart {
+        new_osmosisd_bin: Some(new_osmosisd_bin),
+        ..
+    }
+    | Commands::Resume {
+        new_osmosisd_bin, ..
+    } = &cli.command
+    {
+        if !binaries::is_git(new_osmosisd_bin) {
+            platform::check_binary(new_osmosisd_bin)?;
+        }
+    }
+
+    // a named instance keeps its home and ports across runs
+    let (home_dir, port_offset) = match &cli.instance {
+        Some(name) => {
+            let instance = instances::resolve(name, cli.home_dir, cli.port_offset)?;
+            (Some(instance.home), Some(instance.port_offset))
+        }
+        None => (cli.home_dir, cli.port_offset),
+    };
+    let osmosis_home = home_dir.unwrap_or_else(|| {
+        if gha::is_github_actions() {
+            PathBuf::from(gha::GHA_HOME_DIR)
+        } else {
+            platform::home_dir().join(".osmosisd")
+        }
+    });
+
+    let detach = matches!(
+        cli.command,
+        Commands::StartSync { detach: true, .. }
+            | Commands::StartInPlaceTestnet { detach: true, .. }
+            | Commands::StartStandalone { detach: true, .. }
+            | Commands::Resume { detach: true, .. }
+            | Commands::MagicStart { detach: true, .. }
+    );
+    if detach {
+        // the detached run has no terminal to confirm wiping anything on, only magic-start wipes
+        if matches!(cli.command, Commands::MagicStart { .. }) && !cli.safe_mode.yes {
+            return Err(eyre!(
+                "magic-start --detach runs without a terminal to confirm wiping on, pass -y"
+            ));
+        }
+        return daemon::detach(&osmosis_home);
+    }
+
+    let mut session = Session {
+        endpoints: Endpoints::resolve(cli.chain_id.as_deref(), Some(&osmosis_home), port_offset),
+        // explicit --set overrides win over the shortcuts
+        overrides: [
+            cli.frontend.overrides(),
+            cli.node_overrides.overrides.clone(),
+        ]
+        .concat(),
+        webhook: None,
+        events,
+        stop: shutdown::Stop::default(),
+        cosmovisor: match &cli.command {
+            Commands::StartInPlaceTestnet { cosmovisor, .. }
+            | Commands::MagicStart { cosmovisor, .. } => cosmovisor.cosmovisor,
+            _ => false,
+        },
+    };
+    if dry_run {
+        return plan::magic_start(
+            &cli.command,
+            &osmosisd,
+            &osmosis_home,
+            &cli.policy,
+            &session,
+        )
+        .await;
+    }
+
+    node_log::init(&cli.node_log)?;
+    session.webhook = cli
+        .notify_url
+        .as_deref()
+        .map(|url| webhook::Webhook::new(url, &cli.policy))
+        .transpose()?;
+    let session = &session;
+
+    if let Some(health_addr) = cli.health_addr {
+        health::serve(health_addr).await?;
+    }
+
+    let runs_node = matches!(
+        cli.command,
+        Commands::StartInPlaceTestnet { .. }
+            | Commands::StartStandalone { .. }
+            | Commands::MagicStart { .. }
+            | Commands::Resume { .. }
+    );
+    if cli.grpc_tls.grpc_tls && runs_node {
+        let ca_path = proxy::serve_grpc_tls(&cli.grpc_tls, &session.endpoints).await?;
+        println!(
+            "{} https://{} (CA: {})",
+            "gRPC TLS endpoint:".green(),
+            cli.grpc_tls.grpc_tls_addr,
+            ca_path.display()
+        );
+    }
+
+    if cli.policy.offline {
+        offline::check(&cli.command, &osmosis_home)?;
+    }
+
+    let notify = cli.notify;
+    let safe_mode = cli.safe_mode;
+    let policy = cli.policy;
+    let maintenance = cli.maintenance;
+    let fees_preset = cli.fees_preset;
+    let configure_fees = || match fees_preset {
+        Some(fees_preset) => fees_preset.configure(&osmosis_home),
+        None => Ok(()),
+    };
+
+    // magic-start configures fees once its state is in place
+    if matches!(
+        cli.command,
+        Commands::StartInPlaceTestnet { .. }
+            | Commands::StartStandalone { .. }
+            | Commands::Resume { .. }
+    ) {
+        configure_fees()?;
+    }
+
+    match &cli.command {
+        Commands::DownloadMainnetState { download_args } => {
+            notify.phase(
+                "Download mainnet state",
+                download_mainnet_state(
+                    &osmosisd,
+                    &osmosis_home,
+                    download_args,
+                    &safe_mode,
+                    &policy,
+                    session,
+                )
+                .await,
+            )?;
+        }
+        Commands::BootstrapStateSync {
+            state_sync_args,
+            genesis_url,
+            genesis_overrides,
+        } => notify.phase(
+            "Bootstrap state sync",
+            state_sync::bootstrap(
+                &osmosisd,
+                &osmosis_home,
+                state_sync_args,
+                genesis_url,
+                genesis_overrides,
+                &safe_mode,
+                &policy,
+                session,
+            )
+            .await,
+        )?,
+        Commands::Backup {
+            command: Some(command),
+            ..
+        } => match command {
+            BackupCommands::List => backup::list()?,
+            BackupCommands::Delete { name } => backup::delete(name, &safe_mode)?,
+            BackupCommands::Prune { retention, dry_run } => {
+                if !retention.is_set() {
+                    return Err(eyre!("Pass --keep and/or --max-age-days to prune"));
+                }
+                backup::prune(retention, *dry_run, &safe_mode)?
+            }
+            BackupCommands::Verify { path, name } => {
+                let path = backup::named_or(path, name).unwrap_or_else(backup::default_path);
+                verify_backup(&path, true, session).await?
+            }
+        },
+        Commands::Backup {
+            command: None,
+            path,
+            name,
+            backup_args,
+            retention,
+        } => {
+            let path = backup::named_or(path, name);
+            match path.as_deref().and_then(object_store::Location::parse) {
+                Some(location) => {
+                    upload_backup(&osmosis_home, &location, backup_args, &policy, session).await?
+                }
+                None => {
+                    backup(
+                        &osmosisd,
+                        &osmosis_home,
+                        path,
+                        backup_args,
+                        &safe_mode,
+                        session,
+                    )
+                    .await?;
+                }
+            }
+
+            if retention.is_set() {
+                backup::prune(retention, false, &safe_mode)?;
+            }
+        }
+        Commands::Restore {
+            path,
+            name,
+            skip_verify,
+            data_only,
+        } => {
+            let path = backup::named_or(path, name);
+            restore(
+                &osmosis_home,
+                path,
+                *skip_verify,
+                *data_only,
+                &safe_mode,
+                &policy,
+                session,
+            )
+            .await?
+        }
+        Commands::StartSync {
+            stop_on_first_indexed_block_events,
+            sync_args,
+            log_patterns,
+            ..
+        } => notify.phase(
+            "Sync",
+            start_sync(
+                &osmosisd,
+                &osmosis_home,
+                *stop_on_first_indexed_block_events,
+                sync_args,
+                log_patterns,
+                &policy,
+                session,
+            )
+            .await,
+        )?,
+        Commands::StartInPlaceTestnet {
+            upgrade_handler,
+            new_osmosisd_bin,
+            operator_address,
+            fund_args,
+            fast_params,
+            deploy_args,
+            upgrade_chain,
+            cosmovisor,
+            hooks,
+            log_patterns,
+            restart_args,
+            ..
+        } => {
+            let upgrade_chain = upgrade_chain.resolve(
+                operator_address.as_deref(),
+                fees::gas_prices(fees_preset, &None),
+            );
+            let setup = Setup {
+                fast_params: fast_params.resolve(
+                    &osmosisd,
+                    operator_address.as_deref(),
+                    fees::gas_prices(fees_preset, &None),
+                )?,
+                funding: fund_args.resolve(
+                    operator_address.as_deref(),
+                    fees::gas_prices(fees_preset, &None),
+                )?,
+                contracts: deploy_args.resolve(fees::gas_prices(fees_preset, &None))?,
+                next_upgrade: None,
+            };
+            if cosmovisor.cosmovisor {
+                cosmovisor::layout(
+                    &osmosis_home,
+                    Some(&osmosisd),
+                    &upgrades(upgrade_handler, new_osmosisd_bin, &upgrade_chain),
+                )?;
+            }
+            notify.phase(
+                "In-place testnet",
+                hooks::on_exit(
+                    hooks,
+                    &osmosis_home,
+                    &policy,
+                    session,
+                    start_in_place_testnet(
+                        &osmosisd,
+                        &osmosis_home,
+                        upgrade_handler,
+                        new_osmosisd_bin,
+                        &upgrade_chain,
+                        &accounts::operator_address(
+                            &osmosisd,
+                            &osmosis_home,
+                            operator_address.as_deref(),
+                        )?,
+                        &setup,
+                        hooks,
+                        log_patterns,
+                        restart_args,
+                        &policy,
+                        &maintenance,
+                        session,
+                    )
+                    .await,
+                )
+                .await,
+            )?
+        }
+        Commands::StartStandalone {
+            hooks,
+            block_time,
+            deploy_args,
+            log_patterns,
+            restart_args,
+            ..
+        } => {
+            let setup = Setup {
+                contracts: deploy_args.resolve(fees::gas_prices(fees_preset, &None))?,
+                ..Setup::default()
+            };
+            if let Some(block_time) = block_time {
+                node_config::set_block_time(&osmosis_home, *block_time)?;
+            }
+            hooks::on_exit(
+                hooks,
+                &osmosis_home,
+                &policy,
+                session,
+                start_standalone(
+                    &osmosisd,
+                    &osmosis_home,
+                    &setup,
+                    &hooks.without_upgrade(),
+                    log_patterns,
+                    restart_args,
+                    &policy,
+                    &maintenance,
+                    session,
+                )
+                .await,
+            )
+            .await?
+        }
+        Commands::Resume {
+            new_osmosisd_bin,
+            hooks,
+            ..
+        } => {
+            hooks::on_exit(
+                hooks,
+                &osmosis_home,
+                &policy,
+                session,
+                resume(
+                    &osmosis_home,
+                    new_osmosisd_bin,
+                    hooks,
+                    &policy,
+                    &maintenance,
+                    session,
+                )
+                .await,
+            )
+            .await?
+        }
+        Commands::FollowMainnet {
+            workspace,
+            snapshot_path,
+            snapshot_interval_hours,
+            min_free_gb,
+        } => {
+            let home = platform::home_dir();
+            let workspace = workspace
+                .clone()
+                .unwrap_or_else(|| home.join(".osmosisd_follower"));
+            let snapshot_path = snapshot_path
+                .clone()
+                .unwrap_or_else(|| home.join(".osmosisd_follower_bak"));
+
+            follow::follow_mainnet(
+                &osmosisd,
+                &workspace,
+                &snapshot_path,
+                Duration::from_secs(snapshot_interval_hours * 60 * 60),
+                min_free_gb * 1_000_000_000,
+                &safe_mode,
+                &policy,
+                session,
+            )
+            .await?
+        }
+        Commands::MagicStart {
+            download_mainnet_state: download,
+            download_args,
+            state_sync,
+            state_sync_args,
+            backup_path,
+            skip_backup_verify,
+            upgrade_handler,
+            new_osmosisd_bin,
+            operator_address,
+            fund_args,
+            fast_params,
+            deploy_args,
+            upgrade_chain,
+            cosmovisor,
+            hooks,
+            phases,
+            skip_phase,
+            sync_args,
+            ..
+        } => {
+            let phases = phase::select(phases, skip_phase)?;
+            let upgrade_chain = upgrade_chain.resolve(
+                operator_address.as_deref(),
+                fees::gas_prices(fees_preset, &None),
+            );
+            // fail on a bad accounts file before spending hours on the earlier phases
+            let setup = Setup {
+                fast_params: fast_params.resolve(
+                    &osmosisd,
+                    operator_address.as_deref(),
+                    fees::gas_prices(fees_preset, &None),
+                )?,
+                funding: fund_args.resolve(
+                    operator_address.as_deref(),
+                    fees::gas_prices(fees_preset, &None),
+                )?,
+                contracts: deploy_args.resolve(fees::gas_prices(fees_preset, &None))?,
+                next_upgrade: None,
+            };
+
+            if phases.contains(&Phase::Download) {
+                if *state_sync {
+                    notify.phase(
+                        "Bootstrap state sync",
+                        state_sync::bootstrap(
+                            &osmosisd,
+                            &osmosis_home,
+                            state_sync_args,
+                            &download_args.genesis_url,
+                            &download_args.genesis_overrides,
+                            &safe_mode,
+                            &policy,
+                            session,
+                        )
+                        .await,
+                    )?;
+                } else if *download {
+                    notify.phase(
+                        "Download mainnet state",
+                        download_mainnet_state(
+                            &osmosisd,
+                            &osmosis_home,
+                            download_args,
+                            &safe_mode,
+                            &policy,
+                            session,
+                        )
+                        .await,
+                    )?;
+                } else {
+                    // catch a binary that can't run the backed up state before wiping the home
+                    // for it, archives only tell the height once restored
+                    let check_version = !download_args.skip_version_check;
+                    let backup_height = snapshot::read_metadata(
+                        &backup_path.clone().unwrap_or_else(backup::default_path),
+                    )
+                    .and_then(|s| s.height);
+                    if let (Some(height), true) = (backup_height, check_version) {
+                        upgrade::verify_binary_for_height(&osmosisd, height)?;
+                    }
+                    restore(
+                        &osmosis_home,
+                        backup_path.clone(),
+                        *skip_backup_verify,
+                        false,
+                        &safe_mode,
+                        &policy,
+                        session,
+                    )
+                    .await?;
+                    let height = snapshot::read_metadata(&osmosis_home).and_then(|s| s.height);
+                    if let (None, Some(height), true) = (backup_height, height, check_version) {
+                        upgrade::verify_binary_for_height(&osmosisd, height)?;
+                    }
+                }
+            }
+
+            configure_fees()?;
+
+            // sync the chain to first block after snapshot
+            if phases.contains(&Phase::Sync) {
+                notify.phase(
+                    "Sync",
+                    start_sync(
+                        &osmosisd,
+                        &osmosis_home,
+                        true,
+                        sync_args,
+                        &readiness::LogPatterns::default(),
+                        &policy,
+                        session,
+                    )
+                    .await,
+                )?;
+            }
+
+            // start the node, the upgrade is run right after the fork halts if both are selected
+            let run_upgrade = phases.contains(&Phase::Upgrade);
+            if cosmovisor.cosmovisor {
+                // without the fork phase, the node already halted for the triggered upgrade
+                cosmovisor::layout(
+                    &osmosis_home,
+                    Some(osmosisd.as_path()).filter(|_| phases.contains(&Phase::Fork)),
+                    &upgrades(upgrade_handler, new_osmosisd_bin, &upgrade_chain),
+                )?;
+            }
+            if phases.contains(&Phase::Fork) {
+                notify.phase(
+                    "In-place testnet",
+                    hooks::on_exit(
+                        hooks,
+                        &osmosis_home,
+                        &policy,
+                        session,
+                        start_in_place_testnet(
+                            &osmosisd,
+                            &osmosis_home,
+                            upgrade_handler,
+                            &new_osmosisd_bin.clone().filter(|_| run_upgrade),
+                            &upgrade_chain,
+                            &accounts::operator_address(
+                                &osmosisd,
+                                &osmosis_home,
+                                operator_address.as_deref(),
+                            )?,
+                            &setup,
+                            hooks,
+                            &readiness::LogPatterns::default(),
+                            &RestartArgs::default(),
+                            &policy,
+                            &maintenance,
+                            session,
+                        )
+                        .await,
+                    )
+                    .await,
+                )?;
+            } else if run_upgrade {
+                let new_osmosisd_bin = new_osmosisd_bin
+                    .as_ref()
+                    .ok_or_else(|| eyre!("--new-osmosisd-bin is required for the upgrade phase"))?;
+                notify.phase(
+                    "Upgrade",
+                    hooks::on_exit(
+                        hooks,
+                        &osmosis_home,
+                        &policy,
+                        session,
+                        run_upgrades(
+                            new_osmosisd_bin,
+                            &upgrade_chain,
+                            &osmosis_home,
+                            &setup,
+                            hooks,
+                            &readiness::LogPatterns::default(),
+                            &RestartArgs::default(),
+                            &policy,
+                            &maintenance,
+                            session,
+                        )
+                        .await,
+                    )
+                    .await,
+                )?;
+            }
+        }
+        Commands::GhaSetup { path } => gha::setup(path)?,
+        Commands::GasReport {
+            from,
+            output,
+            compare,
+        } => gas_report::run(
+            &osmosisd,
+            &osmosis_home,
+            from,
+            output,
+            compare,
+            &session.endpoints,
+        )?,
+        Commands::Indexer { command } => match command {
+            IndexerCommands::Up {
+                dsn,
+                container_name,
+                port,
+            } => indexer::up(&osmosis_home, dsn, container_name, *port, &policy, session).await?,
+        },
+        Commands::Snapshot { command } => match command {
+            SnapshotCommands::Create {
+                output,
+                format,
+                full_home,
+            } => {
+                let output = match output {
+                    Some(output) => output.clone(),
+                    // cached under its height, so runs resolving to it find it
+                    None => {
+                        let height = backup::height(&osmosis_home).ok_or_else(|| {
+                            eyre!(
+                                "Unknown height of {}, pass --output",
+                                osmosis_home.display()
+                            )
+                        })?;
+                        snapshot::cached_path(height)
+                    }
+                };
+                snapshot::create(&osmosis_home, &output, *format, *full_home, session).await?
+            }
+        },
+        Commands::Impersonate {
+            address,
+            msg,
+            from,
+            gas_prices,
+        } => {
+            impersonate::run(
+                &osmosisd,
+                &osmosis_home,
+                address,
+                msg,
+                from.as_deref()
+                    .unwrap_or(accounts::DEFAULT_OPERATOR_ADDRESS),
+                &fees::gas_prices(fees_preset, gas_prices),
+                session,
+            )
+            .await?;
+        }
+        Commands::Inject {
+            schedule,
+            gas_prices,
+        } => {
+            let schedule = inject::load(schedule)?;
+            let gas_prices = fees::gas_prices(fees_preset, gas_prices);
+            inject::run(
+                &osmosisd,
+                &osmosis_home,
+                &schedule,
+                &gas_prices,
+                &session.endpoints,
+            )
+            .await?
+        }
+        Commands::AssertChanges { manifest } => {
+            let manifest = changes::load(manifest)?;
+            changes::check(&osmosisd, &osmosis_home, &manifest)?
+        }
+        Commands::Du => du::report(&osmosis_home)?,
+        Commands::Stop => daemon::stop(&osmosis_home, policy.shutdown_timeout())?,
+        Commands::Status => daemon::status(&osmosis_home, &osmosisd, session).await?,
+        Commands::Wait {
+            height,
+            ready,
+            timeout_secs,
+        } => {
+            readiness::wait(
+                &session.endpoints,
+                *height,
+                *ready,
+                Duration::from_secs(*timeout_secs),
+            )
+            .await?
+        }
+        Commands::Logs { follow } => daemon::logs(&osmosis_home, *follow)?,
+        Commands::Env => print!("{}", session.endpoints.env(&osmosis_home)),
+        Commands::Exec { args } => {
+            let status = Command::new(&osmosisd)
+                .args(args)
+                .envs(session.endpoints.osmosisd_env(&osmosis_home))
+                .status()
+                .wrap_err(format!("Failed to run {}", osmosisd.display()))?;
+            // pass osmosisd's exit code through for scripts
+            if !status.success() {
+                return Err(ExitCode(status.code().unwrap_or(1)).into());
+            }
+        }
+        Commands::Cache { command } => match command {
+            CacheCommands::List => snapshot::cache_list()?,
+            CacheCommands::Clean { height } => snapshot::cache_clean(height, &safe_mode)?,
+        },
+        Commands::Accounts { command } => match command {
+            AccountsCommands::Snapshot { addresses, path } => {
+                let path = path
+                    .clone()
+                    .unwrap_or_else(|| accounts::default_snapshot_path(&osmosis_home));
+                accounts::snapshot(&osmosisd, &osmosis_home, addresses, &path, session).await?
+            }
+            AccountsCommands::Restore {
+                path,
+                from,
+                gas_prices,
+            } => {
+                let path = path
+                    .clone()
+                    .unwrap_or_else(|| accounts::default_snapshot_path(&osmosis_home));
+                let gas_prices = fees::gas_prices(fees_preset, gas_prices);
+                accounts::restore(&osmosisd, &osmosis_home, &path, from, &gas_prices, session)
+                    .await?
+            }
+        },
+        Commands::Faucet {
+            address,
+            amount,
+            from,
+            gas_prices,
+            serve,
+            drip,
+        } => {
+            let faucet = faucet::Faucet {
+                osmosisd: osmosisd.clone(),
+                osmosis_home: osmosis_home.clone(),
+                from: from.clone(),
+                gas_prices: fees::gas_prices(fees_preset, gas_prices),
+                session: session.clone(),
+            };
+            match (serve, address) {
+                (Some(addr), _) => faucet.serve(*addr, drip.clone()).await?,
+                (None, Some(address)) => faucet.send(address, amount).await?,
+                (None, None) => unreachable!("address is required without --serve"),
+            }
+        }
+        Commands::Keys { command } => match command {
+            KeysCommands::Seed { mnemonics } => {
+                keys::seed(&osmosisd, &osmosis_home, mnemonics, session)?
+            }
+        },
+        Commands::Gov { command } => {
+            let (proposal, gov_args) = match command {
+                GovCommands::Submit { proposal, gov_args } => {
+                    (gov::Proposal::File(proposal.clone()), gov_args)
+                }
+                GovCommands::Upgrade {
+                    name,
+                    height,
+                    gov_args,
+                } => (
+                    gov::Proposal::Upgrade {
+                        name: name.clone(),
+                        height: *height,
+                    },
+                    gov_args,
+                ),
+                GovCommands::ParamChange {
+                    subspace,
+                    key,
+                    value,
+                    gov_args,
+                } => (
+                    gov::Proposal::ParamChange {
+                        subspace: subspace.clone(),
+                        key: key.clone(),
+                        value: value.clone(),
+                    },
+                    gov_args,
+                ),
+            };
+            let gas_prices = fees::gas_prices(fees_preset, &gov_args.gas_prices);
+            gov::pass(
+                &osmosisd,
+                &osmosis_home,
+                &proposal,
+                &gov_args.from,
+                &gas_prices,
+                session,
+            )
+            .await?;
+        }
+        // handled before the home is resolved
+        Commands::Bin { .. } | Commands::Instances { .. } => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// Replace `osmosis_home` with a freshly initialized one with the mainnet genesis
+fn init_command(osmosisd: &PathBuf, osmosis_home: &PathBuf, endpoints: &Endpoints) -> Command {
+    let mut cmd = Command::new(osmosisd);
+    cmd.arg("init")
+        .arg("test")
+        .arg("--chain-id")
+        .arg(endpoints.chain_id())
+        .arg("--home")
+        .arg(osmosis_home);
+    cmd
+}
+
+async fn init_home(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    genesis_url: &[String],
+    genesis_overrides: &Option<PathBuf>,
+    safe_mode: &SafeMode,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    // Remove existing OSMOSIS_HOME directory if it exists
+    if std::path::Path::new(&osmosis_home).exists() {
+        safe_mode.check_home_wipe(osmosis_home)?;
+        spinner! {
+            session.events,
+            "Removing existing OSMOSIS_HOME directory...",
+            "✓ Removed existing OSMOSIS_HOME directory.",
+            std::fs::remove_dir_all(osmosis_home).wrap_err(format!(
+                "Failed to remove existing OSMOSIS_HOME directory: {}",
+                osmosis_home.display()
+            ))?
+        };
+    }
+
+    // Initialize osmosisd
+    spinner! {
+        session.events,
+        "Initializing osmosis chain...",
+        "✓ Initialized osmosis chain.",
+        init_command(osmosisd, osmosis_home, &session.endpoints)
+            .stderr(std::process::Stdio::null())
+            .status()
+            .wrap_err("Failed to initialize osmosis chain")?
+    };
+
+    // Download genesis file
+    spinner! {
+        session.events,
+        "Downloading genesis file...",
+        "✓ Downloaded genesis file.",
+        {
+            let genesis_urls = match genesis_url.is_empty() {
+                true => vec![GENESIS_URL.to_string()],
+                false => genesis_url.to_vec(),
+            };
+            let client = &policy.client()?;
+            let genesis_content = policy
+                .mirrors("Downloading genesis file", &genesis_urls, |url| async move {
+                    match url.strip_prefix("file://") {
+                        Some(genesis_file) => std::fs::read_to_string(genesis_file)
+                            .wrap_err(format!("Failed to read genesis file: {}", genesis_file)),
+                        None => Ok(client.get(&url).send().await?.error_for_status()?.text().await?),
+                    }
+                })
+                .await?;
+
+            std::fs::write(osmosis_home.join("config").join("genesis.json"), genesis_content)
+                .wrap_err("Failed to write genesis file")?;
+        }
+    };
+
+    // Apply genesis overrides
+    if let Some(genesis_overrides) = genesis_overrides {
+        spinner! {
+            session.events,
+            "Applying genesis overrides...",
+            "✓ Applied genesis overrides.",
+            genesis::apply_overrides(&osmosis_home.join("config").join("genesis.json"), genesis_overrides)?
+        };
+    }
+
+    Ok(())
+}
+
+/// Snapshot `download_args` picks: a cached pinned height, the latest or pinned one, or the first
+/// reachable mirror
+pub async fn resolve_snapshot(
+    download_args: &DownloadArgs,
+    policy: &Policy,
+    session: &Session,
+) -> Result<snapshot::SnapshotInfo> {
+    let pinned_cached = download_args
+        .snapshot_height
+        .and_then(|height| snapshot::cached(height, download_args.skip_checksum));
+
+    let snapshot = if let Some(path) = pinned_cached {
+        // exact height is cached, no need to look it up
+        snapshot::SnapshotInfo {
+            url: path.to_string_lossy().to_string(),
+            height: download_args.snapshot_height,
+            date: None,
+        }
+    } else if download_args.snapshot_url.is_empty() {
+        let client = &policy.client()?;
+
+        // Get snapshot URL
+        let snapshot_url = spinner! {
+            session.events,
+            "Downloading latest snapshot...",
+            "✓ Fetched latest snapshot url.",
+            policy
+                .network("Fetching latest snapshot url", || async move {
+                    let url = download_args.snapshot_kind.latest_url();
+                    Ok(client.get(url).send().await?.error_for_status()?.text().await?)
+                })
+                .await?
+        };
+
+        // Resolve pinned snapshot
+        if download_args.snapshot_height.is_some() || download_args.snapshot_date.is_some() {
+            spinner! {
+                session.events,
+                "Resolving pinned snapshot...",
+                "✓ Resolved pinned snapshot.",
+                snapshot::resolve_pinned(
+                    snapshot_url.trim(),
+                    download_args.snapshot_height,
+                    &download_args.snapshot_date,
+                    policy,
+                )
+                .await?
+            }
+        } else {
+            snapshot::SnapshotInfo::from_url(snapshot_url.trim())
+        }
+    } else {
+        // Pick the first reachable mirror
+        let client = &policy.client()?;
+        let snapshot_url = spinner! {
+            session.events,
+            "Finding reachable snapshot mirror...",
+            "✓ Found reachable snapshot mirror.",
+            policy
+                .mirrors("Reaching snapshot", &download_args.snapshot_url, |url| async move {
+                    client.head(&url).send().await?.error_for_status()?;
+                    Ok(url)
+                })
+                .await?
+        };
+        snapshot::SnapshotInfo::from_url(&snapshot_url)
+    };
+
+    Ok(snapshot)
+}
+
+/// Replace `osmosis_home` with a fresh one holding the mainnet state of a snapshot, returning
+/// the snapshot
+pub async fn download_mainnet_state(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    download_args: &DownloadArgs,
+    safe_mode: &SafeMode,
+    policy: &Policy,
+    session: &Session,
+) -> Result<snapshot::SnapshotInfo> {
+    session.phase("download");
+
+    // Catch a missing snapshot file before wiping the home for it
+    let snapshot_file = download_args.local_snapshot();
+    if let Some(snapshot_file) = &snapshot_file {
+        if !snapshot_file.is_file() {
+            return Err(eyre!(
+                "Snapshot file not found: {}",
+                snapshot_file.display()
+            ));
+        }
+    }
+
+    // and a binary that can't run the snapshot's state
+    let snapshot = match &snapshot_file {
+        Some(snapshot_file) => snapshot::SnapshotInfo::from_url(&snapshot_file.to_string_lossy()),
+        None => resolve_snapshot(download_args, policy, session).await?,
+    };
+    if let Some(height) = snapshot.height {
+        println!("{} {}", "Snapshot height:".cyan(), height);
+        if !download_args.skip_version_check {
+            upgrade::verify_binary_for_height(osmosisd, height)?;
+        }
+    }
+
+    // and a disk too small for the local or cached snapshot, downloads check theirs once the
+    // server told their size
+    let cached = match &snapshot_file {
+        Some(_) => None,
+        None => snapshot
+            .height
+            .and_then(|height| snapshot::cached(height, download_args.skip_checksum)),
+    };
+    if let Some(archive) = snapshot_file.as_ref().or(cached.as_ref()) {
+        snapshot::check_disk_space(
+            std::fs::metadata(archive)
+                .wrap_err(format!(
+                    "Failed to read snapshot file: {}",
+                    archive.display()
+                ))?
+                .len(),
+            osmosis_home,
+            None,
+            download_args.force,
+        )?;
+    }
+
+    let stash = preserve::stash(
+        osmosis_home,
+        download_args.preserve_config,
+        download_args.preserve_keys,
+    )?;
+
+    let initialized = init_home(
+        osmosisd,
+        osmosis_home,
+        &download_args.genesis_url,
+        &download_args.genesis_overrides,
+        safe_mode,
+        policy,
+        session,
+    )
+    .await;
+    if let Err(e) = initialized {
+        // put the preserved files back in whatever is left of the home
+        if !stash.is_empty() {
+            stash
+                .restore(osmosis_home)
+                .wrap_err("Failed to restore preserved config and keys")?;
+        }
+        return Err(e);
+    }
+
+    if !stash.is_empty() {
+        spinner! {
+            session.events,
+            "Restoring preserved config and keys...",
+            "✓ Restored preserved config and keys.",
+            stash.restore(osmosis_home)?
+        };
+    }
+
+    // Extract local snapshot
+    if let Some(snapshot_file) = snapshot_file {
+        snapshot::extract_file(&snapshot_file, osmosis_home).await?;
+        snapshot::write_metadata(osmosis_home, &snapshot)?;
+
+        return Ok(snapshot);
+    }
+
+    // Extract cached snapshot, or download and extract it
+    match cached {
+        Some(cached) => {
+            println!("{} {}", "Using cached snapshot:".cyan(), cached.display());
+            snapshot::extract_cached(&cached, osmosis_home, download_args.skip_checksum, session)
+                .await?
+        }
+        None => {
+            snapshot::download_and_extract(
+                &snapshot.url,
+                osmosis_home,
+                download_args,
+                policy,
+                session,
+            )
+            .await?
+        }
+    }
+    snapshot::write_metadata(osmosis_home, &snapshot)?;
+
+    Ok(snapshot)
+}
+
+/// Back `osmosis_home` up to `path`, the default backup when not given, returning where the
+/// backup went
+pub async fn backup(
+    osmosisd: &Path,
+    osmosis_home: &Path,
+    path: Option<PathBuf>,
+    backup_args: &backup::BackupArgs,
+    safe_mode: &SafeMode,
+    session: &Session,
+) -> Result<PathBuf> {
+    session.phase("backup");
+
+    let backup_path = path.unwrap_or_else(backup::default_path);
+    let (compress, data_only) = (backup_args.compress, backup_args.data_only);
+
+    // Incremental backups need an existing plain backup to update
+    let incremental = backup_args.incremental && backup_path.is_dir();
+
+    // Cleanup if backup path already exists
+    if backup_path.exists() && !incremental {
+        safe_mode.check_backup_overwrite(&backup_path)?;
+        spinner! {
+            session.events,
+            "Removing existing backup...",
+            "✓ Removed existing backup.",
+            {
+                match backup_path.is_dir() {
+                    true => std::fs::remove_dir_all(&backup_path),
+                    false => std::fs::remove_file(&backup_path),
+                }
+                .wrap_err("Failed to remove existing backup")?;
+                backup::remove_manifest(&backup_path)
+            }
+        }?;
+    }
+
+    if let Some(parent) = backup_path.parent() {
+        std::fs::create_dir_all(parent)
+            .wrap_err(format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    if incremental {
+        // Only copy what changed into the existing backup
+        safe_mode.check_backup_overwrite(&backup_path)?;
+        let (home, sync_backup_path) = (osmosis_home.to_path_buf(), backup_path.clone());
+        let copied = spinner! {
+            session.events,
+            &format!("Copying changes in {} to {}...", osmosis_home.display(), backup_path.display()),
+            &format!("✓ Copied changes in {} to {}.", osmosis_home.display(), backup_path.display()),
+            tokio::task::spawn_blocking(move || backup::sync(&home, &sync_backup_path, data_only)).await?
+        }?;
+        println!("{} {}", "Changed files:".cyan(), copied.len());
+    } else if compress != backup::Compression::None {
+        // Pack home into a compressed archive
+        let home = osmosis_home.to_path_buf();
+        let archive_path = backup_path.clone();
+        spinner! {
+            session.events,
+            &format!("Packing {} into {}...", osmosis_home.display(), backup_path.display()),
+            &format!("✓ Packed {} into {}.", osmosis_home.display(), backup_path.display()),
+            tokio::task::spawn_blocking(move || {
+                backup::pack(&home, &archive_path, compress, data_only)
+            })
+            .await?
+        }?;
+    } else {
+        // Copy home to backup
+        let method = spinner! {
+            session.events,
+            &format!("Copying {} to {}...", osmosis_home.display(), backup_path.display()),
+            &format!("✓ Copied {} to {}.", osmosis_home.display(), backup_path.display()),
+            backup::copy(osmosis_home, &backup_path, backup_args.method, data_only)
+        }?;
+        println!("{} {}", "Backup method:".cyan(), method);
+    }
+
+    // Record what the backup holds
+    let (osmosisd, home, manifest_backup_path) = (
+        osmosisd.to_path_buf(),
+        osmosis_home.to_path_buf(),
+        backup_path.clone(),
+    );
+    spinner! {
+        session.events,
+        "Writing backup manifest...",
+        "✓ Wrote backup manifest.",
+        tokio::task::spawn_blocking(move || {
+            backup::write_manifest(&osmosisd, &home, &manifest_backup_path)
+        })
+        .await?
+    }?;
+
+    Ok(backup_path)
+}
+
+/// Upload `osmosis_home` as a backup archive to object storage
+pub async fn upload_backup(
+    osmosis_home: &Path,
+    location: &object_store::Location,
+    backup_args: &backup::BackupArgs,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    session.phase("backup");
+
+    spinner! {
+        session.events,
+        &format!("Uploading {} to {}...", osmosis_home.display(), location),
+        &format!("✓ Uploaded {} to {}.", osmosis_home.display(), location),
+        object_store::upload(osmosis_home, location, backup_args, policy).await
+    }
+}
+
+/// Check `backup_path` against its manifest, recording the checksums of its files not hashed
+/// yet with `checksum`
+pub async fn verify_backup(backup_path: &Path, checksum: bool, session: &Session) -> Result<()> {
+    let path = backup_path.to_path_buf();
+    spinner! {
+        session.events,
+        &format!("Verifying backup {}...", backup_path.display()),
+        &format!("✓ Verified backup {}.", backup_path.display()),
+        tokio::task::spawn_blocking(move || backup::verify(&path, checksum)).await?
+    }
+}
+
+/// Replace `osmosis_home` with the backup at `path`, the default backup when not given
+pub async fn restore(
+    osmosis_home: &PathBuf,
+    path: Option<PathBuf>,
+    skip_verify: bool,
+    data_only: bool,
+    safe_mode: &SafeMode,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    session.phase("restore");
+
+    let backup_path = path.unwrap_or_else(backup::default_path);
+    let location = object_store::Location::parse(&backup_path);
+
+    // Catch a corrupted backup before wiping the home for it
+    match (skip_verify, backup::has_manifest(&backup_path)) {
+        (true, _) => {}
+        (false, false) if location.is_some() => {}
+        (false, true) => verify_backup(&backup_path, false, session).await?,
+        (false, false) => println!(
+            "{}",
+            format!(
+                "No manifest for backup {}, skipping verification",
+                backup_path.display()
+            )
+            .yellow()
+        ),
+    }
+
+    // Cleanup the chain state only, keeping config and keys
+    if data_only {
+        let dirs = backup::STATE_DIRS
+            .map(|dir| osmosis_home.join(dir))
+            .into_iter()
+            .filter(|dir| dir.exists())
+            .collect::<Vec<_>>();
+        safe_mode.check_home_wipe_all(&dirs)?;
+        for dir in dirs {
+            spinner! {
+                session.events,
+                &format!("Removing {}...", dir.display()),
+                &format!("✓ Removed {}.", dir.display()),
+                std::fs::remove_dir_all(&dir).wrap_err(format!("Failed to remove {}", dir.display()))
+            }?;
+        }
+    }
+
+    // Cleanup if osmosis home already exists
+    if osmosis_home.exists() && !data_only {
+        safe_mode.check_home_wipe(osmosis_home)?;
+        spinner! {
+            session.events,
+            "Removing existing osmosis home directory...",
+            "✓ Removed existing osmosis home directory.",
+            std::fs::remove_dir_all(osmosis_home).wrap_err("Failed to remove existing osmosis home directory")
+        }?;
+    }
+
+    // Stream backup archive from object storage to home
+    if let Some(location) = location {
+        return spinner! {
+            session.events,
+            &format!("Downloading {} to {}...", backup_path.display(), osmosis_home.display()),
+            &format!("✓ Downloaded {} to {}.", backup_path.display(), osmosis_home.display()),
+            object_store::download(&location, osmosis_home, data_only, policy).await
+        };
+    }
+
+    // Extract backup archive to home
+    if backup_path.is_file() {
+        let home = osmosis_home.clone();
+        let archive_path = backup_path.clone();
+        return spinner! {
+            session.events,
+            &format!("Extracting {} to {}...", backup_path.display(), osmosis_home.display()),
+            &format!("✓ Extracted {} to {}.", backup_path.display(), osmosis_home.display()),
+            tokio::task::spawn_blocking(move || backup::unpack(&archive_path, &home, data_only)).await?
+        };
+    }
+
+    // Copy chain state of backup to home
+    if data_only {
+        return spinner! {
+            session.events,
+            &format!("Copying chain state of {} to {}...", backup_path.display(), osmosis_home.display()),
+            &format!("✓ Copied chain state of {} to {}.", backup_path.display(), osmosis_home.display()),
+            backup::copy(&backup_path, osmosis_home, backup::Method::Copy, true).map(|_| ())
+        };
+    }
+
+    // Copy backup to home
+    spinner! {
+        session.events,
+        &format!("Copying {} to {}...", backup_path.display(), osmosis_home.display()),
+        &format!("✓ Copied {} to {}.", backup_path.display(), osmosis_home.display()),
+        {
+            let options = fs_extra::dir::CopyOptions::new().copy_inside(true);
+            fs_extra::dir::copy(&backup_path, osmosis_home, &options).wrap_err("Failed to copy backup to home")
+        }
+    }?;
+
+    Ok(())
+}
+
+fn sync_command(osmosisd: &PathBuf, osmosis_home: &PathBuf, sync_args: &SyncArgs) -> Command {
+    let mut cmd = Command::new(osmosisd);
+    cmd.arg("start").arg("--home").arg(osmosis_home);
+    // the node stops by itself once it committed the halt height
+    if let Some(halt_height) = sync_args.halt_height {
+        cmd.arg("--halt-height").arg(halt_height.to_string());
+    }
+    cmd
+}
+
+/// Run the node on `osmosis_home` until it caught up with the network, or reached the halt height
+/// of `sync_args`
+pub async fn start_sync(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    stop_on_first_indexed_block_events: bool,
+    sync_args: &SyncArgs,
+    log_patterns: &readiness::LogPatterns,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    session.phase("sync");
+    session.endpoints.configure_ports(osmosis_home)?;
+    node_config::apply_overrides(osmosis_home, &session.overrides)?;
+
+    if let Some(halt_height) = sync_args.halt_height {
+        if let Some(height) = backup::height(osmosis_home).filter(|height| *height >= halt_height) {
+            return Err(eyre!(
+                "{} is already at height {}, past --halt-height {}",
+                osmosis_home.display(),
+                height,
+                halt_height
+            ));
+        }
+    }
+
+    let timeout = sync_args.timeout(policy);
+    let started = std::time::Instant::now();
+    let mut restarts = 0;
+    let _progress = match sync_args.node_logs {
+        true => None,
+        false => Some(sync::Progress::start(
+            &sync_args.network_rpc,
+            sync_args.halt_height,
+            policy.client()?,
+            &session.endpoints,
+        )?),
+    };
+
+    'restart: loop {
+        // Start osmosisd
+        let cmd = sync_command(osmosisd, osmosis_home, sync_args);
+        let remaining = timeout.map(|timeout| timeout.saturating_sub(started.elapsed()));
+        let mut node = Supervisor::spawn(cmd, log_patterns, false, remaining, session)?
+            .quiet(!sync_args.node_logs)
+            .detect_stalls(sync_args.stall_timeout());
+
+        loop {
+            match node.next().await? {
+                Event::Line(line) => check_strict(&mut node, policy, &line).await?,
+                Event::Ready => {
+                    health::set_node_ready(true);
+                    if stop_on_first_indexed_block_events && sync_args.halt_height.is_none() {
+                        node.kill().await?;
+                        break 'restart;
+                    }
+                }
+                Event::TimedOut(timeout) => {
+                    node.kill().await?;
+                    return Err(eyre!("Sync timed out after {:?}", timeout));
+                }
+                Event::Stalled(stall_timeout) => {
+                    node.kill().await?;
+                    if !sync_args.restart_on_stall || restarts >= sync::MAX_STALL_RESTARTS {
+                        return Err(eyre!("Sync stalled, no new block for {:?}", stall_timeout));
+                    }
+                    restarts += 1;
+                    println!(
+                        "{}",
+                        format!(
+                            "Sync stalled, no new block for {:?}, restarting the node ({}/{})...",
+                            stall_timeout,
+                            restarts,
+                            sync::MAX_STALL_RESTARTS
+                        )
+                        .yellow()
+                    );
+                    continue 'restart;
+                }
+                Event::Interrupted(signal) => {
+                    node.stop(signal, policy.shutdown_timeout()).await?;
+                    return Err(eyre!("Interrupted by {}", signal));
+                }
+                Event::Exited(_) => break 'restart,
+                Event::Failure(_) | Event::HookFinished(_) => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn in_place_testnet_command(
+    osmosisd: &Path,
+    osmosis_home: &PathBuf,
+    operator_address: &str,
+    upgrade_handler: &Option<String>,
+    patch_params: Option<&Path>,
+    session: &Session,
+) -> Command {
+    let mut cmd = cosmovisor::command(osmosisd, osmosis_home, session.cosmovisor);
+    cmd.arg("in-place-testnet")
+        .arg(session.endpoints.chain_id())
+        .arg(operator_address)
+        .arg("--home")
+        .arg(osmosis_home);
+
+    // trigger testnet upgrade if upgrade handler is set
+    if let Some(upgrade_handler) = upgrade_handler {
+        cmd.arg("--trigger-testnet-upgrade").arg(upgrade_handler);
+    }
+    if let Some(patch_params) = patch_params {
+        cmd.arg(gov::PATCH_PARAMS_FLAG).arg(patch_params);
+    }
+    cmd
+}
+
+/// Turn the mainnet state of `osmosis_home` into a testnet run by `operator_address`, and keep
+/// running it, through the triggered upgrade and the chained ones when given
+#[allow(clippy::too_many_arguments)]
+pub async fn start_in_place_testnet(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    upgrade_handler: &Option<String>,
+    new_osmosisd_bin: &Option<PathBuf>,
+    upgrade_chain: &[(NextUpgrade, PathBuf)],
+    operator_address: &str,
+    setup: &Setup,
+    hooks: &Hooks,
+    log_patterns: &readiness::LogPatterns,
+    restart_args: &RestartArgs,
+    policy: &Policy,
+    maintenance: &Maintenance,
+    session: &Session,
+) -> Result<()> {
+    session.phase("in-place-testnet");
+    session.endpoints.configure_ports(osmosis_home)?;
+    node_config::apply_overrides(osmosis_home, &session.overrides)?;
+    endpoints::write_operator(osmosis_home, operator_address)?;
+
+    // params patched as the fork is created aren't proposed again once it's up
+    let patch_params = match &setup.fast_params {
+        Some(fast_params) => fast_params.write_patch(osmosis_home)?,
+        None => None,
+    };
+    let setup = &Setup {
+        fast_params: setup.fast_params.clone().filter(|_| patch_params.is_none()),
+        ..setup.clone()
+    };
+
+    let cmd = in_place_testnet_command(
+        osmosisd,
+        osmosis_home,
+        operator_address,
+        upgrade_handler,
+        patch_params.as_deref(),
+        session,
+    );
+    let mut node = Supervisor::spawn(cmd, log_patterns, true, None, session)?;
+    let mut on_ready_executed = false;
+
+    let crashed = loop {
+        match node.next().await? {
+            Event::Line(line) => check_strict(&mut node, policy, &line).await?,
+            Event::Ready => {
+                session.endpoints.write_client_config(osmosis_home)?;
+                session.endpoints.print_banner(osmosis_home).await;
+                session.events.node_ready(&session.endpoints).await;
+                health::set_node_ready(true);
+                webhook::notify(session, webhook::Event::Ready, None);
+
+                // on_ready and setup only execute here if there is no upgrade_handler, if there is, they will be executed in `start_standalone`
+                if upgrade_handler.is_none() && (hooks.on_ready.is_some() || !setup.is_empty()) {
+                    hooks::spawn_ready(
+                        &mut node,
+                        hooks,
+                        setup,
+                        osmosisd,
+                        osmosis_home,
+                        policy,
+                        session,
+                    )
+                    .await;
+                    on_ready_executed = true;
+                }
+            }
+            Event::Failure(line) => {
+                // halting for the triggered upgrade is expected
+                let crashed = !diagnostics::is_upgrade_halt(&line);
+                if let Some(upgrade_handler) = upgrade_handler.as_ref().filter(|_| !crashed) {
+                    session.events.upgrade_halted(upgrade_handler);
+                }
+                if !crashed && session.cosmovisor {
+                    // cosmovisor stops the node itself once it switched binaries
+                    node.wait().await?;
+                    break false;
+                }
+                if crashed {
+                    diagnostics::report(diagnostics::Crash {
+                        osmosisd,
+                        osmosis_home,
+                        upgrade_handler: upgrade_handler.as_deref(),
+                        failure: &line,
+                        recent_lines: node.recent_lines(),
+                        endpoints: &session.endpoints,
+                    })
+                    .await;
+                    hooks::on_failure(&mut node, hooks, osmosis_home, &line, policy, session).await;
+                }
+                node.kill().await?;
+                break crashed;
+            }
+            Event::Interrupted(signal) => {
+                node.stop(signal, policy.shutdown_timeout()).await?;
+                return Err(eyre!("Interrupted by {}", signal));
+            }
+            Event::Exited(status) => break !status.success(),
+            Event::HookFinished(result) => {
+                if let Err(e) = result {
+                    node.kill().await?;
+                    return Err(e);
+                }
+            }
+            Event::TimedOut(_) | Event::Stalled(_) => {}
+        }
+    };
+    node.finish_hooks().await?;
+
+    if let Some(new_osmosisd_bin) = new_osmosisd_bin {
+        run_upgrades(
+            new_osmosisd_bin,
+            upgrade_chain,
+            osmosis_home,
+            setup,
+            hooks,
+            log_patterns,
+            restart_args,
+            policy,
+            maintenance,
+            session,
+        )
+        .await?;
+    } else if restart_args.applies(crashed) {
+        // the fork is already in place, so the node is restarted as a standalone one
+        restart_delay(restart_args, 1).await;
+        let hooks = Hooks {
+            on_ready: hooks.on_ready.clone().filter(|_| !on_ready_executed),
+            ..hooks.without_upgrade()
+        };
+        let setup = match on_ready_executed {
+            true => Setup::default(),
+            false => setup.clone(),
+        };
+        start_standalone(
+            osmosisd,
+            osmosis_home,
+            &setup,
+            &hooks,
+            log_patterns,
+            restart_args,
+            policy,
+            maintenance,
+            session,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Run the testnet of `osmosis_home` on its own, without peers
+#[allow(clippy::too_many_arguments)]
+pub async fn start_standalone(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    setup: &Setup,
+    hooks: &Hooks,
+    log_patterns: &readiness::LogPatterns,
+    restart_args: &RestartArgs,
+    policy: &Policy,
+    maintenance: &Maintenance,
+    session: &Session,
+) -> Result<()> {
+    session.phase("standalone");
+    session.endpoints.configure_ports(osmosis_home)?;
+    node_config::apply_overrides(osmosis_home, &session.overrides)?;
+
+    maintenance.configure(osmosis_home)?;
+
+    let mut on_ready_executed = false;
+    let mut upgrade_completed = false;
+    let mut client_configured = false;
+    let mut restarts = 0;
+
+    loop {
+        let mut cmd = cosmovisor::command(osmosisd, osmosis_home, session.cosmovisor);
+        start_node_no_peers(&mut cmd, osmosis_home);
+        // the node is restarted for maintenance once its interval is up
+        let mut node = Supervisor::spawn(cmd, log_patterns, true, maintenance.interval(), session)?;
+
+        let mut maintenance_due = false;
+        let mut halted = false;
+
+        let crashed = loop {
+            match node.next().await? {
+                Event::Line(line) => check_strict(&mut node, policy, &line).await?,
+                Event::Ready => {
+                    restarts = 0;
+                    if !client_configured {
+                        session.endpoints.write_client_config(osmosis_home)?;
+                        session.endpoints.print_banner(osmosis_home).await;
+                        client_configured = true;
+                    }
+                    session.events.node_ready(&session.endpoints).await;
+                    health::set_node_ready(true);
+                    webhook::notify(session, webhook::Event::Ready, None);
+                    let height = readiness::status(&session.endpoints)
+                        .await
+                        .ok()
+                        .map(|status| status.latest_block_height);
+                    if let Some(height) = height {
+                        upgrade::clear_applied(osmosis_home, height)?;
+                    }
+                    // the new binary committing blocks means the upgrade went through
+                    if !upgrade_completed {
+                        if let Some(ref on_upgrade_complete) = hooks.on_upgrade_complete {
+                            hooks::spawn(
+                                &mut node,
+                                on_upgrade_complete,
+                                "on_upgrade_complete",
+                                osmosis_home,
+                                &[],
+                                policy,
+                                session,
+                            )
+                            .await;
+                        }
+                        if let Some(ref manifest) = hooks.expect_changes {
+                            hooks::spawn_changes(&mut node, manifest, osmosisd, osmosis_home);
+                        }
+
+                        upgrade_completed = true;
+                    }
+                    if (hooks.on_ready.is_some() || !setup.is_empty()) && !on_ready_executed {
+                        hooks::spawn_ready(
+                            &mut node,
+                            hooks,
+                            setup,
+                            osmosisd,
+                            osmosis_home,
+                            policy,
+                            session,
+                        )
+                        .await;
+
+                        on_ready_executed = true;
+                    }
+                }
+                Event::TimedOut(_) => {
+                    node.kill().await?;
+                    maintenance_due = true;
+                    break false;
+                }
+                Event::Failure(line) => {
+                    // halting for the next chained upgrade hands over to its binary
+                    if let Some(next_upgrade) = setup
+                        .next_upgrade
+                        .as_ref()
+                        .filter(|_| diagnostics::is_upgrade_halt(&line))
+                    {
+                        session.events.upgrade_halted(&next_upgrade.name);
+                        match session.cosmovisor {
+                            true => node.wait().await.map(|_| ())?,
+                            false => node.kill().await?,
+                        }
+                        halted = true;
+                        break false;
+                    }
+                    if !diagnostics::is_upgrade_halt(&line) {
+                        diagnostics::report(diagnostics::Crash {
+                            osmosisd,
+                            osmosis_home,
+                            upgrade_handler: None,
+                            failure: &line,
+                            recent_lines: node.recent_lines(),
+                            endpoints: &session.endpoints,
+                        })
+                        .await;
+                        hooks::on_failure(&mut node, hooks, osmosis_home, &line, policy, session)
+                            .await;
+                    }
+                    // the node keeps running after a consensus failure, so it's only stopped to
+                    // be restarted
+                    if restart_args.applies(true) {
+                        node.kill().await?;
+                        break true;
+                    }
+                }
+                Event::Interrupted(signal) => {
+                    node.stop(signal, policy.shutdown_timeout()).await?;
+                    return Err(eyre!("Interrupted by {}", signal));
+                }
+                Event::Exited(status) => break !status.success(),
+                Event::HookFinished(result) => {
+                    if let Err(e) = result {
+                        node.kill().await?;
+                        return Err(e);
+                    }
+                }
+                Event::Stalled(_) => {}
+            }
+        };
+        node.finish_hooks().await?;
+
+        if halted {
+            return Ok(());
+        }
+        if maintenance_due {
+            // restart the node once maintenance is done
+            maintenance.run(osmosisd, osmosis_home, session)?;
+            continue;
+        }
+
+        if !restart_args.applies(crashed) {
+            break;
+        }
+        if restarts >= restart_args.restart_max_retries {
+            return Err(eyre!(
+                "osmosisd didn't become ready after {} restarts, giving up",
+                restarts
+            ));
+        }
+        restarts += 1;
+        restart_delay(restart_args, restarts).await;
+    }
+
+    match &setup.next_upgrade {
+        Some(next_upgrade) => Err(eyre!(
+            "osmosisd stopped before halting for upgrade {}",
+            next_upgrade.name
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Run the triggered upgrade with `new_osmosisd_bin`, then each of `upgrade_chain` with its binary
+/// in turn. The setup and the on_ready hook are held back for the last binary.
+#[allow(clippy::too_many_arguments)]
+async fn run_upgrades(
+    new_osmosisd_bin: &PathBuf,
+    upgrade_chain: &[(NextUpgrade, PathBuf)],
+    osmosis_home: &PathBuf,
+    setup: &Setup,
+    hooks: &Hooks,
+    log_patterns: &readiness::LogPatterns,
+    restart_args: &RestartArgs,
+    policy: &Policy,
+    maintenance: &Maintenance,
+    session: &Session,
+) -> Result<()> {
+    let mut osmosisd = new_osmosisd_bin;
+    let mut setup = setup.clone();
+    for (next_upgrade, next_osmosisd) in upgrade_chain {
+        if session.cosmovisor {
+            cosmovisor::check_current(osmosis_home, osmosisd)?;
+        }
+        let hooks = Hooks {
+            on_ready: None,
+            ..hooks.clone()
+        };
+        // params are shortened right away, so the proposals of the later hops pass quickly
+        let hop_setup = Setup {
+            fast_params: setup.fast_params.take(),
+            next_upgrade: Some(next_upgrade.clone()),
+            ..Setup::default()
+        };
+        start_standalone(
+            osmosisd,
+            osmosis_home,
+            &hop_setup,
+            &hooks,
+            log_patterns,
+            restart_args,
+            policy,
+            maintenance,
+            session,
+        )
+        .await?;
+        println!(
+            "{}",
+            format!(
+                "Halted for upgrade {}, continuing with {}.",
+                next_upgrade.name,
+                next_osmosisd.display()
+            )
+            .cyan()
+        );
+        osmosisd = next_osmosisd;
+    }
+
+    if session.cosmovisor {
+        cosmovisor::check_current(osmosis_home, osmosisd)?;
+    }
+    start_standalone(
+        osmosisd,
+        osmosis_home,
+        &setup,
+        hooks,
+        log_patterns,
+        restart_args,
+        policy,
+        maintenance,
+        session,
+    )
+    .await
+}
+
+/// Upgrades to lay out for cosmovisor, the triggered one followed by the chained ones
+fn upgrades(
+    upgrade_handler: &Option<String>,
+    new_osmosisd_bin: &Option<PathBuf>,
+    upgrade_chain: &[(NextUpgrade, PathBuf)],
+) -> Vec<(String, PathBuf)> {
+    upgrade_handler
+        .iter()
+        .zip(new_osmosisd_bin)
+        .map(|(name, binary)| (name.clone(), binary.clone()))
+        .chain(
+            upgrade_chain
+                .iter()
+                .map(|(next_upgrade, binary)| (next_upgrade.name.clone(), binary.clone())),
+        )
+        .collect()
+}
+
+/// Stop `node` and fail when its log `line` breaks the strict policy
+async fn check_strict(node: &mut Supervisor, policy: &Policy, line: &str) -> Result<()> {
+    if let Err(e) = policy.check_strict(line) {
+        node.kill().await?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Wait out the backoff before restart `attempt` of the node
+async fn restart_delay(restart_args: &RestartArgs, attempt: u32) {
+    let delay = restart_args.backoff(attempt);
+    println!(
+        "{}",
+        format!(
+            "osmosisd exited, restarting it in {:?} ({}/{})...",
+            delay, attempt, restart_args.restart_max_retries
+        )
+        .yellow()
+    );
+    tokio::time::sleep(delay).await;
+}
+
+/// Continue the testnet of `osmosis_home` with `new_osmosisd_bin` after it halted for an upgrade
+pub async fn resume(
+    osmosis_home: &PathBuf,
+    new_osmosisd_bin: &PathBuf,
+    hooks: &Hooks,
+    policy: &Policy,
+    maintenance: &Maintenance,
+    session: &Session,
+) -> Result<()> {
+    let upgrade = upgrade::pending_upgrade(osmosis_home)?.ok_or_else(|| {
+        eyre!(
+            "No pending upgrade found in {}, nothing to resume",
+            osmosis_home.display()
+        )
+    })?;
+
+    println!(
+        "{}",
+        format!(
+            "Found pending upgrade {} at height {}.",
+            upgrade.name, upgrade.height
+        )
+        .cyan()
+    );
+    session.events.upgrade_halted(&upgrade.name);
+
+    spinner! {
+        session.events,
+        &format!("Verifying {} can run upgrade {}...", new_osmosisd_bin.display(), upgrade.name),
+        &format!("✓ Verified {} can run upgrade {}.", new_osmosisd_bin.display(), upgrade.name),
+        upgrade::verify_binary_for_upgrade(new_osmosisd_bin, &upgrade)?
+    };
+
+    start_standalone(
+        new_osmosisd_bin,
+        osmosis_home,
+        &Setup::default(),
+        hooks,
+        &readiness::LogPatterns::default(),
+        &RestartArgs::default(),
+        policy,
+        maintenance,
+        session,
+    )
+    .await
+}
+
+fn start_node_no_peers<'a>(
+    osmosisd: &'a mut Command,
+    osmosis_home: &'a PathBuf,
+) -> &'a mut Command {
+    osmosisd
+        .arg("start")
+        .arg("--home")
+        .arg(osmosis_home)
+        .arg("--p2p.persistent_peers")
+        .arg("")
+        .arg("--p2p.seeds")
+        .arg("")
+        .arg("--rpc.unsafe")
+        .arg("--grpc.enable")
+        .arg("--grpc-web.enable")
+}
+
+/// Show a spinner with `$message` while evaluating `$e`, reported to `$events` as a step
+#[macro_export]
+macro_rules! spinner {
+    ($events:expr, $message:expr, $finished_message:expr, $e:expr) => {{
+        use $crate::colored::Colorize as _;
+        let events: &$crate::output::Events = &$events;
+        let message = $message;
+        // steps are reported as events instead with --output json
+        let spinner = match events.json() {
+            true => $crate::indicatif::ProgressBar::hidden(),
+            false => $crate::indicatif::ProgressBar::new_spinner(),
+        };
+        events.step_started(message);
+        spinner.set_message(message.cyan().to_string());
+        spinner.enable_steady_tick(::std::time::Duration::from_millis(100));
+        // a `?` or `return` in `$e` leaves early, the spinner has to stop then too
+        let _stop = $crate::output::StopSpinner(spinner.clone());
+
+        let result = $e;
+
+        events.step_completed(message);
+        spinner.finish_with_message($finished_message.green().to_string());
+        result
+    }};
+}
@@ -0,0 +1,110 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+
+use crate::{
+    node_config::{self, ConfigFile},
+    node_log,
+    session::Session,
+    spinner,
+};
+
+/// Pruned blocks between compactions of CometBFT's stores
+const COMPACTION_INTERVAL: i64 = 1000;
+
+/// Periodic maintenance for long-running standalone forks so they don't fill the disk
+#[derive(Args, Debug, Clone, Copy, Default)]
+pub struct Maintenance {
+    /// Hours between maintenance windows in which the node is briefly stopped to prune old heights
+    /// (0 to disable)
+    #[arg(long, default_value_t = 0)]
+    pub maintenance_interval_hours: u64,
+
+    /// Number of recent heights to keep when pruning
+    #[arg(long, default_value_t = 100_000)]
+    pub maintenance_keep_recent: u64,
+}
+
+impl Maintenance {
+    pub fn enabled(&self) -> bool {
+        self.maintenance_interval_hours > 0
+    }
+
+    /// Time between maintenance windows, `None` when disabled
+    pub fn interval(&self) -> Option<Duration> {
+        self.enabled()
+            .then(|| Duration::from_secs(self.maintenance_interval_hours * 60 * 60))
+    }
+
+    /// Have the node prune continuously while running so state grows slower between windows
+    pub fn configure(&self, osmosis_home: &Path) -> Result<()> {
+        if !self.enabled() {
+            return Ok(());
+        }
+
+        node_config::set(osmosis_home, ConfigFile::App, "pruning", "custom")?;
+        node_config::set(
+            osmosis_home,
+            ConfigFile::App,
+            "pruning-keep-recent",
+            self.maintenance_keep_recent.to_string(),
+        )?;
+        node_config::set(osmosis_home, ConfigFile::App, "pruning-interval", "10")?;
+        // compact the block and state stores after CometBFT prunes them, ignored by versions
+        // without storage compaction
+        node_config::set(osmosis_home, ConfigFile::Config, "storage.compact", true)?;
+        node_config::set(
+            osmosis_home,
+            ConfigFile::Config,
+            "storage.compaction_interval",
+            COMPACTION_INTERVAL,
+        )?;
+
+        Ok(())
+    }
+
+    /// Prune old heights of the stopped node and rotate its log file
+    pub fn run(&self, osmosisd: &PathBuf, osmosis_home: &PathBuf, session: &Session) -> Result<()> {
+        println!("{}", "Running maintenance...".cyan());
+
+        spinner! {
+            session.events,
+            "Pruning old heights...",
+            "✓ Pruned old heights.",
+            self.prune(osmosisd, osmosis_home)
+        }?;
+
+        spinner! {
+            session.events,
+            "Rotating log file...",
+            "✓ Rotated log file.",
+            node_log::rotate()
+        }?;
+
+        Ok(())
+    }
+
+    fn prune(&self, osmosisd: &PathBuf, osmosis_home: &PathBuf) -> Result<()> {
+        let status = Command::new(osmosisd)
+            .args(["prune", "custom"])
+            .arg("--home")
+            .arg(osmosis_home)
+            .arg("--pruning-keep-recent")
+            .arg(self.maintenance_keep_recent.to_string())
+            .args(["--pruning-interval", "10"])
+            .stdout(std::process::Stdio::null())
+            .status()
+            .wrap_err("Failed to prune")?;
+
+        if !status.success() {
+            return Err(eyre!("Pruning exited with {}", status));
+        }
+        Ok(())
+    }
+}
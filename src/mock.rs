@@ -0,0 +1,208 @@
+//! Scripted stand-in for osmosisd, enabled with the `mock` feature and selected with
+//! `--osmosisd-bin mock`. The osmoinplace executable re-invokes itself as the mock, so pipelines
+//! and hook scripts can be exercised quickly in CI without a real chain.
+//!
+//! Log lines printed by `start` and `in-place-testnet` can be scripted with a file pointed to by
+//! `OSMOINPLACE_MOCK_SCRIPT`, one line per log line, where `sleep <ms>` pauses instead of printing.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use color_eyre::eyre::{Context, Result};
+
+use crate::{
+    endpoints, gov,
+    node_config::{self, ConfigFile},
+};
+
+/// Set on the osmoinplace process when running with the mock, inherited by spawned "osmosisd"
+/// processes so they know to act as the mock
+pub const MOCK_ENV: &str = "OSMOINPLACE_MOCK_OSMOSISD";
+
+const SCRIPT_ENV: &str = "OSMOINPLACE_MOCK_SCRIPT";
+const VERSION_ENV: &str = "OSMOINPLACE_MOCK_VERSION";
+
+const RPC_ADDR: &str = "127.0.0.1:26657";
+
+/// Latest block height reported by /status, advanced as the mock commits blocks
+static HEIGHT: AtomicU64 = AtomicU64::new(100);
+/// Chain id reported by /status, the one the fork was created with or the home's
+static CHAIN_ID: Mutex<String> = Mutex::new(String::new());
+
+/// Path to use as osmosisd binary for the mock
+pub fn mock_bin() -> Result<PathBuf> {
+    std::env::set_var(MOCK_ENV, "1");
+    std::env::current_exe().wrap_err("Failed to resolve osmoinplace executable")
+}
+
+pub fn is_mock_invocation() -> bool {
+    std::env::var(MOCK_ENV).is_ok()
+}
+
+/// Act as osmosisd with the given arguments
+pub fn run(args: Vec<String>) -> Result<()> {
+    let home = args
+        .iter()
+        .position(|arg| arg == "--home")
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from);
+
+    // listen where --port-offset moved the node's RPC to
+    let rpc_addr = home
+        .as_ref()
+        .and_then(|home| node_config::get(home, ConfigFile::Config, "rpc.laddr"))
+        .map(|laddr| laddr.trim_start_matches("tcp://").to_string())
+        .unwrap_or_else(|| RPC_ADDR.to_string());
+
+    match args.first().map(String::as_str) {
+        Some("version") => {
+            println!(
+                "{}",
+                std::env::var(VERSION_ENV).unwrap_or_else(|_| "25.0.0".to_string())
+            );
+        }
+        Some("init") => {
+            if let Some(home) = home {
+                let config = home.join("config");
+                std::fs::create_dir_all(&config)?;
+                std::fs::write(config.join("config.toml"), "[tx_index]\nindexer = \"kv\"\n")?;
+                std::fs::write(config.join("app.toml"), "minimum-gas-prices = \"0uosmo\"\n")?;
+                std::fs::write(config.join("client.toml"), "chain-id = \"\"\n")?;
+                std::fs::write(config.join("genesis.json"), "{}")?;
+            }
+        }
+        Some("start") => {
+            let chain_id = home
+                .and_then(|home| node_config::get(&home, ConfigFile::Client, "chain-id"))
+                .filter(|chain_id| !chain_id.is_empty())
+                .unwrap_or_else(|| endpoints::DEFAULT_CHAIN_ID.to_string());
+            start(&chain_id, &rpc_addr, false)?
+        }
+        // plays a build that patches params as it creates the fork
+        Some("in-place-testnet") if args.iter().any(|arg| arg == "--help") => {
+            println!(
+                "Usage:\n  osmosisd in-place-testnet [newChainID] [newOperatorAddress] [flags]\n\nFlags:\n      {} string",
+                gov::PATCH_PARAMS_FLAG
+            );
+        }
+        Some("in-place-testnet") => {
+            let chain_id = args
+                .get(1)
+                .cloned()
+                .unwrap_or_else(|| endpoints::DEFAULT_CHAIN_ID.to_string());
+            start(
+                &chain_id,
+                &rpc_addr,
+                args.iter().any(|arg| arg == "--trigger-testnet-upgrade"),
+            )?
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn start(chain_id: &str, rpc_addr: &str, halt_for_upgrade: bool) -> Result<()> {
+    *CHAIN_ID.lock().unwrap() = chain_id.to_string();
+    let rpc_addr = rpc_addr.to_string();
+    std::thread::spawn(move || serve_rpc(&rpc_addr));
+
+    let script = match std::env::var(SCRIPT_ENV) {
+        Ok(path) => std::fs::read_to_string(&path)
+            .wrap_err(format!("Failed to read mock script: {}", path))?,
+        Err(_) => default_script(halt_for_upgrade),
+    };
+
+    let mut stdout = std::io::stdout();
+    for line in script.lines() {
+        match line.strip_prefix("sleep ") {
+            Some(ms) => std::thread::sleep(Duration::from_millis(ms.trim().parse()?)),
+            None => writeln!(stdout, "{}", line)?,
+        }
+        stdout.flush()?;
+    }
+
+    // keep running like a real node until killed
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        let height = HEIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+        writeln!(
+            stdout,
+            "INF indexed block events height={} module=txindex",
+            height
+        )?;
+        stdout.flush()?;
+    }
+}
+
+fn default_script(halt_for_upgrade: bool) -> String {
+    let mut script = (1..=3)
+        .flat_map(|height| {
+            [
+                format!(
+                    "INF finalizing commit of block height={} module=consensus",
+                    height
+                ),
+                format!("INF indexed block events height={} module=txindex", height),
+                "sleep 100".to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    if halt_for_upgrade {
+        script.push("ERR UPGRADE \"mock\" NEEDED at height: 4".to_string());
+        script.push("ERR CONSENSUS FAILURE!!! err=\"UPGRADE NEEDED\"".to_string());
+        script.push("sleep 60000".to_string());
+    }
+
+    script.join("\n")
+}
+
+/// Answer every request with a CometBFT-like /status response, each connection on its own thread
+/// so one left idle by a client doesn't hold up the others
+fn serve_rpc(addr: &str) {
+    let Ok(listener) = TcpListener::bind(addr) else {
+        return;
+    };
+
+    for stream in listener.incoming().flatten() {
+        std::thread::spawn(move || answer_status(stream));
+    }
+}
+
+fn answer_status(mut stream: TcpStream) {
+    let mut request_line = String::new();
+    if BufReader::new(&stream)
+        .read_line(&mut request_line)
+        .is_err()
+    {
+        return;
+    }
+
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": -1,
+        "result": {
+            "node_info": { "network": *CHAIN_ID.lock().unwrap(), "version": "0.38.0" },
+            "sync_info": {
+                "latest_block_height": HEIGHT.load(Ordering::SeqCst).to_string(),
+                "catching_up": false,
+            },
+        },
+    })
+    .to_string();
+    let _ = write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+}
@@ -0,0 +1,320 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use toml_edit::{DocumentMut, Item, Table};
+
+/// Node config file in `$OSMOSIS_HOME/config`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFile {
+    /// CometBFT config.toml
+    Config,
+    /// Cosmos SDK app.toml
+    App,
+    /// Cosmos SDK client.toml, defaults for CLI commands
+    Client,
+}
+
+impl ConfigFile {
+    pub fn path(self, osmosis_home: &Path) -> PathBuf {
+        let file_name = match self {
+            ConfigFile::Config => "config.toml",
+            ConfigFile::App => "app.toml",
+            ConfigFile::Client => "client.toml",
+        };
+        osmosis_home.join("config").join(file_name)
+    }
+}
+
+/// Settings of the node config files to override before every start
+#[derive(Args, Debug, Clone, Default)]
+pub struct OverrideArgs {
+    /// Override a setting before the node starts, as `<file>:<key>=<value>` with file `config`,
+    /// `app` or `client`, e.g. config:rpc.laddr=tcp://0.0.0.0:26657 or app:api.enable=true.
+    /// The value is read as TOML, and as a string when it isn't valid TOML. Repeatable.
+    #[arg(long = "set", value_parser = parse_override)]
+    pub overrides: Vec<Override>,
+}
+
+/// Shortcuts for serving a frontend from the fork
+#[derive(Args, Debug, Clone, Default)]
+pub struct FrontendArgs {
+    /// Enable the REST API and allow any origin on the RPC and REST API, same as --enable-api
+    /// --cors '*'
+    #[arg(long)]
+    pub frontend_dev: bool,
+
+    /// Enable the REST API (port 1317)
+    #[arg(long)]
+    pub enable_api: bool,
+
+    /// Origin allowed to call the RPC, e.g. http://localhost:3000 or '*'. The REST API can only
+    /// allow all origins, so it does whenever this is set.
+    #[arg(long)]
+    pub cors: Option<String>,
+}
+
+impl FrontendArgs {
+    /// Config overrides the shortcuts stand for
+    pub fn overrides(&self) -> Vec<Override> {
+        let mut overrides = vec![];
+        let setting = |file, key: &str, value: String| Override {
+            file,
+            key: key.to_string(),
+            value,
+        };
+
+        if self.frontend_dev || self.enable_api {
+            overrides.push(setting(ConfigFile::App, "api.enable", "true".to_string()));
+        }
+        let cors = match self.frontend_dev {
+            true => Some(self.cors.as_deref().unwrap_or("*")),
+            false => self.cors.as_deref(),
+        };
+        if let Some(origin) = cors {
+            overrides.push(setting(
+                ConfigFile::Config,
+                "rpc.cors_allowed_origins",
+                toml_edit::Value::from_iter([origin]).to_string(),
+            ));
+            overrides.push(setting(
+                ConfigFile::App,
+                "api.enabled-unsafe-cors",
+                "true".to_string(),
+            ));
+        }
+        overrides
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Override {
+    file: ConfigFile,
+    key: String,
+    value: String,
+}
+
+fn parse_override(s: &str) -> Result<Override, String> {
+    let (file, setting) = s
+        .split_once(':')
+        .ok_or_else(|| format!("Expected <file>:<key>=<value>, got {}", s))?;
+    let file = match file {
+        "config" => ConfigFile::Config,
+        "app" => ConfigFile::App,
+        "client" => ConfigFile::Client,
+        _ => {
+            return Err(format!(
+                "Unknown config file {}, expected config, app or client",
+                file
+            ))
+        }
+    };
+    let (key, value) = setting
+        .split_once('=')
+        .ok_or_else(|| format!("Expected <file>:<key>=<value>, got {}", s))?;
+    if key.is_empty() {
+        return Err(format!("Empty key in {}", s));
+    }
+
+    Ok(Override {
+        file,
+        key: key.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Apply the --set `overrides` to the home's config files. Run before each start as the home may
+/// have been replaced since.
+pub fn apply_overrides(osmosis_home: &Path, overrides: &[Override]) -> Result<()> {
+    for Override { file, key, value } in overrides {
+        let value = value
+            .parse::<toml_edit::Value>()
+            .unwrap_or_else(|_| value.as_str().into());
+        set(osmosis_home, *file, key, value)?;
+    }
+    Ok(())
+}
+
+/// String value of dotted `key` in the config file, `None` if the file or key doesn't exist
+pub fn get(osmosis_home: &Path, file: ConfigFile, key: &str) -> Option<String> {
+    let content = std::fs::read_to_string(file.path(osmosis_home)).ok()?;
+    let doc = content.parse::<DocumentMut>().ok()?;
+
+    let mut item = doc.as_item();
+    for segment in key.split('.') {
+        item = item.get(segment)?;
+    }
+    item.as_str().map(str::to_string)
+}
+
+/// Set dotted `key` (e.g. `tx_index.indexer`) to `value` in the config file, preserving formatting
+pub fn set(
+    osmosis_home: &Path,
+    file: ConfigFile,
+    key: &str,
+    value: impl Into<toml_edit::Value>,
+) -> Result<()> {
+    let path = file.path(osmosis_home);
+    let content = std::fs::read_to_string(&path)
+        .wrap_err(format!("Failed to read config file: {}", path.display()))?;
+    let mut doc = content
+        .parse::<DocumentMut>()
+        .wrap_err(format!("Failed to parse config file: {}", path.display()))?;
+
+    let mut segments = key.split('.').collect::<Vec<_>>();
+    let last = segments.pop().ok_or_else(|| eyre!("Empty config key"))?;
+
+    let mut table: &mut Table = doc.as_table_mut();
+    for segment in segments {
+        table = table
+            .entry(segment)
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| eyre!("{} in {} is not a table", segment, path.display()))?;
+    }
+    table[last] = toml_edit::value(value);
+
+    std::fs::write(&path, doc.to_string())
+        .wrap_err(format!("Failed to write config file: {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Parse a duration like `1s`, `1.5s` or `500ms`
+pub fn parse_duration(duration: &str) -> Result<Duration> {
+    let duration = duration.trim();
+    let parsed = match duration.strip_suffix("ms") {
+        Some(millis) => millis.parse::<u64>().map(Duration::from_millis).ok(),
+        None => duration
+            .strip_suffix('s')
+            .and_then(|secs| secs.parse::<f64>().ok())
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(Duration::from_secs_f64),
+    };
+    match parsed {
+        Some(parsed) if !parsed.is_zero() => Ok(parsed),
+        _ => Err(eyre!(
+            "Invalid duration: {}, expected e.g. 1s or 500ms",
+            duration
+        )),
+    }
+}
+
+/// Have the node aim for a block every `block_time` by waiting that long after each commit, with
+/// the propose timeout scaled along to keep CometBFT's default ratio
+pub fn set_block_time(osmosis_home: &Path, block_time: Duration) -> Result<()> {
+    let millis = block_time.as_millis();
+    set(
+        osmosis_home,
+        ConfigFile::Config,
+        "consensus.timeout_commit",
+        format!("{}ms", millis),
+    )?;
+    set(
+        osmosis_home,
+        ConfigFile::Config,
+        "consensus.timeout_propose",
+        format!("{}ms", millis * 3),
+    )?;
+    set(
+        osmosis_home,
+        ConfigFile::Config,
+        "consensus.skip_timeout_commit",
+        false,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home_with_config(content: &str) -> tempfile::TempDir {
+        let home = tempfile::tempdir().unwrap();
+        std::fs::create_dir(home.path().join("config")).unwrap();
+        std::fs::write(ConfigFile::Config.path(home.path()), content).unwrap();
+        home
+    }
+
+    #[test]
+    fn parses_file_key_and_value_of_overrides() {
+        let parsed = parse_override("config:rpc.laddr=tcp://0.0.0.0:26657").unwrap();
+        assert_eq!(parsed.file, ConfigFile::Config);
+        assert_eq!(parsed.key, "rpc.laddr");
+        assert_eq!(parsed.value, "tcp://0.0.0.0:26657");
+
+        let parsed = parse_override("app:minimum-gas-prices=").unwrap();
+        assert_eq!(parsed.file, ConfigFile::App);
+        assert_eq!(parsed.value, "");
+    }
+
+    #[test]
+    fn rejects_malformed_overrides() {
+        assert!(parse_override("moniker=node")
+            .unwrap_err()
+            .starts_with("Expected <file>:<key>=<value>"));
+        assert!(parse_override("config:rpc.laddr")
+            .unwrap_err()
+            .starts_with("Expected <file>:<key>=<value>"));
+        assert!(parse_override("genesis:chain_id=x")
+            .unwrap_err()
+            .starts_with("Unknown config file genesis"));
+        assert!(parse_override("config:=x")
+            .unwrap_err()
+            .starts_with("Empty key"));
+    }
+
+    #[test]
+    fn override_values_are_toml_or_else_strings() {
+        let home = home_with_config("moniker = \"node\"\n");
+        let overrides = [
+            "config:api.enable=true",
+            "config:moniker=hello world",
+            "config:p2p.max=40",
+        ]
+        .map(|s| parse_override(s).unwrap());
+        apply_overrides(home.path(), &overrides).unwrap();
+
+        let doc = std::fs::read_to_string(ConfigFile::Config.path(home.path()))
+            .unwrap()
+            .parse::<DocumentMut>()
+            .unwrap();
+        assert_eq!(doc["api"]["enable"].as_bool(), Some(true));
+        assert_eq!(doc["moniker"].as_str(), Some("hello world"));
+        assert_eq!(doc["p2p"]["max"].as_integer(), Some(40));
+    }
+
+    #[test]
+    fn set_creates_nested_tables_and_keeps_the_rest() {
+        let home = home_with_config("# comment\nmoniker = \"node\"\n\n[a]\nx = 1\n");
+        set(home.path(), ConfigFile::Config, "a.b.c", "deep").unwrap();
+
+        let content = std::fs::read_to_string(ConfigFile::Config.path(home.path())).unwrap();
+        assert!(content.starts_with("# comment\nmoniker = \"node\"\n"));
+        assert_eq!(
+            get(home.path(), ConfigFile::Config, "a.b.c").as_deref(),
+            Some("deep")
+        );
+        let doc = content.parse::<DocumentMut>().unwrap();
+        assert_eq!(doc["a"]["x"].as_integer(), Some(1));
+    }
+
+    #[test]
+    fn set_refuses_to_descend_into_values() {
+        let home = home_with_config("moniker = \"node\"\n");
+        let err = set(home.path(), ConfigFile::Config, "moniker.name", "x").unwrap_err();
+        assert!(err.to_string().starts_with("moniker in"));
+    }
+
+    #[test]
+    fn parses_seconds_and_milliseconds() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration(" 2s ").unwrap(), Duration::from_secs(2));
+        for invalid in ["0s", "0ms", "abc", "1", "-1s", "1.5ms"] {
+            assert!(parse_duration(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use clap::{Args, ValueEnum};
+use color_eyre::eyre::{Context, Result};
+use colored::Colorize;
+
+use crate::output::{self, Events};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static LOG_FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+static FILTER: Mutex<Filter> = Mutex::new(Filter {
+    min_level: None,
+    modules: Vec::new(),
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Level of a zerolog JSON `level` field or the short level of a plain text line
+    fn parse(level: &str) -> Option<Level> {
+        match level.to_lowercase().as_str() {
+            "trace" | "trc" => Some(Level::Trace),
+            "debug" | "dbg" => Some(Level::Debug),
+            "info" | "inf" => Some(Level::Info),
+            "warn" | "warning" | "wrn" => Some(Level::Warn),
+            "error" | "err" | "fatal" | "ftl" | "panic" | "pnc" => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    fn colorize(self, text: &str) -> String {
+        match self {
+            Level::Trace | Level::Debug => text.dimmed().to_string(),
+            Level::Info => text.green().to_string(),
+            Level::Warn => text.yellow().to_string(),
+            Level::Error => text.red().to_string(),
+        }
+    }
+}
+
+/// Which node log lines are echoed, lines without a level or module always are
+struct Filter {
+    min_level: Option<Level>,
+    modules: Vec<String>,
+}
+
+impl Filter {
+    fn shows(&self, level: Option<Level>, module: Option<&str>) -> bool {
+        let level_shown = match (self.min_level, level) {
+            (Some(min_level), Some(level)) => level >= min_level,
+            _ => true,
+        };
+        let module_shown = match module {
+            Some(module) if !self.modules.is_empty() => {
+                self.modules.iter().any(|filter| filter == module)
+            }
+            _ => true,
+        };
+        level_shown && module_shown
+    }
+}
+
+/// Where the output of osmosisd goes, readiness and failure detection see it either way
+#[derive(Args, Debug, Clone)]
+pub struct NodeLogArgs {
+    /// Also write the output of osmosisd to this file, rotated by size
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+
+    /// Size in MB at which --log-file is rotated
+    #[arg(long, default_value_t = 100)]
+    pub log_file_max_mb: u64,
+
+    /// Number of rotated log files to keep, as <log-file>.1 (newest) to <log-file>.N
+    #[arg(long, default_value_t = 5)]
+    pub log_file_keep: u32,
+
+    /// Don't echo the output of osmosisd to stdout
+    #[arg(long)]
+    pub quiet: bool,
+
+    /// Only echo node log lines of this level or above
+    #[arg(long, value_enum)]
+    pub log_level_filter: Option<Level>,
+
+    /// Only echo node log lines of this module, e.g. consensus or x/gamm, repeat to add more
+    #[arg(long)]
+    pub log_module_filter: Vec<String>,
+}
+
+/// Apply `args` to the output of every node started from now on
+pub fn init(args: &NodeLogArgs) -> Result<()> {
+    QUIET.store(args.quiet, Ordering::SeqCst);
+    *FILTER.lock().unwrap() = Filter {
+        min_level: args.log_level_filter,
+        modules: args.log_module_filter.clone(),
+    };
+
+    if let Some(path) = &args.log_file {
+        *LOG_FILE.lock().unwrap() = Some(RotatingFile::open(
+            path,
+            args.log_file_max_mb * 1_000_000,
+            args.log_file_keep,
+        )?);
+    }
+    Ok(())
+}
+
+/// Print a `line` of node output unless --quiet or filtered out, JSON lines are rendered readably
+pub fn echo(line: &str, events: &Events) {
+    if QUIET.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let filter = FILTER.lock().unwrap();
+    if let Some(rendered) = render(line, &filter) {
+        match events.json() {
+            true => events.emit(output::Event::Log { line }),
+            false => println!("{}", rendered),
+        }
+    }
+}
+
+fn render(line: &str, filter: &Filter) -> Option<String> {
+    let Ok(serde_json::Value::Object(mut fields)) = serde_json::from_str(line) else {
+        // plain text logs look like `3:04PM INF message module=consensus key=value`
+        let level = line.split_whitespace().nth(1).and_then(Level::parse);
+        let module = line
+            .split_whitespace()
+            .find_map(|field| field.strip_prefix("module="));
+        return filter.shows(level, module).then(|| line.to_string());
+    };
+
+    let mut take = |key: &str| match fields.remove(key) {
+        Some(serde_json::Value::String(value)) => Some(value),
+        Some(value) => Some(value.to_string()),
+        None => None,
+    };
+    let level_name = take("level");
+    let level = level_name.as_deref().and_then(Level::parse);
+    let module = take("module");
+    if !filter.shows(level, module.as_deref()) {
+        return None;
+    }
+
+    let time = take("time");
+    let message = take("message").or_else(|| take("msg")).unwrap_or_default();
+    let level_name = level_name.unwrap_or_default().to_uppercase();
+
+    let mut rendered = vec![];
+    if let Some(time) = time {
+        rendered.push(time.dimmed().to_string());
+    }
+    rendered.push(match level {
+        Some(level) => level.colorize(&format!("{:<5}", level_name)),
+        None => format!("{:<5}", level_name),
+    });
+    if let Some(module) = module {
+        rendered.push(format!("[{}]", module).cyan().to_string());
+    }
+    rendered.push(message);
+    for (key, value) in fields {
+        let value = match value {
+            serde_json::Value::String(value) => value,
+            value => value.to_string(),
+        };
+        rendered.push(format!("{}={}", key.dimmed(), value));
+    }
+
+    Some(rendered.join(" "))
+}
+
+/// Write a `line` of node output to --log-file, if set
+pub fn write(line: &str) {
+    if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+        // logging must not take the run down, e.g. on a full disk
+        file.write_line(line).ok();
+    }
+}
+
+/// Start a fresh --log-file, if set, keeping the current one as the newest rotated file
+pub fn rotate() -> Result<()> {
+    if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+        file.rotate().wrap_err("Failed to rotate log file")?;
+    }
+    Ok(())
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    keep: u32,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_size: u64, keep: u32) -> Result<RotatingFile> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err(format!("Failed to open log file: {}", path.display()))?;
+
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            size: file.metadata()?.len(),
+            file,
+            max_size,
+            keep,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        if self.max_size > 0 && self.size + line.len() as u64 + 1 > self.max_size {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// Shift <path>.N-1 to <path>.N down to <path> to <path>.1, dropping the oldest
+    fn rotate(&mut self) -> Result<()> {
+        let rotated = |n: u32| PathBuf::from(format!("{}.{}", self.path.display(), n));
+
+        if self.keep > 0 {
+            for n in (1..self.keep).rev() {
+                if rotated(n).exists() {
+                    std::fs::rename(rotated(n), rotated(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated(1))?;
+        }
+
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(min_level: Option<Level>, modules: &[&str]) -> Filter {
+        Filter {
+            min_level,
+            modules: modules.iter().map(|module| module.to_string()).collect(),
+        }
+    }
+
+    fn plain(line: &str, filter: &Filter) -> Option<String> {
+        colored::control::set_override(false);
+        render(line, filter)
+    }
+
+    #[test]
+    fn parses_json_and_short_plain_text_levels() {
+        assert_eq!(Level::parse("info"), Some(Level::Info));
+        assert_eq!(Level::parse("INF"), Some(Level::Info));
+        assert_eq!(Level::parse("warning"), Some(Level::Warn));
+        assert_eq!(Level::parse("ftl"), Some(Level::Error));
+        assert_eq!(Level::parse("panic"), Some(Level::Error));
+        assert_eq!(Level::parse(""), None);
+        assert_eq!(Level::parse("3:04PM"), None);
+    }
+
+    #[test]
+    fn renders_json_lines_readably() {
+        let line = r#"{"level":"info","module":"consensus","height":12,"time":"2024-07-01T10:00:00Z","message":"finalizing commit of block"}"#;
+        assert_eq!(
+            plain(line, &filter(None, &[])).unwrap(),
+            "2024-07-01T10:00:00Z INFO  [consensus] finalizing commit of block height=12"
+        );
+
+        // osmosisd logs `msg` on some versions and may leave out the rest, the level column
+        // stays aligned
+        assert_eq!(
+            plain(r#"{"msg":"starting"}"#, &filter(None, &[])).unwrap(),
+            format!("{:<5} starting", "")
+        );
+    }
+
+    #[test]
+    fn filters_json_and_plain_lines_by_level_and_module() {
+        let filter = filter(Some(Level::Warn), &["x/gamm"]);
+
+        for (line, shown) in [
+            (r#"{"level":"error","module":"x/gamm","message":"a"}"#, true),
+            (r#"{"level":"info","module":"x/gamm","message":"a"}"#, false),
+            (
+                r#"{"level":"error","module":"consensus","message":"a"}"#,
+                false,
+            ),
+            ("3:04PM WRN swap failed module=x/gamm", true),
+            ("3:04PM INF swap module=x/gamm", false),
+            ("3:04PM ERR timeout module=consensus", false),
+        ] {
+            assert_eq!(plain(line, &filter).is_some(), shown, "{}", line);
+        }
+    }
+
+    #[test]
+    fn always_shows_lines_without_a_level_or_module() {
+        let filter = filter(Some(Level::Error), &["consensus"]);
+
+        for line in [
+            "",
+            "panic: runtime error: index out of range",
+            "{not json",
+            r#"{"message":"no level or module"}"#,
+            "[1, 2, 3]",
+        ] {
+            assert!(plain(line, &filter).is_some(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn rotates_log_files_keeping_the_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs/node.log");
+        let rotated = |n: u32| dir.path().join(format!("logs/node.log.{}", n));
+
+        // each line is 4 bytes with its newline, so two fit
+        let mut file = RotatingFile::open(&path, 8, 2).unwrap();
+        for line in ["aaa", "bbb", "ccc", "ddd", "eee", "fff", "ggg"] {
+            file.write_line(line).unwrap();
+        }
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ggg\n");
+        assert_eq!(std::fs::read_to_string(rotated(1)).unwrap(), "eee\nfff\n");
+        assert_eq!(std::fs::read_to_string(rotated(2)).unwrap(), "ccc\nddd\n");
+        assert!(!rotated(3).exists());
+    }
+}
@@ -0,0 +1,52 @@
+use std::process::{Command, Stdio};
+
+use clap::ValueEnum;
+use color_eyre::eyre::Result;
+use serde::Deserialize;
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Notify {
+    /// Send desktop notifications via notify-send (linux) or osascript (macOS)
+    Desktop,
+    /// Don't send any notifications
+    #[default]
+    Off,
+}
+
+impl Notify {
+    /// Notify about completion or failure of a long running phase, passing its result through
+    pub fn phase<T>(self, phase: &str, result: Result<T>) -> Result<T> {
+        match &result {
+            Ok(_) => self.send(&format!("{} completed", phase)),
+            Err(e) => self.send(&format!("{} failed: {}", phase, e)),
+        }
+
+        result
+    }
+
+    fn send(self, message: &str) {
+        if self == Notify::Off {
+            return;
+        }
+
+        let mut cmd = if cfg!(target_os = "macos") {
+            let mut cmd = Command::new("osascript");
+            cmd.arg("-e").arg(format!(
+                "display notification {:?} with title \"osmoinplace\"",
+                message
+            ));
+            cmd
+        } else {
+            let mut cmd = Command::new("notify-send");
+            cmd.arg("osmoinplace").arg(message);
+            cmd
+        };
+
+        // notifications are best effort, a missing notifier shouldn't fail the run
+        let _ = cmd
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
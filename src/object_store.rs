@@ -0,0 +1,202 @@
+use std::{
+    io::BufReader,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use futures::StreamExt;
+use tokio::io::AsyncWriteExt;
+
+use crate::{backup, policy::Policy, shutdown};
+
+/// Backup kept in object storage instead of on the local disk, given as the `--path` of `backup`
+/// and `restore`
+#[derive(Clone, Debug)]
+pub enum Location {
+    /// `s3://bucket/key`, through the aws CLI
+    S3(String),
+    /// `gs://bucket/key`, through the gsutil CLI
+    Gcs(String),
+    /// `http(s)://...`, uploaded with PUT and downloaded with GET
+    Http(String),
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::S3(url) | Location::Gcs(url) | Location::Http(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+impl Location {
+    /// Object storage location `path` points to, `None` for local paths
+    pub fn parse(path: &Path) -> Option<Location> {
+        let url = path.to_str()?.to_string();
+        match url.split_once("://")?.0 {
+            "s3" => Some(Location::S3(url)),
+            "gs" => Some(Location::Gcs(url)),
+            "http" | "https" => Some(Location::Http(url)),
+            _ => None,
+        }
+    }
+
+    /// CLI streaming an object from stdin (`upload`) or to stdout
+    fn cli(&self, upload: bool) -> Option<Command> {
+        let (program, url) = match self {
+            Location::S3(url) => ("aws", url),
+            Location::Gcs(url) => ("gsutil", url),
+            Location::Http(_) => return None,
+        };
+
+        let mut cmd = Command::new(program);
+        if program == "aws" {
+            cmd.arg("s3");
+        }
+        match upload {
+            true => cmd.arg("cp").arg("-").arg(url),
+            false => cmd.arg("cp").arg(url).arg("-"),
+        };
+        Some(cmd)
+    }
+}
+
+/// Stream `osmosis_home` as a compressed archive to `location`
+pub async fn upload(
+    osmosis_home: &Path,
+    location: &Location,
+    backup_args: &backup::BackupArgs,
+    policy: &Policy,
+) -> Result<()> {
+    let data_only = backup_args.data_only;
+    let compression = match backup_args.compress {
+        backup::Compression::None => backup::Compression::Zstd,
+        compression => compression,
+    };
+
+    if let Some(mut cmd) = location.cli(true) {
+        let osmosis_home = osmosis_home.to_path_buf();
+        return tokio::task::spawn_blocking(move || -> Result<()> {
+            let mut child = cmd
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .wrap_err(format!("Failed to run {:?}", cmd.get_program()))?;
+            let stdin = child
+                .stdin
+                .take()
+                .ok_or_else(|| eyre!("Failed to open stdin"))?;
+            if let Err(e) = backup::pack_into(&osmosis_home, stdin, compression, data_only) {
+                // don't leave a partial object behind the cli
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(eyre!("Upload failed with {}", status));
+            }
+            Ok(())
+        })
+        .await?;
+    }
+
+    // http servers need the length upfront, so the archive is staged on disk
+    let Location::Http(url) = location else {
+        unreachable!()
+    };
+    let archive = staging_path();
+    let (home, staged) = (osmosis_home.to_path_buf(), archive.clone());
+    tokio::task::spawn_blocking(move || backup::pack(&home, &staged, compression, data_only))
+        .await??;
+
+    let client = &policy.client()?;
+    let archive_path = &archive;
+    let result = policy
+        .network("Uploading backup", || async move {
+            let file = tokio::fs::File::open(archive_path).await?;
+            let len = file.metadata().await?.len();
+            client
+                .put(url)
+                .header(reqwest::header::CONTENT_LENGTH, len)
+                .body(file)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+        .await;
+
+    remove_staged(&archive);
+    result
+}
+
+/// Stream the compressed archive at `location` into `osmosis_home`, only the chain state if
+/// `data_only`
+pub async fn download(
+    location: &Location,
+    osmosis_home: &Path,
+    data_only: bool,
+    policy: &Policy,
+) -> Result<()> {
+    if let Some(mut cmd) = location.cli(false) {
+        let osmosis_home = osmosis_home.to_path_buf();
+        return tokio::task::spawn_blocking(move || -> Result<()> {
+            let mut child = cmd
+                .stdout(Stdio::piped())
+                .spawn()
+                .wrap_err(format!("Failed to run {:?}", cmd.get_program()))?;
+            let stdout = child
+                .stdout
+                .take()
+                .ok_or_else(|| eyre!("Failed to open stdout"))?;
+            backup::unpack_from(BufReader::new(stdout), &osmosis_home, data_only)?;
+
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(eyre!("Download failed with {}", status));
+            }
+            Ok(())
+        })
+        .await?;
+    }
+
+    let Location::Http(url) = location else {
+        unreachable!()
+    };
+    let archive = staging_path();
+    let client = &policy.client()?;
+    let archive_path = &archive;
+    policy
+        .network("Downloading backup", || async move {
+            let response = client.get(url).send().await?.error_for_status()?;
+            let mut file = tokio::fs::File::create(archive_path).await?;
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                file.write_all(&chunk?).await?;
+            }
+            file.flush().await?;
+            Ok(())
+        })
+        .await?;
+
+    let (staged, home) = (archive.clone(), osmosis_home.to_path_buf());
+    let result =
+        tokio::task::spawn_blocking(move || backup::unpack(&staged, &home, data_only)).await?;
+    remove_staged(&archive);
+    result
+}
+
+/// Temp file to stage an archive in, removed on exit if the transfer is interrupted
+fn staging_path() -> PathBuf {
+    let path = std::env::temp_dir().join(format!("osmoinplace-backup-{}.tar", std::process::id()));
+    shutdown::track(&path);
+    path
+}
+
+fn remove_staged(path: &Path) {
+    std::fs::remove_file(path).ok();
+    shutdown::untrack(path);
+}
@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{eyre, Result};
+
+use crate::{
+    backup, object_store, phase,
+    phase::Phase,
+    snapshot::{self, DownloadArgs},
+    Commands, IndexerCommands,
+};
+
+/// Fail fast with everything `command` would need from the network, so nothing is downloaded
+/// behind the user's back when running with --offline
+pub fn check(command: &Commands, osmosis_home: &Path) -> Result<()> {
+    let missing = missing_artifacts(command, osmosis_home)?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    Err(eyre!(
+        "Network access is disabled by --offline but the following are missing:\n{}",
+        missing
+            .iter()
+            .map(|artifact| format!("  - {}", artifact))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+fn missing_artifacts(command: &Commands, osmosis_home: &Path) -> Result<Vec<String>> {
+    let mut missing = vec![];
+
+    match command {
+        Commands::DownloadMainnetState { download_args } => {
+            missing_download_artifacts(download_args, &mut missing)
+        }
+        Commands::Restore { path, name, .. } => {
+            missing_backup(&backup::named_or(path, name), &mut missing)
+        }
+        Commands::StartSync { .. } => {
+            missing.push("mainnet peers, syncing needs the network".to_string())
+        }
+        Commands::FollowMainnet { .. } => {
+            missing.push("mainnet peers, following mainnet needs the network".to_string())
+        }
+        Commands::BootstrapStateSync { .. } => {
+            missing.push("mainnet RPC servers, state sync needs the network".to_string())
+        }
+        Commands::MagicStart {
+            download_mainnet_state,
+            state_sync,
+            download_args,
+            backup_path,
+            phases,
+            skip_phase,
+            ..
+        } => {
+            let phases = phase::select(phases, skip_phase)?;
+            if phases.contains(&Phase::Download) {
+                match (state_sync, download_mainnet_state) {
+                    (true, _) => missing
+                        .push("mainnet RPC servers, state sync needs the network".to_string()),
+                    (false, true) => missing_download_artifacts(download_args, &mut missing),
+                    (false, false) => missing_backup(backup_path, &mut missing),
+                }
+            }
+            if phases.first() == Some(&Phase::Fork) && !osmosis_home.exists() {
+                missing.push(format!("osmosis home at {}", osmosis_home.display()));
+            }
+            if phases.contains(&Phase::Sync) {
+                missing.push(
+                    "mainnet peers, the sync phase needs the network (fork an already synced home with --phases fork)"
+                        .to_string(),
+                );
+            }
+        }
+        Commands::Indexer {
+            command: IndexerCommands::Up { .. },
+        } => missing.push("CometBFT indexer schema, it is fetched from github".to_string()),
+        Commands::Backup { path, name, .. }
+            if backup::named_or(path, name)
+                .is_some_and(|path| object_store::Location::parse(&path).is_some()) =>
+        {
+            missing.push("object storage, the backup is uploaded there".to_string())
+        }
+        Commands::Backup { .. }
+        | Commands::StartInPlaceTestnet { .. }
+        | Commands::StartStandalone { .. }
+        | Commands::Resume { .. }
+        | Commands::GhaSetup { .. }
+        | Commands::GasReport { .. }
+        | Commands::Snapshot { .. }
+        | Commands::Cache { .. }
+        | Commands::Env
+        | Commands::Exec { .. }
+        | Commands::Du
+        | Commands::Stop
+        | Commands::Status
+        | Commands::Wait { .. }
+        | Commands::Logs { .. }
+        | Commands::AssertChanges { .. }
+        | Commands::Inject { .. }
+        | Commands::Impersonate { .. }
+        | Commands::Accounts { .. }
+        | Commands::Faucet { .. }
+        | Commands::Keys { .. }
+        | Commands::Bin { .. }
+        | Commands::Instances { .. }
+        | Commands::Gov { .. } => {}
+    }
+
+    if !osmosis_home.exists() && needs_home(command) {
+        missing.push(format!("osmosis home at {}", osmosis_home.display()));
+    }
+
+    Ok(missing)
+}
+
+fn needs_home(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Backup { command: None, .. }
+            | Commands::Snapshot { .. }
+            | Commands::StartInPlaceTestnet { .. }
+            | Commands::StartStandalone { .. }
+            | Commands::Resume { .. }
+    )
+}
+
+fn missing_download_artifacts(download_args: &DownloadArgs, missing: &mut Vec<String>) {
+    match download_args.local_snapshot() {
+        Some(snapshot_file) if !snapshot_file.exists() => {
+            missing.push(format!("snapshot archive at {}", snapshot_file.display()))
+        }
+        Some(_) => {}
+        None if download_args
+            .snapshot_height
+            .and_then(|height| snapshot::cached(height, download_args.skip_checksum))
+            .is_some() => {}
+        None => missing.push(
+            "snapshot archive, pass --snapshot-file, a file:// --snapshot-url or the --snapshot-height of a cached snapshot".to_string(),
+        ),
+    }
+
+    // any local mirror will do, the others are skipped
+    let genesis_files = download_args
+        .genesis_url
+        .iter()
+        .filter_map(|url| url.strip_prefix("file://"))
+        .collect::<Vec<_>>();
+    if genesis_files.is_empty() {
+        missing.push("genesis file, pass a file:// --genesis-url".to_string());
+    } else if !genesis_files.iter().any(|file| Path::new(file).exists()) {
+        missing.push(format!("genesis file at {}", genesis_files.join(" or ")));
+    }
+}
+
+fn missing_backup(path: &Option<PathBuf>, missing: &mut Vec<String>) {
+    let backup_path = path.clone().unwrap_or_else(backup::default_path);
+
+    if object_store::Location::parse(&backup_path).is_some() {
+        missing.push(format!(
+            "backup at {}, it is in object storage",
+            backup_path.display()
+        ));
+    } else if !backup_path.exists() {
+        missing.push(format!("backup at {}", backup_path.display()));
+    }
+}
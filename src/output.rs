@@ -0,0 +1,241 @@
+use std::{
+    fs::File,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{Context, Result};
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use serde::Serialize;
+
+use crate::{endpoints::Endpoints, platform, readiness};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Output {
+    /// Spinners, progress bars and the node's logs
+    #[default]
+    Human,
+    /// Newline delimited JSON events on stdout, with anything else on stderr
+    Json,
+}
+
+/// Progress reported with `--output json`, one JSON object per line
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    PhaseStarted {
+        phase: &'a str,
+    },
+    PhaseCompleted {
+        phase: &'a str,
+    },
+    StepStarted {
+        step: &'a str,
+    },
+    StepCompleted {
+        step: &'a str,
+    },
+    DownloadProgress {
+        downloaded: u64,
+        total: u64,
+    },
+    Log {
+        line: &'a str,
+    },
+    NodeReady {
+        height: Option<u64>,
+        rpc: String,
+    },
+    UpgradeComplete {
+        upgrade: &'a str,
+        height: Option<u64>,
+    },
+    Failure {
+        error: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(flatten)]
+    event: Event<'a>,
+    timestamp: u64,
+}
+
+/// Where `--output json` events go, reporting nothing in human mode. Clones report to the same
+/// place.
+#[derive(Clone, Default)]
+pub struct Events(Option<Arc<Mutex<Sink>>>);
+
+struct Sink {
+    /// Original stdout, where events go in JSON mode while everything else is moved to stderr
+    out: File,
+    phase: Option<&'static str>,
+    /// Upgrade the node halted for, completed once the next binary is ready
+    pending_upgrade: Option<String>,
+}
+
+impl Sink {
+    fn emit(&mut self, event: Event) {
+        let record = Record {
+            event,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default(),
+        };
+        if let Ok(line) = serde_json::to_string(&record) {
+            let _ = writeln!(self.out, "{}", line);
+        }
+    }
+}
+
+impl Events {
+    /// Report progress as `output`. JSON mode keeps stdout to itself by pointing the process's
+    /// stdout, and with it every println and child process, at stderr.
+    pub fn init(output: Output) -> Result<Events> {
+        if output == Output::Human {
+            return Ok(Events::default());
+        }
+
+        let out = platform::take_stdout().wrap_err("Failed to move stdout to stderr")?;
+        Ok(Events(Some(Arc::new(Mutex::new(Sink {
+            out,
+            phase: None,
+            pending_upgrade: None,
+        })))))
+    }
+
+    pub fn json(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn emit(&self, event: Event) {
+        if let Some(sink) = &self.0 {
+            sink.lock().unwrap().emit(event);
+        }
+    }
+
+    /// Write the result of a reporting command like `status` as a single JSON object on stdout
+    pub fn report(&self, report: &impl Serialize) -> Result<()> {
+        if let Some(sink) = &self.0 {
+            writeln!(
+                sink.lock().unwrap().out,
+                "{}",
+                serde_json::to_string(report)?
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Complete the previous phase and start `phase`
+    pub fn phase(&self, phase: &'static str) {
+        let Some(sink) = &self.0 else {
+            return;
+        };
+        let mut sink = sink.lock().unwrap();
+        if let Some(previous) = sink.phase.replace(phase) {
+            sink.emit(Event::PhaseCompleted { phase: previous });
+        }
+        sink.emit(Event::PhaseStarted { phase });
+    }
+
+    /// Spinner steps are named after their message, e.g. "Downloading genesis"
+    pub fn step_started(&self, message: &str) {
+        self.emit(Event::StepStarted {
+            step: message.trim_end_matches("..."),
+        });
+    }
+
+    pub fn step_completed(&self, message: &str) {
+        self.emit(Event::StepCompleted {
+            step: message.trim_end_matches("..."),
+        });
+    }
+
+    /// Hide `pb` in JSON mode and report its progress as events instead
+    pub fn progress(&self, pb: &ProgressBar) {
+        if !self.json() {
+            return;
+        }
+
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+        // stops reporting once the download finished or was given up
+        let pb = pb.downgrade();
+        let events = self.clone();
+        tokio::spawn(async move {
+            while let Some(pb) = pb.upgrade().filter(|pb| !pb.is_finished()) {
+                events.emit(Event::DownloadProgress {
+                    downloaded: pb.position(),
+                    total: pb.length().unwrap_or_default(),
+                });
+                drop(pb);
+                tokio::time::sleep(PROGRESS_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Record that the node halted for upgrade `name`
+    pub fn upgrade_halted(&self, name: &str) {
+        if let Some(sink) = &self.0 {
+            sink.lock().unwrap().pending_upgrade = Some(name.to_string());
+        }
+    }
+
+    /// Report the node at `endpoints` ready, completing the upgrade it halted for if there was
+    /// one
+    pub async fn node_ready(&self, endpoints: &Endpoints) {
+        let Some(sink) = &self.0 else {
+            return;
+        };
+
+        let height = readiness::status(endpoints)
+            .await
+            .ok()
+            .map(|status| status.latest_block_height);
+        let mut sink = sink.lock().unwrap();
+        if let Some(upgrade) = sink.pending_upgrade.take() {
+            sink.emit(Event::UpgradeComplete {
+                upgrade: &upgrade,
+                height,
+            });
+        }
+        sink.emit(Event::NodeReady {
+            height,
+            rpc: endpoints.rpc_url(),
+        });
+    }
+
+    /// Complete the last phase, or report the error the run failed with
+    pub fn finish(&self, result: &Result<()>) {
+        let Some(sink) = &self.0 else {
+            return;
+        };
+        let mut sink = sink.lock().unwrap();
+        match result {
+            Ok(()) => {
+                if let Some(phase) = sink.phase.take() {
+                    sink.emit(Event::PhaseCompleted { phase });
+                }
+            }
+            Err(e) => sink.emit(Event::Failure {
+                error: &format!("{:#}", e),
+            }),
+        }
+    }
+}
+
+/// Abandons a spinner that wasn't finished when dropped, e.g. by an early return
+pub struct StopSpinner(pub ProgressBar);
+
+impl Drop for StopSpinner {
+    fn drop(&mut self) {
+        if !self.0.is_finished() {
+            self.0.abandon();
+        }
+    }
+}
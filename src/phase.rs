@@ -0,0 +1,89 @@
+use clap::ValueEnum;
+use color_eyre::eyre::{eyre, Result};
+
+/// Phases of the magic-start pipeline, in the order they run
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Download mainnet state, or restore it from backup
+    Download,
+    /// Sync the chain to the first block after the snapshot
+    Sync,
+    /// Start the in-place testnet
+    Fork,
+    /// Run the upgrade with the new binary
+    Upgrade,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [Phase::Download, Phase::Sync, Phase::Fork, Phase::Upgrade];
+}
+
+/// Phases to run given `--phases` and `--skip-phase`, which must form a contiguous part of the
+/// pipeline since each phase works on the state the previous one left behind
+pub fn select(phases: &[Phase], skip: &[Phase]) -> Result<Vec<Phase>> {
+    let selected = Phase::ALL
+        .into_iter()
+        .filter(|phase| phases.is_empty() || phases.contains(phase))
+        .filter(|phase| !skip.contains(phase))
+        .collect::<Vec<_>>();
+
+    let (Some(first), Some(last)) = (selected.first(), selected.last()) else {
+        return Err(eyre!("No phases selected"));
+    };
+
+    let contiguous = Phase::ALL
+        .into_iter()
+        .filter(|phase| phase >= first && phase <= last)
+        .eq(selected.iter().copied());
+
+    if !contiguous {
+        return Err(eyre!(
+            "Selected phases {:?} are not contiguous, each phase needs the state of the previous one",
+            selected
+        ));
+    }
+
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_the_whole_pipeline_by_default() {
+        assert_eq!(select(&[], &[]).unwrap(), Phase::ALL);
+    }
+
+    #[test]
+    fn selects_a_contiguous_part() {
+        assert_eq!(
+            select(&[Phase::Fork, Phase::Sync], &[]).unwrap(),
+            [Phase::Sync, Phase::Fork]
+        );
+        assert_eq!(
+            select(&[], &[Phase::Download]).unwrap(),
+            [Phase::Sync, Phase::Fork, Phase::Upgrade]
+        );
+        assert_eq!(
+            select(
+                &[Phase::Download, Phase::Sync, Phase::Fork],
+                &[Phase::Download]
+            )
+            .unwrap(),
+            [Phase::Sync, Phase::Fork]
+        );
+    }
+
+    #[test]
+    fn rejects_gaps() {
+        assert!(select(&[Phase::Download, Phase::Fork], &[]).is_err());
+        assert!(select(&[], &[Phase::Sync]).is_err());
+    }
+
+    #[test]
+    fn rejects_an_empty_selection() {
+        assert!(select(&[Phase::Sync], &[Phase::Sync]).is_err());
+        assert!(select(&[], &Phase::ALL).is_err());
+    }
+}
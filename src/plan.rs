@@ -0,0 +1,457 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use color_eyre::eyre::Result;
+use colored::Colorize;
+
+use crate::{
+    accounts, backup, binaries, cosmovisor, phase,
+    phase::Phase,
+    policy::Policy,
+    session::Session,
+    snapshot::{self, DownloadArgs},
+    upgrade, Commands, GENESIS_URL,
+};
+
+/// Operations magic-start would run, in order
+#[derive(Default)]
+struct Plan {
+    steps: Vec<String>,
+}
+
+impl Plan {
+    fn step(&mut self, step: String) {
+        self.steps.push(step);
+    }
+
+    fn delete(&mut self, path: &Path) {
+        if path.exists() {
+            self.step(format!("{} {}", "delete".red(), path.display()));
+        }
+    }
+
+    fn spawn(&mut self, cmd: &Command) {
+        self.step(format!("{} {}", "run".cyan(), display(cmd)));
+    }
+
+    fn print(&self) {
+        println!("{}", "Dry run, magic-start would:".bold());
+        for (i, step) in self.steps.iter().enumerate() {
+            println!("{:>3}. {}", i + 1, step);
+        }
+    }
+}
+
+/// `cmd` as it would be typed into a shell, with its environment
+fn display(cmd: &Command) -> String {
+    let env = cmd
+        .get_envs()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .map(|(key, value)| format!("{}={}", key.to_string_lossy(), value.to_string_lossy()));
+    let program = std::iter::once(cmd.get_program().to_string_lossy().to_string());
+    let args = cmd.get_args().map(|arg| {
+        let arg = arg.to_string_lossy();
+        match arg.is_empty() || arg.contains(char::is_whitespace) {
+            true => format!("'{}'", arg),
+            false => arg.to_string(),
+        }
+    });
+
+    env.chain(program).chain(args).collect::<Vec<_>>().join(" ")
+}
+
+/// Print everything `command` (a magic-start) would delete, download and spawn, without running
+/// any of it. Snapshot lookups still hit the network to report what would be downloaded.
+pub async fn magic_start(
+    command: &Commands,
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    if let Some(plan) = plan(command, osmosisd, osmosis_home, policy, session).await? {
+        plan.print();
+    }
+    Ok(())
+}
+
+/// Steps of `command`, `None` for anything but magic-start
+async fn plan(
+    command: &Commands,
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    policy: &Policy,
+    session: &Session,
+) -> Result<Option<Plan>> {
+    let Commands::MagicStart {
+        download_mainnet_state,
+        download_args,
+        state_sync,
+        state_sync_args,
+        backup_path,
+        upgrade_handler,
+        new_osmosisd_bin,
+        operator_address,
+        fast_params,
+        upgrade_chain,
+        cosmovisor,
+        hooks,
+        phases,
+        skip_phase,
+        sync_args,
+        ..
+    } = command
+    else {
+        return Ok(None);
+    };
+    let phases = phase::select(phases, skip_phase)?;
+    let mut plan = Plan::default();
+
+    for binary in new_osmosisd_bin
+        .iter()
+        .chain(upgrade_chain.then_upgrade.iter().map(|(_, binary)| binary))
+        .filter(|binary| binaries::is_git(binary))
+    {
+        plan.step(format!(
+            "{} osmosisd from {}",
+            "build".cyan(),
+            binary.display()
+        ));
+    }
+
+    if phases.contains(&Phase::Download) {
+        if *state_sync {
+            plan.delete(osmosis_home);
+            plan.spawn(&crate::init_command(
+                osmosisd,
+                osmosis_home,
+                &session.endpoints,
+            ));
+            genesis_step(&mut plan, download_args);
+            plan.step(format!(
+                "{} from {}",
+                "state sync".cyan(),
+                state_sync_args.state_sync_rpc.join(", ")
+            ));
+        } else if *download_mainnet_state {
+            plan.delete(osmosis_home);
+            plan.spawn(&crate::init_command(
+                osmosisd,
+                osmosis_home,
+                &session.endpoints,
+            ));
+            genesis_step(&mut plan, download_args);
+            snapshot_step(
+                &mut plan,
+                osmosisd,
+                osmosis_home,
+                download_args,
+                policy,
+                session,
+            )
+            .await?;
+        } else {
+            let backup_path = backup_path.clone().unwrap_or_else(backup::default_path);
+            plan.delete(osmosis_home);
+            plan.step(format!(
+                "{} {} to {}",
+                "restore".cyan(),
+                backup_path.display(),
+                osmosis_home.display()
+            ));
+        }
+    }
+
+    if phases.contains(&Phase::Sync) {
+        plan.spawn(&crate::sync_command(osmosisd, osmosis_home, sync_args));
+    }
+
+    let upgrades = crate::upgrades(
+        upgrade_handler,
+        new_osmosisd_bin,
+        &upgrade_chain.resolve(operator_address.as_deref(), String::new()),
+    );
+    if cosmovisor.cosmovisor {
+        plan.step(format!(
+            "{} {} into {}",
+            "link".cyan(),
+            upgrades
+                .iter()
+                .map(|(name, binary)| format!("{}={}", name, binary.display()))
+                .collect::<Vec<_>>()
+                .join(", "),
+            osmosis_home.join("cosmovisor").display()
+        ));
+    }
+
+    if phases.contains(&Phase::Fork) {
+        // a key only exists once the state is in place, so its name stands in for it until then
+        let operator =
+            accounts::operator_address(osmosisd, osmosis_home, operator_address.as_deref())
+                .unwrap_or_else(|_| operator_address.clone().unwrap_or_default());
+        let patch_params = fast_params
+            .resolve(osmosisd, operator_address.as_deref(), String::new())?
+            .and_then(|fast_params| fast_params.patch_path(osmosis_home));
+        let cmd = crate::in_place_testnet_command(
+            osmosisd,
+            osmosis_home,
+            &operator,
+            upgrade_handler,
+            patch_params.as_deref(),
+            session,
+        );
+        plan.spawn(&cmd);
+    }
+
+    if phases.contains(&Phase::Upgrade) {
+        for (name, binary) in &upgrades {
+            let mut cmd = Command::new(binary);
+            crate::start_node_no_peers(&mut cmd, osmosis_home);
+            if cosmovisor.cosmovisor {
+                cmd = with_program(cosmovisor::run(osmosis_home), &cmd);
+            }
+            plan.step(format!(
+                "{} {}",
+                format!("upgrade {}:", name).cyan(),
+                display(&cmd)
+            ));
+        }
+    }
+
+    if let Some(manifest) = &hooks.expect_changes {
+        plan.step(format!(
+            "{} {}",
+            "check changes:".cyan(),
+            manifest.display()
+        ));
+    }
+    for (name, hook) in [
+        ("on_ready", &hooks.on_ready),
+        ("on_upgrade_complete", &hooks.on_upgrade_complete),
+        ("on_failure", &hooks.on_failure),
+        ("on_exit", &hooks.on_exit),
+    ] {
+        if let Some(hook) = hook {
+            plan.step(format!("{} {}: {}", "hook".cyan(), name, hook));
+        }
+    }
+
+    Ok(Some(plan))
+}
+
+/// `cmd`'s arguments run by `program` instead, e.g. through cosmovisor
+fn with_program(mut program: Command, cmd: &Command) -> Command {
+    program.args(cmd.get_args());
+    program
+}
+
+fn genesis_step(plan: &mut Plan, download_args: &DownloadArgs) {
+    let urls = match download_args.genesis_url.is_empty() {
+        true => vec![GENESIS_URL.to_string()],
+        false => download_args.genesis_url.clone(),
+    };
+    plan.step(format!(
+        "{} genesis from {}",
+        "download".cyan(),
+        urls.join(" or ")
+    ));
+    if let Some(overrides) = &download_args.genesis_overrides {
+        plan.step(format!(
+            "{} genesis overrides from {}",
+            "apply".cyan(),
+            overrides.display()
+        ));
+    }
+}
+
+async fn snapshot_step(
+    plan: &mut Plan,
+    osmosisd: &PathBuf,
+    osmosis_home: &Path,
+    download_args: &DownloadArgs,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    let (source, size) = match download_args.local_snapshot() {
+        Some(path) => {
+            let size = std::fs::metadata(&path).ok().map(|metadata| metadata.len());
+            (
+                snapshot::SnapshotInfo::from_url(&path.to_string_lossy()),
+                size,
+            )
+        }
+        None => {
+            let info = crate::resolve_snapshot(download_args, policy, session).await?;
+            let cached = info
+                .height
+                .and_then(|height| snapshot::cached(height, download_args.skip_checksum));
+            let size = match &cached {
+                Some(path) => std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+                None => content_length(&info.url, policy).await,
+            };
+            (info, size)
+        }
+    };
+
+    if let Some(height) = source.height {
+        if !download_args.skip_version_check {
+            upgrade::verify_binary_for_height(osmosisd, height)?;
+        }
+    }
+
+    let size = size.map_or("unknown size".to_string(), |size| {
+        format!("{:.1} GB", size as f64 / 1e9)
+    });
+    let height = source
+        .height
+        .map_or(String::new(), |height| format!(" at height {}", height));
+    plan.step(format!(
+        "{} snapshot {} ({}{}) into {}",
+        "download".cyan(),
+        source.url,
+        size,
+        height,
+        osmosis_home.display()
+    ));
+    Ok(())
+}
+
+async fn content_length(url: &str, policy: &Policy) -> Option<u64> {
+    let client = policy.client().ok()?;
+    let response = client.head(url).send().await.ok()?;
+    response.error_for_status().ok()?.content_length()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::{endpoints::Endpoints, Cli};
+
+    async fn steps(args: &[&str], osmosis_home: &Path) -> Vec<String> {
+        colored::control::set_override(false);
+        let cli = Cli::try_parse_from(["osmoinplace"].iter().chain(args)).unwrap();
+        let session = Session::new(Endpoints::new("edgenet", None));
+        plan(
+            &cli.command,
+            &PathBuf::from("osmosisd"),
+            &osmosis_home.to_path_buf(),
+            &Policy::default(),
+            &session,
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .steps
+    }
+
+    #[test]
+    fn quotes_empty_and_spaced_arguments_of_commands() {
+        let mut cmd = Command::new("osmosisd");
+        cmd.env("DAEMON_NAME", "osmosisd").args([
+            "start",
+            "--p2p.seeds",
+            "",
+            "--moniker",
+            "my node",
+        ]);
+
+        assert_eq!(
+            display(&cmd),
+            "DAEMON_NAME=osmosisd osmosisd start --p2p.seeds '' --moniker 'my node'"
+        );
+    }
+
+    #[tokio::test]
+    async fn restores_the_backup_over_an_existing_home_then_forks_and_upgrades() {
+        let home = tempfile::tempdir().unwrap();
+        let home_path = home.path().display();
+
+        let steps = steps(
+            &[
+                "magic-start",
+                "--backup-path",
+                "/backups/v25",
+                "--upgrade-handler",
+                "v26",
+                "--new-osmosisd-bin",
+                "osmosisd_v26",
+                "--on-ready",
+                "./run-tests.sh",
+            ],
+            home.path(),
+        )
+        .await;
+
+        assert_eq!(steps[0], format!("delete {}", home_path));
+        assert_eq!(steps[1], format!("restore /backups/v25 to {}", home_path));
+        assert_eq!(steps[2], format!("run osmosisd start --home {}", home_path));
+        assert_eq!(
+            steps[3],
+            format!(
+                "run osmosisd in-place-testnet edgenet {} --home {} --trigger-testnet-upgrade v26",
+                accounts::DEFAULT_OPERATOR_ADDRESS,
+                home_path
+            )
+        );
+        assert!(
+            steps[4].starts_with(&format!(
+                "upgrade v26: osmosisd_v26 start --home {}",
+                home_path
+            )),
+            "{}",
+            steps[4]
+        );
+        assert_eq!(steps[5], "hook on_ready: ./run-tests.sh");
+        assert_eq!(steps.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn only_plans_the_selected_phases() {
+        let home = tempfile::tempdir().unwrap();
+
+        let steps = steps(&["magic-start", "--phases", "fork"], home.path()).await;
+
+        assert_eq!(steps.len(), 1);
+        assert!(
+            steps[0].starts_with("run osmosisd in-place-testnet"),
+            "{}",
+            steps[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn doesnt_plan_deleting_a_missing_home_but_building_git_binaries() {
+        let home = tempfile::tempdir().unwrap();
+        let missing = home.path().join("missing");
+
+        let steps = steps(
+            &[
+                "magic-start",
+                "--upgrade-handler",
+                "v27",
+                "--new-osmosisd-bin",
+                "git:osmosis-labs/osmosis#main",
+                "--phases",
+                "download",
+            ],
+            &missing,
+        )
+        .await;
+
+        assert_eq!(
+            steps,
+            [
+                "build osmosisd from git:osmosis-labs/osmosis#main".to_string(),
+                format!(
+                    "restore {} to {}",
+                    backup::default_path().display(),
+                    missing.display()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    fmt,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+
+use crate::shutdown::Signal;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Os {
+    Linux,
+    Darwin,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arch {
+    Amd64,
+    Arm64,
+}
+
+/// Platform a binary is built for, named the way osmosis names its release artifacts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Platform {
+    pub os: Os,
+    pub arch: Arch,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let os = match self.os {
+            Os::Linux => "linux",
+            Os::Darwin => "darwin",
+        };
+        let arch = match self.arch {
+            Arch::Amd64 => "amd64",
+            Arch::Arm64 => "arm64",
+        };
+        write!(f, "{}-{}", os, arch)
+    }
+}
+
+impl Platform {
+    pub fn host() -> Result<Platform> {
+        let os = match std::env::consts::OS {
+            "linux" => Os::Linux,
+            "macos" => Os::Darwin,
+            os => return Err(eyre!("Unsupported operating system: {}", os)),
+        };
+        let arch = match std::env::consts::ARCH {
+            "x86_64" => Arch::Amd64,
+            "aarch64" => Arch::Arm64,
+            arch => return Err(eyre!("Unsupported architecture: {}", arch)),
+        };
+
+        Ok(Platform { os, arch })
+    }
+
+    /// Detect the platform of an executable from its header, `None` if it is not a native
+    /// executable (e.g. a wrapper script) or the format is not recognized
+    pub fn of_binary(path: &Path) -> Result<Option<Platform>> {
+        let mut header = [0u8; 20];
+        let mut file = std::fs::File::open(path)
+            .wrap_err(format!("Failed to open binary: {}", path.display()))?;
+        if file.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+
+        let platform = match header {
+            // ELF, e_machine at offset 18
+            [0x7f, b'E', b'L', b'F', ..] => {
+                let arch = match u16::from_le_bytes([header[18], header[19]]) {
+                    0x3e => Arch::Amd64,
+                    0xb7 => Arch::Arm64,
+                    _ => return Ok(None),
+                };
+                Platform {
+                    os: Os::Linux,
+                    arch,
+                }
+            }
+            // 64-bit Mach-O, cputype at offset 4
+            [0xcf, 0xfa, 0xed, 0xfe, ..] => {
+                let arch = match u32::from_le_bytes([header[4], header[5], header[6], header[7]]) {
+                    0x0100_0007 => Arch::Amd64,
+                    0x0100_000c => Arch::Arm64,
+                    _ => return Ok(None),
+                };
+                Platform {
+                    os: Os::Darwin,
+                    arch,
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(platform))
+    }
+}
+
+/// Fail early with a clear message if `osmosisd` is a native binary built for another platform
+pub fn check_binary(osmosisd: &Path) -> Result<()> {
+    let path =
+        which::which(osmosisd).wrap_err(format!("{} not found in PATH", osmosisd.display()))?;
+
+    let Some(binary) = Platform::of_binary(&path)? else {
+        return Ok(());
+    };
+    let host = Platform::host()?;
+
+    // apple silicon can run amd64 binaries through rosetta
+    let compatible = binary == host
+        || (host.os == Os::Darwin && binary.os == Os::Darwin && binary.arch == Arch::Amd64);
+
+    if !compatible {
+        return Err(eyre!(
+            "{} is a {} binary, but you're on {}",
+            path.display(),
+            binary,
+            host
+        ));
+    }
+
+    Ok(())
+}
+
+/// Home directory of the current user
+pub fn home_dir() -> PathBuf {
+    dirs::home_dir().expect("Failed to resolve the home directory")
+}
+
+/// Command running `script` through the platform shell, `sh -c` or `cmd /C` on Windows
+pub fn shell(script: &str) -> Command {
+    #[cfg(unix)]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    };
+    cmd.arg(script);
+    cmd
+}
+
+/// Whether `a` and `b` are on the same filesystem, so files can be linked between them
+pub fn same_filesystem(a: &Path, b: &Path) -> Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(a.metadata()?.dev() == b.metadata()?.dev())
+    }
+    #[cfg(windows)]
+    {
+        // same volume, e.g. `C:`
+        let volume = |path: &Path| -> Result<_> {
+            Ok(std::fs::canonicalize(path)?
+                .components()
+                .next()
+                .map(|prefix| prefix.as_os_str().to_ascii_lowercase()))
+        };
+        Ok(volume(a)? == volume(b)?)
+    }
+}
+
+/// Let everyone run the downloaded binary at `path`, a no-op on Windows
+pub fn make_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(windows)]
+    let _ = path;
+    Ok(())
+}
+
+/// Link `link` to the directory or file at `target`
+pub fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(windows)]
+    {
+        match target.is_dir() {
+            true => std::os::windows::fs::symlink_dir(target, link),
+            false => std::os::windows::fs::symlink_file(target, link),
+        }
+    }
+}
+
+/// Keep Ctrl-C in the terminal from reaching `cmd`, it's stopped with `signal` instead
+pub fn own_process_group(cmd: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP);
+    }
+}
+
+/// Like `own_process_group`, and without a console at all on Windows so closing the terminal
+/// leaves `cmd` running
+pub fn detach_from_terminal(cmd: &mut Command) {
+    #[cfg(unix)]
+    own_process_group(cmd);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use windows_sys::Win32::System::Threading::{CREATE_NEW_PROCESS_GROUP, DETACHED_PROCESS};
+        cmd.creation_flags(CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS);
+    }
+}
+
+/// Ask the process `pid`, started with `own_process_group`, to stop cleanly. Windows has no
+/// SIGTERM, both signals are sent as Ctrl-Break, which osmosisd handles like Ctrl-C.
+pub fn signal(pid: u32, signal: Signal) {
+    #[cfg(unix)]
+    {
+        // SAFETY: only sends a signal, callers pass a process they started
+        unsafe {
+            libc::kill(pid as libc::pid_t, signal.raw());
+        }
+    }
+    #[cfg(windows)]
+    {
+        let _ = signal;
+        // SAFETY: only sends a console event to the process group of `pid`
+        unsafe {
+            windows_sys::Win32::System::Console::GenerateConsoleCtrlEvent(
+                windows_sys::Win32::System::Console::CTRL_BREAK_EVENT,
+                pid,
+            );
+        }
+    }
+}
+
+/// Whether a process with id `pid` is running
+pub fn is_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: signal 0 only checks that the process exists
+        unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+    }
+    #[cfg(windows)]
+    {
+        Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .output()
+            .is_ok_and(|output| {
+                String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid))
+            })
+    }
+}
+
+/// Ask the detached process `pid` to stop. A detached process on Windows has no console to
+/// send Ctrl-Break to, so it's ended with its children right away there.
+pub fn terminate(pid: u32) {
+    #[cfg(unix)]
+    signal(pid, Signal::Terminate);
+    #[cfg(windows)]
+    kill(pid);
+}
+
+/// End the process `pid` without letting it clean up, with its children on Windows
+pub fn kill(pid: u32) {
+    #[cfg(unix)]
+    {
+        // SAFETY: only sends a signal, callers pass a process they started
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+    #[cfg(windows)]
+    {
+        let _ = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status();
+    }
+}
+
+/// Point the process's stdout, and with it every println and child process, at stderr,
+/// returning the original stdout
+pub fn take_stdout() -> std::io::Result<File> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::FromRawFd;
+        // SAFETY: only duplicates the process's own standard streams
+        let stdout = unsafe { libc::dup(libc::STDOUT_FILENO) };
+        if stdout < 0 || unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: `stdout` is a new descriptor nothing else owns
+        Ok(unsafe { File::from_raw_fd(stdout) })
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::{FromRawHandle, RawHandle};
+        use windows_sys::Win32::{
+            Foundation::INVALID_HANDLE_VALUE,
+            System::Console::{GetStdHandle, SetStdHandle, STD_ERROR_HANDLE, STD_OUTPUT_HANDLE},
+        };
+        // SAFETY: only swaps the process's own standard handles, the original stdout handle is
+        // owned by the returned file from then on
+        unsafe {
+            let stdout = GetStdHandle(STD_OUTPUT_HANDLE);
+            if stdout == 0
+                || stdout == INVALID_HANDLE_VALUE
+                || SetStdHandle(STD_OUTPUT_HANDLE, GetStdHandle(STD_ERROR_HANDLE)) == 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(File::from_raw_handle(stdout as RawHandle))
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    future::Future,
+    process::{Child, ExitStatus},
+    time::{Duration, Instant},
+};
+
+use clap::{ArgAction, Args};
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+
+use crate::{health, platform};
+
+/// Node log patterns that fail the run with --strict when they show up in a warning or error
+const DEFAULT_STRICT_PATTERNS: &[&str] = &["iavl", "migrat", "wasm cache"];
+
+/// Timeouts and retries applied uniformly to network operations, child processes and hooks. A
+/// timeout of 0 means no timeout.
+#[derive(Args, Debug, Clone)]
+pub struct Policy {
+    /// Timeout in seconds for each network request
+    #[arg(long, default_value_t = 60)]
+    pub network_timeout_secs: u64,
+
+    /// Number of times to retry a failed network request
+    #[arg(long, default_value_t = 3)]
+    pub network_retries: u32,
+
+    /// Delay in seconds before the first retry of a failed network request or interrupted
+    /// download, doubled on every further attempt
+    #[arg(long, default_value_t = 2)]
+    pub network_retry_delay_secs: u64,
+
+    /// Timeout in seconds for syncing the node
+    #[arg(long, default_value_t = 0)]
+    pub process_timeout_secs: u64,
+
+    /// Seconds to wait for osmosisd to close its databases after forwarding SIGINT or SIGTERM to
+    /// it, before killing it
+    #[arg(long, default_value_t = 30)]
+    pub shutdown_timeout_secs: u64,
+
+    /// Timeout in seconds for each hook command
+    #[arg(long, default_value_t = 0)]
+    pub hook_timeout_secs: u64,
+
+    /// Number of times to retry a failed hook command
+    #[arg(long, default_value_t = 0)]
+    pub hook_retries: u32,
+
+    /// Never access the network, only use local archives, caches and backups. Fails upfront
+    /// listing what is missing otherwise.
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true"
+    )]
+    pub offline: bool,
+
+    /// Proxy for all downloads, e.g. http://proxy.corp:3128. HTTPS_PROXY / HTTP_PROXY are
+    /// honored without it.
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// Fail the run when the node logs a warning or error matching one of the --strict-pattern
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true"
+    )]
+    pub strict: bool,
+
+    /// Case insensitive pattern of node warnings and errors that fail the run with --strict,
+    /// repeat to add more. Defaults to store migration, iavl and wasm cache issues.
+    #[arg(long)]
+    pub strict_pattern: Vec<String>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            network_timeout_secs: 60,
+            network_retries: 3,
+            network_retry_delay_secs: 2,
+            process_timeout_secs: 0,
+            shutdown_timeout_secs: 30,
+            hook_timeout_secs: 0,
+            hook_retries: 0,
+            offline: false,
+            proxy: None,
+            strict: false,
+            strict_pattern: vec![],
+        }
+    }
+}
+
+fn timeout(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+impl Policy {
+    pub fn process_timeout(&self) -> Option<Duration> {
+        timeout(self.process_timeout_secs)
+    }
+
+    pub fn shutdown_timeout(&self) -> Option<Duration> {
+        timeout(self.shutdown_timeout_secs)
+    }
+
+    /// HTTP client for downloads, going through --proxy if set
+    pub fn client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &self.proxy {
+            builder = builder
+                .proxy(reqwest::Proxy::all(proxy).wrap_err(format!("Invalid proxy: {}", proxy))?);
+        }
+        builder.build().wrap_err("Failed to build HTTP client")
+    }
+
+    /// Exponential backoff before retry `attempt` (starting at 1) of a network operation
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        Duration::from_secs(
+            self.network_retry_delay_secs * 2u64.pow(attempt.saturating_sub(1).min(6)),
+        )
+    }
+
+    /// Fail on a node log `line` that is a warning or error matching a strict pattern
+    pub fn check_strict(&self, line: &str) -> Result<()> {
+        if !self.strict || !(line.contains("WRN") || line.contains("ERR")) {
+            return Ok(());
+        }
+
+        let lowercase = line.to_lowercase();
+        let matched = match self.strict_pattern.is_empty() {
+            true => DEFAULT_STRICT_PATTERNS
+                .iter()
+                .any(|pattern| lowercase.contains(pattern)),
+            false => self
+                .strict_pattern
+                .iter()
+                .any(|pattern| lowercase.contains(&pattern.to_lowercase())),
+        };
+
+        match matched {
+            true => Err(eyre!("Node logged a warning in strict mode: {}", line)),
+            false => Ok(()),
+        }
+    }
+
+    /// Run network operation `f`, timing out and retrying it with exponential backoff as configured
+    pub async fn network<T, F, Fut>(&self, what: &str, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        if self.offline {
+            return Err(eyre!(
+                "{} needs the network, which is disabled by --offline",
+                what
+            ));
+        }
+
+        let mut attempt = 0;
+        loop {
+            let result = match timeout(self.network_timeout_secs) {
+                Some(timeout) => tokio::time::timeout(timeout, f())
+                    .await
+                    .unwrap_or_else(|_| Err(eyre!("Timed out after {:?}", timeout))),
+                None => f().await,
+            };
+
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.network_retries => {
+                    attempt += 1;
+                    eprintln!(
+                        "{}",
+                        format!(
+                            "{} failed ({}), retrying [{}/{}]",
+                            what, e, attempt, self.network_retries
+                        )
+                        .yellow()
+                    );
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                }
+                Err(e) => return Err(e.wrap_err(format!("{} failed", what))),
+            }
+        }
+    }
+
+    /// Run network operation `f` against each of the mirror `urls` in order until one succeeds,
+    /// with the configured timeouts and retries for each. `file://` mirrors are local, `f` runs
+    /// once for them, with --offline too.
+    pub async fn mirrors<T, F, Fut>(&self, what: &str, urls: &[String], mut f: F) -> Result<T>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_error = None;
+        for url in urls {
+            let result = match url.starts_with("file://") {
+                true => f(url.clone()).await,
+                false => {
+                    self.network(&format!("{} from {}", what, url), || f(url.clone()))
+                        .await
+                }
+            };
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| eyre!("{} failed, no urls given", what)))
+    }
+
+    /// Run hook command through the shell with `env` added to its environment, timing out and
+    /// retrying it as configured
+    pub fn run_hook(&self, hook: &str, env: &[(&str, String)]) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            let mut child = platform::shell(hook)
+                .envs(env.iter().map(|(key, value)| (key, value)))
+                .spawn()
+                .wrap_err(format!("Failed to execute hook: {}", hook))?;
+
+            let result = match wait_with_timeout(&mut child, timeout(self.hook_timeout_secs))? {
+                Some(status) if status.success() => Ok(()),
+                Some(status) => Err(eyre!("Hook `{}` exited with {}", hook, status)),
+                None => Err(eyre!(
+                    "Hook `{}` timed out after {}s",
+                    hook,
+                    self.hook_timeout_secs
+                )),
+            };
+
+            if result.is_err() {
+                health::record_hook_failure();
+            }
+
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < self.hook_retries => {
+                    attempt += 1;
+                    eprintln!(
+                        "{}",
+                        format!("{}, retrying [{}/{}]", e, attempt, self.hook_retries).yellow()
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Wait for `child` to exit, killing it and returning `None` if it runs past `timeout`
+fn wait_with_timeout(child: &mut Child, timeout: Option<Duration>) -> Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return Ok(Some(child.wait()?));
+    };
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+
+        if started.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
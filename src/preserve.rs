@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Context, Result};
+
+/// Tuned node configuration, relative to the home
+const CONFIG_FILES: [&str; 3] = [
+    "config/app.toml",
+    "config/config.toml",
+    "config/client.toml",
+];
+
+/// Node identity, relative to the home
+const KEY_FILES: [&str; 2] = ["config/node_key.json", "config/priv_validator_key.json"];
+
+/// Files copied aside on disk while the home is wiped and re-initialized
+pub struct Stash {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+/// Copy the config and/or key files of `osmosis_home` that exist to a temporary directory, so
+/// they survive re-initialization and a crash in the middle of it
+pub fn stash(osmosis_home: &Path, config: bool, keys: bool) -> Result<Stash> {
+    let files = CONFIG_FILES
+        .iter()
+        .filter(|_| config)
+        .chain(KEY_FILES.iter().filter(|_| keys))
+        .map(PathBuf::from)
+        .filter(|file| osmosis_home.join(file).exists())
+        .collect::<Vec<_>>();
+
+    let dir = std::env::temp_dir().join(format!("osmoinplace-preserve-{}", std::process::id()));
+    for file in &files {
+        let stashed = dir.join(file);
+        if let Some(parent) = stashed.parent() {
+            std::fs::create_dir_all(parent)
+                .wrap_err(format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::copy(osmosis_home.join(file), &stashed).wrap_err(format!(
+            "Failed to stash {}",
+            osmosis_home.join(file).display()
+        ))?;
+    }
+
+    Ok(Stash { dir, files })
+}
+
+impl Stash {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Copy the stashed files back over the freshly initialized ones, or into the wiped home
+    /// when re-initialization failed, and drop the stash
+    pub fn restore(self, osmosis_home: &Path) -> Result<()> {
+        for file in &self.files {
+            let path = osmosis_home.join(file);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .wrap_err(format!("Failed to create {}", parent.display()))?;
+            }
+            std::fs::copy(self.dir.join(file), &path).wrap_err(format!(
+                "Failed to restore {}, a copy is kept in {}",
+                path.display(),
+                self.dir.display()
+            ))?;
+        }
+        let _ = std::fs::remove_dir_all(&self.dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use crate::{fees::FeesPreset, notify::Notify};
+
+/// Named set of defaults for command line options, flags given on the command line always win
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub home_dir: Option<PathBuf>,
+    pub chain_id: Option<String>,
+    pub osmosisd_bin: Option<PathBuf>,
+    pub notify: Option<Notify>,
+    pub notify_url: Option<String>,
+    pub safe: Option<bool>,
+    pub offline: Option<bool>,
+    pub strict: Option<bool>,
+    pub strict_pattern: Option<Vec<String>>,
+    pub fees_preset: Option<FeesPreset>,
+    pub backup_path: Option<PathBuf>,
+    pub upgrade_handler: Option<String>,
+    pub new_osmosisd_bin: Option<PathBuf>,
+    pub operator_address: Option<String>,
+    /// Chained upgrades as `name=binary`, like --then-upgrade
+    pub then_upgrade: Option<Vec<String>>,
+    pub cosmovisor: Option<bool>,
+    /// Accounts to fund as `address:coins`, like --fund
+    pub fund: Option<Vec<String>>,
+    pub accounts_file: Option<PathBuf>,
+    pub fund_from: Option<String>,
+    pub fast_gov: Option<bool>,
+    pub short_unbonding: Option<bool>,
+    pub fast_epochs: Option<bool>,
+    pub deploy_contracts: Option<PathBuf>,
+    pub on_ready: Option<String>,
+    pub on_failure: Option<String>,
+    pub on_exit: Option<String>,
+    pub on_upgrade_complete: Option<String>,
+    pub on_ready_timeout: Option<u64>,
+    pub on_ready_retries: Option<u32>,
+    pub expect_changes: Option<PathBuf>,
+    pub network_timeout_secs: Option<u64>,
+    pub network_retries: Option<u32>,
+    pub network_retry_delay_secs: Option<u64>,
+    pub proxy: Option<String>,
+    pub process_timeout_secs: Option<u64>,
+    pub shutdown_timeout_secs: Option<u64>,
+    pub hook_timeout_secs: Option<u64>,
+    pub hook_retries: Option<u32>,
+    pub maintenance_interval_hours: Option<u64>,
+    pub maintenance_keep_recent: Option<u64>,
+}
+
+/// Load profile `name` from `config`, profiles in the sibling `<name>.local.toml` (meant to be
+/// kept out of version control) overlay the ones in `config` field by field.
+pub fn load(config: &Path, name: &str) -> Result<Profile> {
+    Value::Table(load_table(config, name)?)
+        .try_into()
+        .wrap_err(format!("Invalid profile {}", name))
+}
+
+/// Fields of profile `name` as `load` resolves them, with the `extends` chain flattened
+pub fn load_table(config: &Path, name: &str) -> Result<Table> {
+    let mut profiles = read_profiles(config)?
+        .ok_or_else(|| eyre!("Config file not found: {}", config.display()))?;
+
+    if let Some(local) = read_profiles(&local_config_path(config))? {
+        merge(&mut profiles, local);
+    }
+
+    resolve(&profiles, name, &mut vec![])
+}
+
+fn local_config_path(config: &Path) -> PathBuf {
+    let stem = config
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    config.with_file_name(format!("{}.local.toml", stem))
+}
+
+fn read_profiles(path: &Path) -> Result<Option<Table>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)
+        .wrap_err(format!("Failed to read config file: {}", path.display()))?;
+    let mut config: Table = content
+        .parse()
+        .wrap_err(format!("Failed to parse config file: {}", path.display()))?;
+
+    match config.remove("profiles") {
+        Some(Value::Table(profiles)) => Ok(Some(profiles)),
+        Some(_) => Err(eyre!("`profiles` in {} must be a table", path.display())),
+        None => Ok(Some(Table::new())),
+    }
+}
+
+/// Flatten the `extends` chain of profile `name`, later profiles override fields of earlier ones
+fn resolve(profiles: &Table, name: &str, chain: &mut Vec<String>) -> Result<Table> {
+    if chain.iter().any(|n| n == name) {
+        chain.push(name.to_string());
+        return Err(eyre!(
+            "Circular profile inheritance: {}",
+            chain.join(" -> ")
+        ));
+    }
+    chain.push(name.to_string());
+
+    let profile = profiles
+        .get(name)
+        .and_then(Value::as_table)
+        .ok_or_else(|| eyre!("Profile not found: {}", name))?;
+
+    let mut resolved = match profile.get("extends") {
+        Some(Value::String(base)) => resolve(profiles, base, chain)?,
+        Some(_) => return Err(eyre!("`extends` of profile {} must be a string", name)),
+        None => Table::new(),
+    };
+    merge(&mut resolved, profile.clone());
+    resolved.remove("extends");
+
+    Ok(resolved)
+}
+
+fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiles(content: &str) -> Table {
+        content.parse::<Table>().unwrap()["profiles"]
+            .as_table()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn extending_profiles_override_their_bases() {
+        let profiles = profiles(
+            r#"
+            [profiles.base]
+            chain_id = "base"
+            home_dir = "/base"
+            offline = true
+
+            [profiles.ci]
+            extends = "base"
+            chain_id = "ci"
+
+            [profiles.nightly]
+            extends = "ci"
+            home_dir = "/nightly"
+            "#,
+        );
+
+        let resolved = resolve(&profiles, "nightly", &mut vec![]).unwrap();
+
+        assert_eq!(resolved["chain_id"].as_str(), Some("ci"));
+        assert_eq!(resolved["home_dir"].as_str(), Some("/nightly"));
+        assert_eq!(resolved["offline"].as_bool(), Some(true));
+        assert!(!resolved.contains_key("extends"));
+    }
+
+    #[test]
+    fn local_config_overlays_profiles_field_by_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("osmoinplace.toml");
+        std::fs::write(
+            &config,
+            r#"
+            [profiles.ci]
+            chain_id = "ci"
+            home_dir = "/shared"
+            "#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("osmoinplace.local.toml"),
+            r#"
+            [profiles.ci]
+            home_dir = "/mine"
+            "#,
+        )
+        .unwrap();
+
+        let profile = load(&config, "ci").unwrap();
+
+        assert_eq!(profile.chain_id.as_deref(), Some("ci"));
+        assert_eq!(profile.home_dir, Some(PathBuf::from("/mine")));
+    }
+
+    #[test]
+    fn rejects_circular_inheritance() {
+        let profiles = profiles(
+            r#"
+            [profiles.a]
+            extends = "b"
+
+            [profiles.b]
+            extends = "a"
+            "#,
+        );
+
+        let error = resolve(&profiles, "a", &mut vec![]).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Circular profile inheritance: a -> b -> a"
+        );
+    }
+}
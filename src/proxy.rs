@@ -0,0 +1,131 @@
+use std::{
+    io::BufReader,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::{
+    rustls::{
+        pki_types::{CertificateDer, PrivateKeyDer},
+        ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+use crate::{endpoints::Endpoints, platform};
+
+#[derive(Args, Debug, Clone)]
+pub struct GrpcTlsArgs {
+    /// Serve the node's gRPC over TLS for clients that refuse plaintext
+    #[arg(long)]
+    pub grpc_tls: bool,
+
+    /// Address to serve gRPC over TLS on
+    #[arg(long, default_value = "127.0.0.1:9443")]
+    pub grpc_tls_addr: SocketAddr,
+
+    /// PEM certificate to serve, a self-signed one is generated if not set
+    #[arg(long, requires = "grpc_tls_key")]
+    pub grpc_tls_cert: Option<PathBuf>,
+
+    /// PEM private key of --grpc-tls-cert
+    #[arg(long, requires = "grpc_tls_cert")]
+    pub grpc_tls_key: Option<PathBuf>,
+}
+
+/// Terminate TLS on `args.grpc_tls_addr` and forward the decrypted HTTP/2 stream to the plaintext
+/// gRPC port of `endpoints` in the background. Returns the path of the CA certificate clients should
+/// trust.
+pub async fn serve_grpc_tls(args: &GrpcTlsArgs, endpoints: &Endpoints) -> Result<PathBuf> {
+    let (cert_path, key_path) = match (&args.grpc_tls_cert, &args.grpc_tls_key) {
+        (Some(cert), Some(key)) => (cert.clone(), key.clone()),
+        _ => self_signed()?,
+    };
+
+    let certs = rustls_pemfile::certs(&mut BufReader::new(open(&cert_path)?))
+        .collect::<Result<Vec<CertificateDer>, _>>()
+        .wrap_err(format!(
+            "Failed to parse certificate: {}",
+            cert_path.display()
+        ))?;
+    let key: PrivateKeyDer = rustls_pemfile::private_key(&mut BufReader::new(open(&key_path)?))
+        .wrap_err(format!(
+            "Failed to parse private key: {}",
+            key_path.display()
+        ))?
+        .ok_or_else(|| eyre!("No private key found in {}", key_path.display()))?;
+
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .wrap_err("Invalid TLS certificate")?;
+    // gRPC runs over HTTP/2 only
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind(args.grpc_tls_addr)
+        .await
+        .wrap_err(format!(
+            "Failed to bind gRPC TLS endpoint to {}",
+            args.grpc_tls_addr
+        ))?;
+
+    let grpc_addr = endpoints.grpc_addr();
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let (acceptor, grpc_addr) = (acceptor.clone(), grpc_addr.clone());
+
+            tokio::spawn(async move {
+                let Ok(mut tls) = acceptor.accept(stream).await else {
+                    return;
+                };
+                let Ok(mut upstream) = TcpStream::connect(grpc_addr).await else {
+                    return;
+                };
+                let _ = tokio::io::copy_bidirectional(&mut tls, &mut upstream).await;
+            });
+        }
+    });
+
+    Ok(cert_path)
+}
+
+fn open(path: &Path) -> Result<std::fs::File> {
+    std::fs::File::open(path).wrap_err(format!("Failed to open {}", path.display()))
+}
+
+/// Generate a self-signed certificate for localhost outside of the osmosis home (which gets wiped
+/// on restore), reusing an existing one so clients only need to trust it once
+fn self_signed() -> Result<(PathBuf, PathBuf)> {
+    let dir = platform::home_dir().join(".cache/osmoinplace/grpc-tls");
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+
+    if cert_path.exists() && key_path.exists() {
+        return Ok((cert_path, key_path));
+    }
+
+    let certified =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string(), "127.0.0.1".to_string()])
+            .wrap_err("Failed to generate self-signed certificate")?;
+
+    std::fs::create_dir_all(&dir)
+        .wrap_err(format!("Failed to create directory: {}", dir.display()))?;
+    std::fs::write(&cert_path, certified.cert.pem()).wrap_err(format!(
+        "Failed to write certificate: {}",
+        cert_path.display()
+    ))?;
+    std::fs::write(&key_path, certified.key_pair.serialize_pem()).wrap_err(format!(
+        "Failed to write private key: {}",
+        key_path.display()
+    ))?;
+
+    Ok((cert_path, key_path))
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use regex::Regex;
+
+use crate::endpoints::Endpoints;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const DEFAULT_READY_PATTERN: &str = "indexed block events";
+const DEFAULT_FAILURE_PATTERN: &str = "CONSENSUS FAILURE!!!";
+
+/// Node log lines marking it ready or halted, for osmosisd versions logging them differently
+#[derive(Args, Debug, Clone)]
+pub struct LogPatterns {
+    /// Regex of the node log line that marks it ready, next to polling its RPC
+    #[arg(long, default_value = DEFAULT_READY_PATTERN)]
+    pub ready_pattern: Regex,
+
+    /// Regex of the node log line that marks it halted, e.g. for its upgrade
+    #[arg(long, default_value = DEFAULT_FAILURE_PATTERN)]
+    pub failure_pattern: Regex,
+}
+
+impl Default for LogPatterns {
+    fn default() -> Self {
+        LogPatterns {
+            ready_pattern: Regex::new(DEFAULT_READY_PATTERN).unwrap(),
+            failure_pattern: Regex::new(DEFAULT_FAILURE_PATTERN).unwrap(),
+        }
+    }
+}
+
+impl LogPatterns {
+    pub fn is_ready(&self, line: &str) -> bool {
+        self.ready_pattern.is_match(line)
+    }
+
+    pub fn is_failure(&self, line: &str) -> bool {
+        self.failure_pattern.is_match(line)
+    }
+}
+
+/// Sync state reported by the node's CometBFT RPC
+pub struct Status {
+    pub chain_id: String,
+    pub latest_block_height: u64,
+    pub catching_up: bool,
+}
+
+/// Query `/status` of the local node at `endpoints`
+pub async fn status(endpoints: &Endpoints) -> Result<Status> {
+    status_of(&reqwest::Client::new(), &endpoints.rpc_url())
+        .await
+        .wrap_err("Failed to query node status, is the node running?")
+}
+
+/// Query `/status` of the CometBFT RPC at `rpc`
+pub async fn status_of(client: &reqwest::Client, rpc: &str) -> Result<Status> {
+    let status: serde_json::Value = client
+        .get(format!("{}/status", rpc.trim_end_matches('/')))
+        .send()
+        .await
+        .wrap_err(format!("Failed to query status of {}", rpc))?
+        .error_for_status()?
+        .json()
+        .await
+        .wrap_err("Failed to parse node status")?;
+
+    let sync_info = &status["result"]["sync_info"];
+    Ok(Status {
+        chain_id: status["result"]["node_info"]["network"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        latest_block_height: sync_info["latest_block_height"]
+            .as_str()
+            .ok_or_else(|| eyre!("Missing latest block height in node status"))?
+            .parse::<u64>()
+            .wrap_err("Failed to parse latest block height")?,
+        catching_up: sync_info["catching_up"].as_bool().unwrap_or(false),
+    })
+}
+
+/// Version of the application the local node runs, from its `/abci_info`
+pub async fn app_version(endpoints: &Endpoints) -> Result<String> {
+    let info: serde_json::Value = reqwest::Client::new()
+        .get(format!("{}/abci_info", endpoints.rpc_url()))
+        .send()
+        .await
+        .wrap_err("Failed to query abci info")?
+        .error_for_status()?
+        .json()
+        .await
+        .wrap_err("Failed to parse abci info")?;
+
+    info["result"]["response"]["version"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| eyre!("Missing version in abci info"))
+}
+
+/// Resolves once the node committed a block after the probe started, polling its RPC instead of
+/// relying on the wording or format of its logs. With `caught_up` the node must also no longer be
+/// catching up with peers.
+pub async fn probe(endpoints: &Endpoints, caught_up: bool) {
+    let mut start_height = None;
+    loop {
+        if let Ok(status) = status(endpoints).await {
+            let start = *start_height.get_or_insert(status.latest_block_height);
+            if status.latest_block_height > start && !(caught_up && status.catching_up) {
+                return;
+            }
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Wait until the node is `ready` like `probe` with `caught_up` and at `height` or above, failing
+/// after `timeout`
+pub async fn wait(
+    endpoints: &Endpoints,
+    height: Option<u64>,
+    ready: bool,
+    timeout: Duration,
+) -> Result<()> {
+    let reached = async {
+        if ready {
+            probe(endpoints, true).await;
+        }
+        if let Some(height) = height {
+            while !status(endpoints)
+                .await
+                .is_ok_and(|status| status.latest_block_height >= height)
+            {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    };
+    let goal = match height {
+        Some(height) => format!("reach height {}", height),
+        None => "become ready".to_string(),
+    };
+    tokio::time::timeout(timeout, reached)
+        .await
+        .map_err(|_| eyre!("Node didn't {} within {:?}", goal, timeout))?;
+
+    let reached = match height {
+        Some(height) => format!("✓ Node reached height {}.", height),
+        None => "✓ Node is ready.".to_string(),
+    };
+    println!("{}", reached.green());
+    Ok(())
+}
@@ -0,0 +1,494 @@
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use toml::{Table, Value};
+
+use crate::{
+    endpoints::Endpoints,
+    platform::{Arch, Os, Platform},
+    policy::Policy,
+};
+
+/// Where the tool is copied to on the remote host, relative to the remote user's home
+const REMOTE_BIN: &str = ".cache/osmoinplace/bin/osmoinplace";
+
+/// Printed by the remote run's --on-ready hook so the local one can run at the same point
+const READY_MARKER: &str = "osmoinplace-remote-ready";
+
+/// Where the profile of the run is copied to on the remote host, relative to the remote user's
+/// home
+const REMOTE_CONFIG: &str = ".cache/osmoinplace/remote.toml";
+
+/// Options that only make sense locally, or are resolved locally and forwarded in their resolved
+/// form by `remote_args`
+const LOCAL_OPTIONS: &[&str] = &[
+    "--target",
+    "--on-ready",
+    "--profile",
+    "--config",
+    "--port-offset",
+];
+
+/// Host to run the heavy phases on, given as `ssh://[user@]host[:port]`
+#[derive(Clone, Debug)]
+pub struct Target {
+    destination: String,
+    port: Option<u16>,
+}
+
+impl FromStr for Target {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rest = s.strip_prefix("ssh://").ok_or_else(|| {
+            eyre!(
+                "Unsupported target {}, expected ssh://[user@]host[:port]",
+                s
+            )
+        })?;
+
+        let (destination, port) = match rest.rsplit_once(':') {
+            Some((destination, port)) => (
+                destination,
+                Some(
+                    port.parse()
+                        .wrap_err(format!("Invalid port in target: {}", s))?,
+                ),
+            ),
+            None => (rest, None),
+        };
+
+        if destination.is_empty() {
+            return Err(eyre!("Missing host in target: {}", s));
+        }
+
+        Ok(Target {
+            destination: destination.to_string(),
+            port,
+        })
+    }
+}
+
+impl Target {
+    fn ssh(&self) -> Command {
+        let mut cmd = Command::new("ssh");
+        if let Some(port) = self.port {
+            cmd.arg("-p").arg(port.to_string());
+        }
+        cmd
+    }
+
+    fn output(&self, remote_cmd: &str) -> Result<String> {
+        let output = self
+            .ssh()
+            .arg(&self.destination)
+            .arg(remote_cmd)
+            .output()
+            .wrap_err("Failed to run ssh")?;
+
+        if !output.status.success() {
+            return Err(eyre!(
+                "`{}` failed on {}: {}",
+                remote_cmd,
+                self.destination,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn platform(&self) -> Result<Platform> {
+        let uname = self.output("uname -sm")?;
+        let (os, arch) = uname
+            .split_once(' ')
+            .ok_or_else(|| eyre!("Unexpected uname output: {}", uname))?;
+
+        let os = match os {
+            "Linux" => Os::Linux,
+            "Darwin" => Os::Darwin,
+            os => return Err(eyre!("Unsupported remote operating system: {}", os)),
+        };
+        let arch = match arch {
+            "x86_64" => Arch::Amd64,
+            "aarch64" | "arm64" => Arch::Arm64,
+            arch => return Err(eyre!("Unsupported remote architecture: {}", arch)),
+        };
+
+        Ok(Platform { os, arch })
+    }
+
+    /// Copy this executable to the remote host so nothing has to be installed there
+    fn install(&self) -> Result<()> {
+        let remote = self.platform()?;
+        let host = Platform::host()?;
+        if remote != host {
+            return Err(eyre!(
+                "{} is {} but this osmoinplace is built for {}, install osmoinplace for {} there instead",
+                self.destination,
+                remote,
+                host,
+                remote
+            ));
+        }
+
+        self.output(&format!(
+            "mkdir -p {}",
+            Path::new(REMOTE_BIN).parent().unwrap().display()
+        ))?;
+
+        let exe = std::env::current_exe().wrap_err("Failed to locate osmoinplace executable")?;
+        let mut scp = Command::new("scp");
+        if let Some(port) = self.port {
+            scp.arg("-P").arg(port.to_string());
+        }
+        let status = scp
+            .arg("-q")
+            .arg(&exe)
+            .arg(format!("{}:{}", self.destination, REMOTE_BIN))
+            .status()
+            .wrap_err("Failed to run scp")?;
+
+        if !status.success() {
+            return Err(eyre!("Failed to copy osmoinplace to {}", self.destination));
+        }
+
+        Ok(())
+    }
+
+    /// Write `content` to `path` on the remote host, relative to the remote user's home
+    fn write(&self, path: &str, content: &str) -> Result<()> {
+        let mut child = self
+            .ssh()
+            .arg(&self.destination)
+            .arg(format!(
+                "mkdir -p {} && cat > {}",
+                quote(&Path::new(path).parent().unwrap().display().to_string()),
+                quote(path)
+            ))
+            .stdin(Stdio::piped())
+            .spawn()
+            .wrap_err("Failed to run ssh")?;
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| eyre!("Failed to open ssh stdin"))?
+            .write_all(content.as_bytes())?;
+
+        if !child.wait()?.success() {
+            return Err(eyre!("Failed to write {} on {}", path, self.destination));
+        }
+
+        Ok(())
+    }
+}
+
+/// Config file holding only the resolved `profile` named `name`, for the remote run to take the
+/// same defaults. Its on_ready is left out, it runs locally.
+fn remote_config(name: &str, mut profile: Table) -> Result<String> {
+    profile.remove("on_ready");
+    let mut profiles = Table::new();
+    profiles.insert(name.to_string(), Value::Table(profile));
+    let mut config = Table::new();
+    config.insert("profiles".to_string(), Value::Table(profiles));
+    toml::to_string(&config).wrap_err("Failed to serialize the profile for the remote run")
+}
+
+/// Arguments of the remote run: the `forwarded` ones of this invocation with the options
+/// resolved locally put back, so the remote fork listens on the ports forwarded to it
+fn remote_args(
+    forwarded: Vec<String>,
+    port_offset: Option<u16>,
+    profile: Option<&str>,
+    on_ready: bool,
+) -> Vec<String> {
+    let mut args = vec![];
+    if let Some(port_offset) = port_offset {
+        args.extend(["--port-offset".to_string(), port_offset.to_string()]);
+    }
+    if let Some(profile) = profile {
+        args.extend([
+            "--config".to_string(),
+            REMOTE_CONFIG.to_string(),
+            "--profile".to_string(),
+            profile.to_string(),
+        ]);
+    }
+    args.extend(forwarded);
+    if on_ready {
+        args.extend(["--on-ready".to_string(), format!("echo {}", READY_MARKER)]);
+    }
+    args
+}
+
+/// Quote `arg` for the remote shell
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Command line arguments of this invocation with the local-only options removed
+fn forwarded_args(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut forwarded = vec![];
+    let mut skip_value = false;
+    for arg in args {
+        if skip_value {
+            skip_value = false;
+            continue;
+        }
+
+        if LOCAL_OPTIONS.contains(&arg.as_str()) {
+            skip_value = true;
+            continue;
+        }
+        if LOCAL_OPTIONS
+            .iter()
+            .any(|option| arg.starts_with(&format!("{}=", option)))
+        {
+            continue;
+        }
+
+        forwarded.push(arg);
+    }
+    forwarded
+}
+
+/// Run this invocation on `target` over ssh, streaming its output, forwarding the fork's ports
+/// at `endpoints` to localhost and running `on_ready` locally when the remote fork is ready. The
+/// resolved `profile`, given as its name and fields, is copied over for the remote run to use.
+pub fn run(
+    target: &Target,
+    on_ready: Option<String>,
+    profile: Option<(String, Table)>,
+    policy: &Policy,
+    endpoints: &Endpoints,
+) -> Result<()> {
+    println!(
+        "{}",
+        format!("Installing osmoinplace on {}...", target.destination).cyan()
+    );
+    target.install()?;
+
+    if let Some((name, profile)) = &profile {
+        target.write(REMOTE_CONFIG, &remote_config(name, profile.clone())?)?;
+    }
+
+    let args = remote_args(
+        forwarded_args(std::env::args().skip(1)),
+        endpoints.port_offset(),
+        profile.as_ref().map(|(name, _)| name.as_str()),
+        on_ready.is_some(),
+    );
+    let remote_cmd = std::iter::once(format!("~/{}", REMOTE_BIN))
+        .chain(args.iter().map(|arg| quote(arg)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut ssh = target.ssh();
+    for addr in [
+        endpoints.rpc_addr().trim_start_matches("tcp://"),
+        &endpoints.grpc_addr(),
+        endpoints.api_addr().trim_start_matches("http://"),
+    ] {
+        let port = addr.rsplit(':').next().unwrap();
+        ssh.arg("-L").arg(format!("{}:{}", port, addr));
+    }
+
+    let mut child = ssh
+        // allocate a terminal so the remote run stops when this one is interrupted
+        .arg("-tt")
+        .arg(&target.destination)
+        .arg(remote_cmd)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .spawn()
+        .wrap_err("Failed to run ssh")?;
+
+    // on_ready runs on its own thread so the remote output keeps streaming meanwhile
+    let mut hook = None;
+    if let Some(stdout) = child.stdout.as_mut() {
+        for line in std::io::BufReader::new(stdout).lines() {
+            let line = line?;
+            // the terminal allocated remotely ends lines with \r\n
+            let line = line.trim_end_matches('\r');
+            if line == READY_MARKER {
+                if let Some(on_ready) = on_ready.clone() {
+                    let policy = policy.clone();
+                    let env = endpoints.hook_env(None, None);
+                    hook = Some(std::thread::spawn(move || {
+                        policy
+                            .run_hook(&on_ready, &env)
+                            .wrap_err("Failed to execute on_ready command")
+                    }));
+                }
+                continue;
+            }
+            println!("{}", line);
+        }
+    }
+
+    let status = child.wait()?;
+    if let Some(hook) = hook {
+        hook.join()
+            .map_err(|_| eyre!("on_ready command panicked"))??;
+    }
+    if !status.success() {
+        return Err(eyre!(
+            "Remote run on {} exited with {}",
+            target.destination,
+            status
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn drops_local_options_given_with_separate_values() {
+        let forwarded = forwarded_args(args(&[
+            "--target",
+            "ssh://validator@fork-box",
+            "--profile",
+            "upgrade",
+            "--config",
+            "osmoinplace.toml",
+            "--port-offset",
+            "10",
+            "--name",
+            "v26",
+            "magic-start",
+            "--on-ready",
+            "./run-tests.sh",
+            "--upgrade-handler",
+            "v26",
+        ]));
+        assert_eq!(
+            forwarded,
+            ["--name", "v26", "magic-start", "--upgrade-handler", "v26"]
+        );
+    }
+
+    #[test]
+    fn drops_local_options_given_with_equals() {
+        let forwarded = forwarded_args(args(&[
+            "--target=ssh://fork-box",
+            "--profile=upgrade",
+            "--config=osmoinplace.toml",
+            "--port-offset=10",
+            "start-in-place-testnet",
+            "--on-ready=./run-tests.sh",
+            "--fast-gov",
+        ]));
+        assert_eq!(forwarded, ["start-in-place-testnet", "--fast-gov"]);
+    }
+
+    #[test]
+    fn puts_back_the_locally_resolved_options() {
+        let forwarded = forwarded_args(args(&[
+            "--target",
+            "ssh://fork-box",
+            "--profile",
+            "upgrade",
+            "--name",
+            "v26",
+            "magic-start",
+            "--on-ready",
+            "./run-tests.sh",
+        ]));
+        let remote = remote_args(forwarded, Some(20), Some("upgrade"), true);
+        assert_eq!(
+            remote,
+            [
+                "--port-offset",
+                "20",
+                "--config",
+                REMOTE_CONFIG,
+                "--profile",
+                "upgrade",
+                "--name",
+                "v26",
+                "magic-start",
+                "--on-ready",
+                "echo osmoinplace-remote-ready",
+            ]
+        );
+
+        let remote = remote_args(args(&["resume"]).collect(), None, None, false);
+        assert_eq!(remote, ["resume"]);
+    }
+
+    #[test]
+    fn remote_config_keeps_the_profile_but_its_on_ready() {
+        let profile = r#"
+            upgrade_handler = "v26"
+            fast_gov = true
+            on_ready = "./run-tests.sh"
+            on_exit = "./collect-logs.sh"
+            "#
+        .parse::<Table>()
+        .unwrap();
+
+        let config = remote_config("upgrade", profile)
+            .unwrap()
+            .parse::<Table>()
+            .unwrap();
+
+        let profile = config["profiles"]["upgrade"].as_table().unwrap();
+        assert_eq!(profile["upgrade_handler"].as_str(), Some("v26"));
+        assert_eq!(profile["fast_gov"].as_bool(), Some(true));
+        assert_eq!(profile["on_exit"].as_str(), Some("./collect-logs.sh"));
+        assert!(!profile.contains_key("on_ready"));
+    }
+
+    #[test]
+    fn keeps_options_that_only_share_a_prefix_with_local_ones() {
+        let forwarded = forwarded_args(args(&["--targets", "a", "--config-dir=b"]));
+        assert_eq!(forwarded, ["--targets", "a", "--config-dir=b"]);
+    }
+
+    #[test]
+    fn parses_user_host_and_port_of_targets() {
+        let target = "ssh://validator@fork-box:2222".parse::<Target>().unwrap();
+        assert_eq!(target.destination, "validator@fork-box");
+        assert_eq!(target.port, Some(2222));
+
+        let target = "ssh://fork-box".parse::<Target>().unwrap();
+        assert_eq!(target.destination, "fork-box");
+        assert_eq!(target.port, None);
+    }
+
+    #[test]
+    fn rejects_invalid_targets() {
+        for invalid in [
+            "validator@fork-box",
+            "https://fork-box",
+            "ssh://",
+            "ssh://:22",
+            "ssh://fork-box:ssh",
+            "ssh://fork-box:70000",
+        ] {
+            assert!(invalid.parse::<Target>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn quotes_single_quotes_for_the_remote_shell() {
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+}
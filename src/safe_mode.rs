@@ -0,0 +1,165 @@
+use std::{
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{ArgAction, Args};
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+
+use crate::disk;
+
+/// Guards destructive steps, when `safe` is set they fail unless explicitly forced
+#[derive(Args, Debug, Clone, Copy, Default)]
+pub struct SafeMode {
+    /// Refuse to delete anything unless the matching --force-* flag is provided
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true"
+    )]
+    pub safe: bool,
+
+    /// Allow wiping the osmosis home directory in safe mode
+    #[arg(long)]
+    pub force_home_wipe: bool,
+
+    /// Allow overwriting or deleting an existing backup in safe mode
+    #[arg(long)]
+    pub force_backup_overwrite: bool,
+
+    /// Allow removing cached snapshots in safe mode
+    #[arg(long)]
+    pub force_cache_clean: bool,
+
+    /// Don't ask for confirmation before wiping the osmosis home directory
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+impl SafeMode {
+    pub fn check_home_wipe(&self, osmosis_home: &Path) -> Result<()> {
+        self.check_home_wipe_all(&[osmosis_home.to_path_buf()])
+    }
+
+    /// Like `check_home_wipe` for several directories of the home, confirmed together
+    pub fn check_home_wipe_all(&self, paths: &[PathBuf]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        if self.safe && !self.force_home_wipe {
+            return Err(eyre!(
+                "Refusing to remove {} in safe mode, pass --force-home-wipe to allow it",
+                display(paths)
+            ));
+        }
+
+        if !self.yes {
+            confirm_delete(paths)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn check_backup_delete(&self, backup_path: &Path) -> Result<()> {
+        if self.safe && !self.force_backup_overwrite {
+            return Err(eyre!(
+                "Refusing to delete backup {} in safe mode, pass --force-backup-overwrite to allow it",
+                backup_path.display()
+            ));
+        }
+
+        if !self.yes {
+            confirm_delete(&[backup_path.to_path_buf()])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn check_cache_delete(&self, paths: &[PathBuf]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        if self.safe && !self.force_cache_clean {
+            return Err(eyre!(
+                "Refusing to remove cached snapshots {} in safe mode, pass --force-cache-clean to allow it",
+                display(paths)
+            ));
+        }
+
+        if !self.yes {
+            confirm_delete(paths)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn check_backup_overwrite(&self, backup_path: &Path) -> Result<()> {
+        if self.safe && !self.force_backup_overwrite {
+            return Err(eyre!(
+                "Refusing to overwrite backup {} in safe mode, pass --force-backup-overwrite to allow it",
+                backup_path.display()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Show what is about to be deleted and ask to go on, refusing on anything but yes. The answer
+/// can be piped in (`yes | osmoinplace ...`), a closed stdin refuses. The size is left out when
+/// it can't be measured.
+fn confirm_delete(paths: &[PathBuf]) -> Result<()> {
+    let size = paths
+        .iter()
+        .map(|path| disk::usage(path).ok())
+        .sum::<Option<u64>>();
+    match size {
+        Some(size) => print!(
+            "{} {} ({:.1} GB). Continue? [y/N] ",
+            "About to delete".yellow(),
+            display(paths),
+            size as f64 / 1e9
+        ),
+        None => print!(
+            "{} {}. Continue? [y/N] ",
+            "About to delete".yellow(),
+            display(paths)
+        ),
+    }
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    let read = std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .wrap_err("Failed to read confirmation")?;
+    if read == 0 {
+        println!();
+        return Err(eyre!(
+            "Not deleting {} without confirmation, stdin is closed, pass --yes to skip it",
+            display(paths)
+        ));
+    }
+
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(eyre!(
+            "Not deleting {}, pass --yes to skip the confirmation",
+            display(paths)
+        )),
+    }
+}
+
+fn display(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,38 @@
+use crate::{
+    endpoints::Endpoints, health, node_config::Override, output::Events, shutdown::Stop,
+    webhook::Webhook,
+};
+
+/// What a run of osmoinplace reports to and configures its fork with: the fork's endpoints, the
+/// --set overrides, the --notify-url webhook, where --output json events go, how to stop its node
+/// and whether the node runs through cosmovisor. The health endpoint's state, the node log
+/// settings and the signal handlers are still process wide, see `health`, `node_log` and
+/// `shutdown`, so only one session runs a node per process.
+#[derive(Clone, Default)]
+pub struct Session {
+    pub endpoints: Endpoints,
+    pub overrides: Vec<Override>,
+    pub webhook: Option<Webhook>,
+    pub events: Events,
+    pub stop: Stop,
+    /// Run the node through cosmovisor, the home is laid out for it by --cosmovisor before the
+    /// node first starts
+    pub cosmovisor: bool,
+}
+
+impl Session {
+    /// Session of a library run against the fork at `endpoints`, without overrides, webhook or
+    /// events
+    pub fn new(endpoints: Endpoints) -> Session {
+        Session {
+            endpoints,
+            ..Session::default()
+        }
+    }
+
+    /// Record that the run entered `phase`, for the health endpoint and as an event
+    pub fn phase(&self, phase: &'static str) {
+        health::set_phase(phase);
+        self.events.phase(phase);
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+};
+
+use colored::Colorize;
+use tokio::sync::watch;
+
+static SUPERVISED_NODES: AtomicUsize = AtomicUsize::new(0);
+static TEMP_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+static REQUESTED: OnceLock<watch::Sender<Option<Signal>>> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    Interrupt,
+    Terminate,
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signal::Interrupt => write!(f, "SIGINT"),
+            Signal::Terminate => write!(f, "SIGTERM"),
+        }
+    }
+}
+
+impl Signal {
+    #[cfg(unix)]
+    pub fn raw(self) -> libc::c_int {
+        match self {
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+        }
+    }
+
+    /// Exit code of a run stopped by the signal, 128 + its number like shells report it
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Signal::Interrupt => 130,
+            Signal::Terminate => 143,
+        }
+    }
+}
+
+fn requested() -> &'static watch::Sender<Option<Signal>> {
+    REQUESTED.get_or_init(|| watch::channel(None).0)
+}
+
+/// Handle SIGINT and SIGTERM (Ctrl-C, and Ctrl-Break or closing the console on Windows) for the
+/// rest of the run. While a node is supervised the first signal is handed to its supervisor to
+/// stop it cleanly, otherwise (or on a second signal) temp files are removed and the run exits
+/// right away.
+pub fn listen() -> std::io::Result<()> {
+    #[cfg(unix)]
+    let (mut interrupt, mut terminate) = {
+        use tokio::signal::unix::{signal, SignalKind};
+        (
+            signal(SignalKind::interrupt())?,
+            signal(SignalKind::terminate())?,
+        )
+    };
+    #[cfg(windows)]
+    let (mut interrupt, mut terminate, mut close) = {
+        use tokio::signal::windows::{ctrl_break, ctrl_c, ctrl_close};
+        (ctrl_c()?, ctrl_break()?, ctrl_close()?)
+    };
+
+    tokio::spawn(async move {
+        loop {
+            #[cfg(unix)]
+            let signal = tokio::select! {
+                _ = interrupt.recv() => Signal::Interrupt,
+                _ = terminate.recv() => Signal::Terminate,
+            };
+            #[cfg(windows)]
+            let signal = tokio::select! {
+                _ = interrupt.recv() => Signal::Interrupt,
+                _ = terminate.recv() => Signal::Terminate,
+                _ = close.recv() => Signal::Terminate,
+            };
+
+            let first = requested().borrow().is_none();
+            if first && SUPERVISED_NODES.load(Ordering::SeqCst) > 0 {
+                println!(
+                    "{}",
+                    format!("Received {}, stopping osmosisd...", signal).yellow()
+                );
+                requested().send_replace(Some(signal));
+                continue;
+            }
+
+            remove_temp_files();
+            std::process::exit(signal.exit_code());
+        }
+    });
+
+    Ok(())
+}
+
+/// Wait for a signal asking to stop the supervised node
+pub async fn signaled() -> Signal {
+    wait(requested()).await
+}
+
+async fn wait(sender: &watch::Sender<Option<Signal>>) -> Signal {
+    let mut receiver = sender.subscribe();
+    loop {
+        if let Some(signal) = *receiver.borrow_and_update() {
+            return signal;
+        }
+        if receiver.changed().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Asks the nodes of one run to stop like a signal does for the whole process, e.g. for a
+/// `Testnet` killed by its test. Clones ask the same nodes.
+#[derive(Clone)]
+pub struct Stop(Arc<watch::Sender<Option<Signal>>>);
+
+impl Default for Stop {
+    fn default() -> Stop {
+        Stop(Arc::new(watch::channel(None).0))
+    }
+}
+
+impl Stop {
+    /// Stop the supervised node with `signal`, and any node supervised later on
+    pub fn request(&self, signal: Signal) {
+        self.0.send_replace(Some(signal));
+    }
+
+    /// Wait for `request`
+    pub async fn requested(&self) -> Signal {
+        wait(&self.0).await
+    }
+}
+
+/// Marks a node as supervised until dropped, so signals stop it instead of exiting right away
+pub struct Supervised(());
+
+pub fn supervise() -> Supervised {
+    SUPERVISED_NODES.fetch_add(1, Ordering::SeqCst);
+    Supervised(())
+}
+
+impl Drop for Supervised {
+    fn drop(&mut self) {
+        SUPERVISED_NODES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Remove `path` if the run is interrupted or fails before it is `untrack`ed
+pub fn track(path: &Path) {
+    TEMP_FILES.lock().unwrap().push(path.to_path_buf());
+}
+
+pub fn untrack(path: &Path) {
+    TEMP_FILES.lock().unwrap().retain(|file| file != path);
+}
+
+pub fn remove_temp_files() {
+    for file in TEMP_FILES.lock().unwrap().drain(..) {
+        std::fs::remove_file(file).ok();
+    }
+}
@@ -0,0 +1,1405 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use clap::{Args, ValueEnum};
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+use futures::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::{
+    header::{ACCEPT_RANGES, CONTENT_LENGTH, RANGE},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use crate::{
+    backup, disk, health, output::Events, platform, policy::Policy, readiness, safe_mode::SafeMode,
+    session::Session, spinner,
+};
+
+/// Where a snapshot is downloaded to, kept across runs so interrupted downloads can be resumed
+pub fn partial_path(url: &str) -> Result<PathBuf> {
+    let file_name = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| eyre!("Failed to get snapshot file name from url: {}", url))?;
+
+    Ok(platform::home_dir()
+        .join(".cache/osmoinplace/downloads")
+        .join(format!("{}.part", file_name)))
+}
+
+/// Flavor of snapshot published by osmosis
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SnapshotKind {
+    /// Only recent heights, smallest download
+    Pruned,
+    /// Default pruning settings
+    #[default]
+    Default,
+    /// Full history, for historical queries
+    Archive,
+}
+
+impl SnapshotKind {
+    /// Url that responds with the url of the latest snapshot of this kind
+    pub fn latest_url(self) -> &'static str {
+        match self {
+            SnapshotKind::Pruned => "https://snapshots.osmosis.zone/latest-pruned",
+            SnapshotKind::Default => "https://snapshots.osmosis.zone/latest",
+            SnapshotKind::Archive => "https://snapshots.osmosis.zone/latest-archive",
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct DownloadArgs {
+    /// Kind of snapshot to download
+    #[arg(long, value_enum, default_value_t = SnapshotKind::Default)]
+    pub snapshot_kind: SnapshotKind,
+
+    /// JSON file to deep merge on top of the downloaded genesis
+    #[arg(long)]
+    pub genesis_overrides: Option<PathBuf>,
+
+    /// Resume a previously interrupted snapshot download instead of starting over, implies --keep-archive
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Download the whole archive to disk before extracting instead of extracting while
+    /// downloading, so the download can be continued with --resume if it fails for good. Needs
+    /// twice the disk space.
+    #[arg(long)]
+    pub keep_archive: bool,
+
+    /// Number of times to resume the snapshot download when the connection drops
+    #[arg(long, default_value_t = 5)]
+    pub download_retries: u32,
+
+    /// Number of concurrent connections to download the snapshot with, each fetching its own
+    /// segment. More than one implies --keep-archive.
+    #[arg(long, default_value_t = 1, conflicts_with = "resume")]
+    pub connections: u64,
+
+    /// Maximum download speed across all connections, e.g. 50MB or 500K (1024 based)
+    #[arg(long, value_parser = parse_rate)]
+    pub limit_rate: Option<u64>,
+
+    /// Snapshot archive url to download instead of looking up the latest one, repeat to add
+    /// mirrors that are tried in order. `file://` urls point to local archives.
+    #[arg(long, conflicts_with_all = ["snapshot_height", "snapshot_date"])]
+    pub snapshot_url: Vec<String>,
+
+    /// Local snapshot archive to extract instead of downloading one
+    #[arg(long, conflicts_with_all = ["snapshot_url", "snapshot_height", "snapshot_date"])]
+    pub snapshot_file: Option<PathBuf>,
+
+    /// Genesis url to download instead of the one in the osmosis repo, repeat to add mirrors that
+    /// are tried in order. A `file://` url points to a local genesis file.
+    #[arg(long)]
+    pub genesis_url: Vec<String>,
+
+    /// Download the most recent snapshot at or below this height instead of the latest
+    #[arg(long, conflicts_with = "snapshot_date")]
+    pub snapshot_height: Option<u64>,
+
+    /// Download the most recent snapshot taken on this date (YYYY-MM-DD) instead of the latest
+    #[arg(long)]
+    pub snapshot_date: Option<String>,
+
+    /// Don't verify the snapshot against its published sha256 checksum
+    #[arg(long)]
+    pub skip_checksum: bool,
+
+    /// Don't check that osmosisd is the version mainnet ran at the snapshot's height
+    #[arg(long)]
+    pub skip_version_check: bool,
+
+    /// Download and extract even if the disk space preflight check estimates there isn't enough
+    /// free space
+    #[arg(long)]
+    pub force: bool,
+
+    /// Keep the downloaded archive in the local snapshot cache so later runs at the same height
+    /// reuse it instead of downloading again, implies --keep-archive
+    #[arg(long)]
+    pub cache: bool,
+
+    /// Keep the existing app.toml, config.toml and client.toml instead of the freshly
+    /// initialized ones
+    #[arg(long)]
+    pub preserve_config: bool,
+
+    /// Keep the existing node key and validator key instead of generating new ones
+    #[arg(long)]
+    pub preserve_keys: bool,
+}
+
+impl Default for DownloadArgs {
+    fn default() -> Self {
+        DownloadArgs {
+            snapshot_kind: SnapshotKind::Default,
+            genesis_overrides: None,
+            resume: false,
+            keep_archive: false,
+            download_retries: 5,
+            connections: 1,
+            limit_rate: None,
+            snapshot_url: vec![],
+            snapshot_file: None,
+            genesis_url: vec![],
+            snapshot_height: None,
+            snapshot_date: None,
+            skip_checksum: false,
+            skip_version_check: false,
+            force: false,
+            cache: false,
+            preserve_config: false,
+            preserve_keys: false,
+        }
+    }
+}
+
+/// Parse a rate like `50MB`, `500K` or `1048576` into bytes per second
+fn parse_rate(rate: &str) -> Result<u64> {
+    let rate = rate.trim().to_uppercase();
+    let rate = rate.strip_suffix('B').unwrap_or(&rate);
+    let (number, multiplier) = match rate.chars().last() {
+        Some('K') => (&rate[..rate.len() - 1], 1 << 10),
+        Some('M') => (&rate[..rate.len() - 1], 1 << 20),
+        Some('G') => (&rate[..rate.len() - 1], 1 << 30),
+        _ => (rate, 1),
+    };
+
+    let number = number
+        .parse::<u64>()
+        .wrap_err(format!("Invalid rate: {}", rate))?;
+    if number == 0 {
+        return Err(eyre!("Rate must be greater than 0"));
+    }
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| eyre!("Rate is too large: {}", rate))
+}
+
+/// Throttles downloads to a number of bytes per second, shared across connections
+struct RateLimit {
+    bytes_per_sec: Option<u64>,
+    started: Instant,
+    consumed: AtomicU64,
+}
+
+impl RateLimit {
+    fn new(bytes_per_sec: Option<u64>) -> Self {
+        RateLimit {
+            bytes_per_sec,
+            started: Instant::now(),
+            consumed: AtomicU64::new(0),
+        }
+    }
+
+    /// Wait until `len` more bytes fit in the rate
+    async fn throttle(&self, len: u64) {
+        let Some(bytes_per_sec) = self.bytes_per_sec else {
+            return;
+        };
+
+        let consumed = self.consumed.fetch_add(len, Ordering::SeqCst) + len;
+        let due = Duration::from_secs_f64(consumed as f64 / bytes_per_sec as f64);
+        let elapsed = self.started.elapsed();
+        if due > elapsed {
+            tokio::time::sleep(due - elapsed).await;
+        }
+    }
+}
+
+/// How a snapshot is transferred: retries of interrupted transfers, their backoff, the rate
+/// limit and where its progress is reported
+struct Transfer<'a> {
+    retries: u32,
+    policy: &'a Policy,
+    limit: RateLimit,
+    events: &'a Events,
+}
+
+impl DownloadArgs {
+    /// Local snapshot archive given through --snapshot-file or a `file://` --snapshot-url
+    pub fn local_snapshot(&self) -> Option<PathBuf> {
+        self.snapshot_file.clone().or_else(|| {
+            self.snapshot_url
+                .iter()
+                .find_map(|url| url.strip_prefix("file://"))
+                .map(PathBuf::from)
+        })
+    }
+}
+
+/// Where downloaded bytes go
+enum Target {
+    /// Archive kept on disk, can be resumed in a later run
+    File(File),
+    /// Extracted on the fly by a blocking task
+    Stream(mpsc::Sender<Bytes>),
+}
+
+/// Download target, hashing everything written to it when a checksum is to be verified
+struct Sink {
+    target: Target,
+    hasher: Option<Sha256>,
+}
+
+impl Sink {
+    async fn write(&mut self, chunk: Bytes) -> Result<()> {
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&chunk);
+        }
+
+        match &mut self.target {
+            Target::File(file) => file
+                .write_all(&chunk)
+                .wrap_err("Failed to write chunk to download file"),
+            Target::Stream(tx) => tx
+                .send(chunk)
+                .await
+                .map_err(|_| eyre!("Snapshot extraction stopped early")),
+        }
+    }
+
+    /// Whether the extraction stopped reading, so retrying the download is pointless
+    fn is_closed(&self) -> bool {
+        matches!(&self.target, Target::Stream(tx) if tx.is_closed())
+    }
+
+    /// Discard what was written so far, only possible when the archive is on disk
+    fn reset(&mut self) -> Result<()> {
+        if let Some(hasher) = &mut self.hasher {
+            hasher.reset();
+        }
+
+        match &mut self.target {
+            Target::File(file) => Ok(file.set_len(0)?),
+            Target::Stream(_) => Err(eyre!(
+                "Server doesn't support resuming the snapshot download mid-stream"
+            )),
+        }
+    }
+}
+
+/// Compare the hash of everything written to a sink against `expected`
+fn verify_checksum(hasher: Option<Sha256>, expected: &Option<String>) -> Result<()> {
+    let (Some(hasher), Some(expected)) = (hasher, expected) else {
+        return Ok(());
+    };
+
+    let actual = format!("{:x}", hasher.finalize());
+    if &actual != expected {
+        return Err(eyre!(
+            "Snapshot checksum mismatch, expected sha256 {} but got {}, the download is corrupted",
+            expected,
+            actual
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fetch the sha256 digest published next to the snapshot at `<url>.sha256`
+async fn fetch_checksum(url: &str, policy: &Policy) -> Result<String> {
+    let checksum_url = &format!("{}.sha256", url);
+    let client = &policy.client()?;
+    let content = policy
+        .network("Fetching snapshot checksum", || async move {
+            Ok(client
+                .get(checksum_url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?)
+        })
+        .await
+        .wrap_err("Pass --skip-checksum to download without verification")?;
+
+    // `sha256sum` format: `<digest>  <file name>`
+    content
+        .split_whitespace()
+        .next()
+        .map(str::to_lowercase)
+        .ok_or_else(|| eyre!("Empty snapshot checksum at {}", checksum_url))
+}
+
+/// Download the snapshot at `url` and extract it into `dest`, retrying interrupted transfers from
+/// where they stopped up to `retries` times.
+///
+/// By default the archive is decompressed and extracted while it downloads. With `keep_archive`
+/// (implied by `resume`) it is written to its partial path first instead, so a download
+/// interrupted for good can be continued by a later run with `resume`, at the cost of twice the
+/// disk space.
+pub async fn download_and_extract(
+    url: &str,
+    dest: &Path,
+    args: &DownloadArgs,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    let keeps_archive = args.resume || args.keep_archive || args.cache || args.connections > 1;
+    let remote = remote_info(url, policy).await;
+    if let Some(size) = remote.as_ref().and_then(|remote| remote.size) {
+        let partial = partial_path(url)?;
+        let already_downloaded = match args.resume && partial.exists() {
+            true => partial.metadata()?.len(),
+            false => 0,
+        };
+        let archive =
+            keeps_archive.then(|| (partial.as_path(), size.saturating_sub(already_downloaded)));
+        check_disk_space(size, dest, archive, args.force)?;
+    }
+
+    let expected_checksum = match args.skip_checksum {
+        true => None,
+        false => Some(fetch_checksum(url, policy).await?),
+    };
+    let hasher = expected_checksum.as_ref().map(|_| Sha256::new());
+
+    let transfer = Transfer {
+        retries: args.download_retries,
+        policy,
+        limit: RateLimit::new(args.limit_rate),
+        events: &session.events,
+    };
+
+    let segmented_size = match args.connections > 1 {
+        true => ranged_size(remote.as_ref()),
+        false => None,
+    };
+
+    if let Some(size) = segmented_size {
+        let path = partial_path(url)?;
+
+        download_segmented(url, &path, size, args.connections, &transfer)
+            .await
+            .wrap_err("Failed to download snapshot")?;
+
+        let hasher = match hasher {
+            Some(mut hasher) => {
+                let file = File::open(&path)?;
+                let hasher = spinner! {
+                    session.events,
+                    "Hashing snapshot...",
+                    "✓ Hashed snapshot.",
+                    tokio::task::spawn_blocking(move || -> Result<Sha256> {
+                        std::io::copy(&mut &file, &mut hasher)?;
+                        Ok(hasher)
+                    })
+                    .await??
+                };
+                Some(hasher)
+            }
+            None => None,
+        };
+
+        if let Err(e) = verify_checksum(hasher, &expected_checksum) {
+            std::fs::remove_file(&path)?;
+            return Err(e);
+        }
+
+        let file = File::open(&path).wrap_err(format!(
+            "Failed to open downloaded snapshot: {}",
+            path.display()
+        ))?;
+        progress_extract(file, dest.to_path_buf()).await?;
+
+        match SnapshotInfo::from_url(url).height.filter(|_| args.cache) {
+            Some(height) => add_to_cache(&path, height, &expected_checksum)?,
+            None => std::fs::remove_file(&path).wrap_err(format!(
+                "Failed to remove downloaded snapshot: {}",
+                path.display()
+            ))?,
+        }
+    } else if keeps_archive {
+        let path = partial_path(url)?;
+        let file = open_partial(&path, args.resume)?;
+        let mut downloaded = file.metadata()?.len();
+
+        // what was downloaded in a previous run needs to be part of the hash too
+        let hasher = match hasher {
+            Some(mut hasher) if downloaded > 0 => {
+                let file = File::open(&path)?;
+                let hasher = spinner! {
+                    session.events,
+                    "Hashing previously downloaded part of snapshot...",
+                    "✓ Hashed previously downloaded part of snapshot.",
+                    tokio::task::spawn_blocking(move || -> Result<Sha256> {
+                        std::io::copy(&mut &file, &mut hasher)
+                            .wrap_err("Failed to hash previously downloaded part of snapshot")?;
+                        Ok(hasher)
+                    })
+                    .await??
+                };
+                Some(hasher)
+            }
+            hasher => hasher,
+        };
+
+        let mut sink = Sink {
+            target: Target::File(file),
+            hasher,
+        };
+
+        download(url, &mut sink, &mut downloaded, &transfer)
+            .await
+            .wrap_err(format!(
+                "Partial download kept at {}, rerun with --resume to continue",
+                path.display()
+            ))?;
+
+        if let Err(e) = verify_checksum(sink.hasher, &expected_checksum) {
+            std::fs::remove_file(&path)?;
+            return Err(e);
+        }
+
+        let file = File::open(&path).wrap_err(format!(
+            "Failed to open downloaded snapshot: {}",
+            path.display()
+        ))?;
+        progress_extract(file, dest.to_path_buf()).await?;
+
+        match SnapshotInfo::from_url(url).height.filter(|_| args.cache) {
+            Some(height) => add_to_cache(&path, height, &expected_checksum)?,
+            None => std::fs::remove_file(&path).wrap_err(format!(
+                "Failed to remove downloaded snapshot: {}",
+                path.display()
+            ))?,
+        }
+    } else {
+        let (tx, rx) = mpsc::channel(64);
+        let extraction = tokio::task::spawn_blocking({
+            let dest = dest.to_path_buf();
+            move || extract(ChannelReader::new(rx), &dest)
+        });
+
+        let mut downloaded = 0;
+        let mut sink = Sink {
+            target: Target::Stream(tx),
+            hasher,
+        };
+        let download_result = download(url, &mut sink, &mut downloaded, &transfer).await;
+        let extraction_stopped = sink.is_closed();
+        // closing the channel lets the extraction finish
+        let Sink { target, hasher } = sink;
+        drop(target);
+
+        // report whichever side failed first, the other one only fails as a consequence
+        let extraction_result = extraction.await?;
+        if extraction_stopped {
+            extraction_result?;
+            download_result?;
+        } else {
+            download_result?;
+            extraction_result?;
+        }
+
+        verify_checksum(hasher, &expected_checksum).wrap_err(format!(
+            "{} contains a corrupted snapshot and needs to be downloaded again",
+            dest.display()
+        ))?;
+
+        println!("{}", "✓ Decompressed and extracted snapshot.".green());
+    }
+
+    Ok(())
+}
+
+fn open_partial(path: &Path, resume: bool) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).wrap_err(format!(
+            "Failed to create download directory: {}",
+            parent.display()
+        ))?;
+    }
+
+    if !resume && path.exists() {
+        std::fs::remove_file(path).wrap_err(format!(
+            "Failed to remove previous partial download: {}",
+            path.display()
+        ))?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .wrap_err(format!("Failed to open download file: {}", path.display()))
+}
+
+fn download_progress_bar(events: &Events) -> Result<ProgressBar> {
+    // Indicatif setup
+    let pb = ProgressBar::new(0);
+    pb.set_style(ProgressStyle::default_bar()
+                .template("{msg}\n{spinner:.cyan} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?
+                .progress_chars("#>-"));
+    pb.set_message("Downloading latest snapshot...".cyan().to_string());
+    events.progress(&pb);
+    Ok(pb)
+}
+
+async fn download(
+    url: &str,
+    sink: &mut Sink,
+    downloaded: &mut u64,
+    transfer: &Transfer<'_>,
+) -> Result<()> {
+    let pb = download_progress_bar(transfer.events)?;
+    let retries = transfer.retries;
+
+    let mut attempt = 0;
+    loop {
+        match download_range(url, sink, downloaded, &pb, transfer).await {
+            Ok(()) => break,
+            Err(e) if attempt < retries && !sink.is_closed() => {
+                attempt += 1;
+                pb.println(
+                    format!(
+                        "Download interrupted ({}), resuming from byte {} [{}/{}]",
+                        e, downloaded, attempt, retries
+                    )
+                    .yellow()
+                    .to_string(),
+                );
+                tokio::time::sleep(transfer.policy.backoff(attempt)).await;
+            }
+            Err(e) => return Err(e.wrap_err("Failed to download snapshot")),
+        }
+    }
+
+    pb.finish_with_message("✓ Downloaded latest snapshot.".green().to_string());
+
+    Ok(())
+}
+
+async fn download_range(
+    url: &str,
+    sink: &mut Sink,
+    downloaded: &mut u64,
+    pb: &ProgressBar,
+    transfer: &Transfer<'_>,
+) -> Result<()> {
+    let mut request = transfer.policy.client()?.get(url);
+    if *downloaded > 0 {
+        request = request.header(RANGE, format!("bytes={}-", downloaded));
+    }
+    let response = request.send().await.wrap_err("Failed to fetch snapshot")?;
+
+    // everything has already been downloaded
+    if *downloaded > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(());
+    }
+
+    let response = response
+        .error_for_status()
+        .wrap_err("Failed to fetch snapshot")?;
+
+    // server doesn't support ranges and sent the whole snapshot, start over
+    if response.status() != StatusCode::PARTIAL_CONTENT && *downloaded > 0 {
+        sink.reset()?;
+        *downloaded = 0;
+    }
+
+    let remaining = response
+        .content_length()
+        .ok_or_else(|| eyre!("Failed to get snapshot size from response"))?;
+    pb.set_length(*downloaded + remaining);
+    pb.set_position(*downloaded);
+
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.wrap_err("Failed to download chunk")?;
+        let len = chunk.len() as u64;
+        transfer.limit.throttle(len).await;
+        sink.write(chunk).await?;
+        health::add_downloaded_bytes(len);
+        *downloaded += len;
+        pb.set_position(*downloaded);
+    }
+
+    Ok(())
+}
+
+/// Extract the local snapshot archive at `path` into `dest`
+pub async fn extract_file(path: &Path, dest: &Path) -> Result<()> {
+    let file =
+        File::open(path).wrap_err(format!("Failed to open snapshot file: {}", path.display()))?;
+    progress_extract(file, dest.to_path_buf()).await
+}
+
+/// Rough ratio of extracted to compressed snapshot size
+const EXTRACTED_SIZE_RATIO: u64 = 2;
+
+/// Fail early unless there is room to extract an archive of `archive_size` bytes into `dest` and,
+/// if it is kept on disk, to write the given number of bytes of it to the given path first
+pub fn check_disk_space(
+    archive_size: u64,
+    dest: &Path,
+    archive: Option<(&Path, u64)>,
+    force: bool,
+) -> Result<()> {
+    let existing = |path: &Path| -> Result<PathBuf> {
+        path.ancestors()
+            .find(|p| p.exists())
+            .map(Path::to_path_buf)
+            .ok_or_else(|| eyre!("No existing ancestor of {}", path.display()))
+    };
+
+    let mut required = vec![(dest, archive_size * EXTRACTED_SIZE_RATIO)];
+    if let Some((archive_path, archive_bytes)) = archive {
+        match platform::same_filesystem(&existing(archive_path)?, &existing(dest)?)? {
+            true => required[0].1 += archive_bytes,
+            false => required.push((archive_path, archive_bytes)),
+        }
+    }
+
+    for (path, bytes) in required {
+        let available = disk::available_space(path)?;
+        if available < bytes && !force {
+            return Err(eyre!(
+                "Not enough disk space for the snapshot at {}: {:.1} GB free but about {:.1} GB needed, free up space or pass --force to continue anyway",
+                path.display(),
+                available as f64 / 1e9,
+                bytes as f64 / 1e9
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// What a HEAD request tells about the snapshot
+struct RemoteInfo {
+    size: Option<u64>,
+    accepts_ranges: bool,
+}
+
+/// Size and range support of the snapshot at `url` as reported by the server, `None` with a
+/// warning when the server doesn't answer a HEAD request
+async fn remote_info(url: &str, policy: &Policy) -> Option<RemoteInfo> {
+    let client = &policy.client().ok()?;
+    let response = policy
+        .network("Fetching snapshot headers", || async move {
+            Ok(client.head(url).send().await?.error_for_status()?)
+        })
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            println!(
+                "{}",
+                format!(
+                    "Failed to fetch snapshot headers, skipping the disk space check: {:#}",
+                    e
+                )
+                .yellow()
+            );
+            return None;
+        }
+    };
+
+    Some(RemoteInfo {
+        size: response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok()),
+        accepts_ranges: response
+            .headers()
+            .get(ACCEPT_RANGES)
+            .is_some_and(|value| value == "bytes"),
+    })
+}
+
+/// Size of the snapshot if the server supports range requests, which segmented downloads need
+fn ranged_size(remote: Option<&RemoteInfo>) -> Option<u64> {
+    match remote {
+        Some(RemoteInfo {
+            size: Some(size),
+            accepts_ranges: true,
+        }) => Some(*size),
+        _ => {
+            println!(
+                "{}",
+                "Server doesn't support range requests, downloading over a single connection"
+                    .yellow()
+            );
+            None
+        }
+    }
+}
+
+/// Download the `size` bytes of the snapshot at `url` into `path` over `connections` concurrent
+/// range requests, each writing its own segment of the file
+async fn download_segmented(
+    url: &str,
+    path: &Path,
+    size: u64,
+    connections: u64,
+    transfer: &Transfer<'_>,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).wrap_err(format!(
+            "Failed to create download directory: {}",
+            parent.display()
+        ))?;
+    }
+    File::create(path)
+        .and_then(|file| file.set_len(size))
+        .wrap_err(format!(
+            "Failed to create download file: {}",
+            path.display()
+        ))?;
+
+    let pb = download_progress_bar(transfer.events)?;
+    pb.set_length(size);
+
+    let client = transfer.policy.client()?;
+    let segments = (0..connections).map(|i| {
+        let start = size * i / connections;
+        let end = size * (i + 1) / connections;
+        download_segment(&client, url, path, start..end, &pb, transfer)
+    });
+    futures::future::try_join_all(segments).await?;
+
+    pb.finish_with_message("✓ Downloaded latest snapshot.".green().to_string());
+
+    Ok(())
+}
+
+/// Download `segment` of the snapshot at `url` into the same range of `path`, resuming from where
+/// it stopped as often as `transfer` allows
+async fn download_segment(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    segment: Range<u64>,
+    pb: &ProgressBar,
+    transfer: &Transfer<'_>,
+) -> Result<()> {
+    let retries = transfer.retries;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .wrap_err(format!("Failed to open download file: {}", path.display()))?;
+
+    let end = segment.end;
+    let mut position = segment.start;
+    let mut attempt = 0;
+    while position < end {
+        let result: Result<()> = async {
+            let response = client
+                .get(url)
+                .header(RANGE, format!("bytes={}-{}", position, end - 1))
+                .send()
+                .await?
+                .error_for_status()?;
+            if response.status() != StatusCode::PARTIAL_CONTENT {
+                return Err(eyre!("Server ignored range request"));
+            }
+
+            file.seek(SeekFrom::Start(position))?;
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.wrap_err("Failed to download chunk")?;
+                transfer.limit.throttle(chunk.len() as u64).await;
+                file.write_all(&chunk)
+                    .wrap_err("Failed to write chunk to download file")?;
+                let len = chunk.len() as u64;
+                health::add_downloaded_bytes(len);
+                position += len;
+                pb.inc(len);
+            }
+
+            if position < end {
+                return Err(eyre!("Connection closed early"));
+            }
+
+            Ok(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => {}
+            Err(e) if attempt < retries => {
+                attempt += 1;
+                pb.println(
+                    format!(
+                        "Segment download interrupted ({}), resuming from byte {} [{}/{}]",
+                        e, position, attempt, retries
+                    )
+                    .yellow()
+                    .to_string(),
+                );
+                tokio::time::sleep(transfer.policy.backoff(attempt)).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Extract the archive `file` into `dest`, showing progress by the compressed bytes read
+async fn progress_extract(file: File, dest: PathBuf) -> Result<()> {
+    let pb = ProgressBar::new(file.metadata()?.len());
+    pb.set_style(ProgressStyle::default_bar()
+                .template("{msg}\n{spinner:.cyan} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?
+                .progress_chars("#>-"));
+    pb.set_message(
+        "Decompressing and extracting snapshot..."
+            .cyan()
+            .to_string(),
+    );
+
+    let reader = pb.wrap_read(file);
+    tokio::task::spawn_blocking(move || extract(reader, &dest)).await??;
+
+    pb.finish_with_message("✓ Decompressed and extracted snapshot.".green().to_string());
+
+    Ok(())
+}
+
+/// Decompress snapshot (lz4, or zstd for exported ones) and extract using tar
+fn extract(reader: impl Read + 'static, dest: &Path) -> Result<()> {
+    let decoder = backup::decoder(BufReader::new(reader))?;
+    let mut archive = tar::Archive::new(decoder);
+    archive.unpack(dest).wrap_err("Failed to extract snapshot")
+}
+
+/// Blocking reader over chunks received from the download task
+struct ChannelReader {
+    rx: mpsc::Receiver<Bytes>,
+    chunk: Bytes,
+}
+
+impl ChannelReader {
+    fn new(rx: mpsc::Receiver<Bytes>) -> Self {
+        ChannelReader {
+            rx,
+            chunk: Bytes::new(),
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => self.chunk = chunk,
+                // download finished (or failed), report end of stream
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.chunk.len());
+        buf[..len].copy_from_slice(&self.chunk.split_to(len));
+        Ok(len)
+    }
+}
+
+/// Local cache of snapshot archives
+pub fn cache_dir() -> PathBuf {
+    platform::home_dir().join(".cache/osmoinplace/snapshots")
+}
+
+/// Cached archive of the snapshot at `height`
+pub fn cached_path(height: u64) -> PathBuf {
+    cache_dir().join(format!("{}.tar.lz4", height))
+}
+
+/// Digest of a cached archive recorded when it was verified on download
+fn checksum_path(archive: &Path) -> PathBuf {
+    archive.with_extension("lz4.sha256")
+}
+
+/// Cached archive of the snapshot at `height` if there is one that can be used, which requires a
+/// recorded digest to verify it against unless `skip_checksum`
+pub fn cached(height: u64, skip_checksum: bool) -> Option<PathBuf> {
+    let path = cached_path(height);
+    (path.exists() && (skip_checksum || checksum_path(&path).exists())).then_some(path)
+}
+
+fn add_to_cache(archive: &Path, height: u64, checksum: &Option<String>) -> Result<()> {
+    let path = cached_path(height);
+    std::fs::create_dir_all(cache_dir()).wrap_err(format!(
+        "Failed to create snapshot cache directory: {}",
+        cache_dir().display()
+    ))?;
+    std::fs::rename(archive, &path).wrap_err(format!(
+        "Failed to move downloaded snapshot into cache: {}",
+        path.display()
+    ))?;
+
+    if let Some(checksum) = checksum {
+        std::fs::write(checksum_path(&path), checksum).wrap_err(format!(
+            "Failed to write snapshot checksum: {}",
+            checksum_path(&path).display()
+        ))?;
+    }
+
+    println!(
+        "{}",
+        format!("✓ Cached snapshot at {}.", path.display()).green()
+    );
+
+    Ok(())
+}
+
+/// Extract the cached archive at `path` into `dest`, verifying it against the digest recorded
+/// when it was cached unless `skip_checksum`
+pub async fn extract_cached(
+    path: &Path,
+    dest: &Path,
+    skip_checksum: bool,
+    session: &Session,
+) -> Result<()> {
+    if !skip_checksum {
+        // `snapshot create` writes the file name after the digest
+        let expected = std::fs::read_to_string(checksum_path(path))
+            .wrap_err("Failed to read cached snapshot checksum")?
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        spinner! {
+            session.events,
+            "Verifying cached snapshot...",
+            "✓ Verified cached snapshot.",
+            {
+                let archive = path.to_path_buf();
+                let hasher = tokio::task::spawn_blocking(move || -> Result<Sha256> {
+                    let mut hasher = Sha256::new();
+                    std::io::copy(&mut File::open(&archive)?, &mut hasher)?;
+                    Ok(hasher)
+                })
+                .await??;
+
+                verify_checksum(Some(hasher), &Some(expected)).wrap_err(format!(
+                    "Cached snapshot {} is corrupted, remove it with `osmoinplace cache clean`",
+                    path.display()
+                ))?
+            }
+        };
+    }
+
+    extract_file(path, dest).await
+}
+
+/// Print the archives in the snapshot cache
+pub fn cache_list() -> Result<()> {
+    let entries = cache_entries()?;
+    if entries.is_empty() {
+        println!("No cached snapshots in {}", cache_dir().display());
+        return Ok(());
+    }
+
+    for path in entries {
+        let size = path.metadata()?.len();
+        let verified = match checksum_path(&path).exists() {
+            true => "verified",
+            false => "unverified",
+        };
+        println!(
+            "{}  {:.1} GB  {}",
+            path.display(),
+            size as f64 / 1e9,
+            verified
+        );
+    }
+
+    Ok(())
+}
+
+/// Remove the cached archives at `heights`, or all of them if none are given
+pub fn cache_clean(heights: &[u64], safe_mode: &SafeMode) -> Result<()> {
+    let paths = match heights.is_empty() {
+        true => cache_entries()?,
+        false => heights.iter().map(|height| cached_path(*height)).collect(),
+    };
+    if let Some(missing) = paths.iter().find(|path| !path.exists()) {
+        return Err(eyre!("No cached snapshot at {}", missing.display()));
+    }
+    safe_mode.check_cache_delete(&paths)?;
+
+    for path in paths {
+        std::fs::remove_file(&path).wrap_err(format!(
+            "Failed to remove cached snapshot: {}",
+            path.display()
+        ))?;
+        let checksum = checksum_path(&path);
+        if checksum.exists() {
+            std::fs::remove_file(&checksum).wrap_err(format!(
+                "Failed to remove cached snapshot checksum: {}",
+                checksum.display()
+            ))?;
+        }
+
+        println!("{}", format!("✓ Removed {}.", path.display()).green());
+    }
+
+    Ok(())
+}
+
+fn cache_entries() -> Result<Vec<PathBuf>> {
+    if !cache_dir().exists() {
+        return Ok(vec![]);
+    }
+
+    let mut entries = std::fs::read_dir(cache_dir())?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|path| path.to_string_lossy().ends_with(".tar.lz4"))
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    Ok(entries)
+}
+
+/// Compression of a created snapshot archive
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `.tar.lz4`, like the published snapshots
+    Lz4,
+    /// `.tar.zst`, smaller but slower to create
+    Zstd,
+}
+
+/// Hashes everything written through it
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Pack `data/` and `wasm/` (or the whole home if `full_home`) of `osmosis_home` into a compressed
+/// tar at `output`, in the same layout as the published snapshots so it can be extracted over a
+/// freshly initialized home, and write its digest to `<output>.sha256`
+pub async fn create(
+    osmosis_home: &Path,
+    output: &Path,
+    format: Format,
+    full_home: bool,
+    session: &Session,
+) -> Result<()> {
+    // the databases are only consistent once the node has released them
+    let rpc = session.endpoints.rpc_url();
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()?;
+    if readiness::status_of(&client, &rpc).await.is_ok() {
+        return Err(eyre!(
+            "A node is running on {}, stop it before creating a snapshot",
+            rpc
+        ));
+    }
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)
+            .wrap_err(format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    let dirs = match full_home {
+        true => vec![PathBuf::new()],
+        false => ["data", "wasm"].map(PathBuf::from).to_vec(),
+    };
+    let mut entries = vec![];
+    for dir in dirs.iter().filter(|dir| osmosis_home.join(dir).exists()) {
+        tree(osmosis_home, dir, &mut entries)?;
+    }
+
+    let mut total = 0;
+    for entry in &entries {
+        let metadata = osmosis_home.join(entry).metadata()?;
+        if metadata.is_file() {
+            total += metadata.len();
+        }
+    }
+
+    let pb = ProgressBar::new(total);
+    pb.set_style(ProgressStyle::default_bar()
+                .template("{msg}\n{spinner:.cyan} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?
+                .progress_chars("#>-"));
+    pb.set_message(
+        format!("Creating snapshot {}...", output.display())
+            .cyan()
+            .to_string(),
+    );
+
+    let osmosis_home = osmosis_home.to_path_buf();
+    let output_path = output.to_path_buf();
+    let progress = pb.clone();
+    let digest = tokio::task::spawn_blocking(move || -> Result<String> {
+        let file = File::create(&output_path).wrap_err(format!(
+            "Failed to create snapshot file: {}",
+            output_path.display()
+        ))?;
+        let mut writer = HashingWriter {
+            inner: file,
+            hasher: Sha256::new(),
+        };
+
+        match format {
+            Format::Lz4 => {
+                let encoder = lz4::EncoderBuilder::new()
+                    .build(&mut writer)
+                    .wrap_err("Failed to create lz4 encoder")?;
+                let (_, result) =
+                    append_entries(encoder, &osmosis_home, &entries, &progress)?.finish();
+                result.wrap_err("Failed to finish lz4 stream")?;
+            }
+            Format::Zstd => {
+                let encoder =
+                    zstd::Encoder::new(&mut writer, 3).wrap_err("Failed to create zstd encoder")?;
+                append_entries(encoder, &osmosis_home, &entries, &progress)?
+                    .finish()
+                    .wrap_err("Failed to finish zstd stream")?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(format!("{:x}", writer.hasher.finalize()))
+    })
+    .await??;
+
+    let checksum_path = PathBuf::from(format!("{}.sha256", output.display()));
+    let file_name = output.file_name().unwrap_or_default().to_string_lossy();
+    std::fs::write(&checksum_path, format!("{}  {}\n", digest, file_name)).wrap_err(format!(
+        "Failed to write snapshot checksum: {}",
+        checksum_path.display()
+    ))?;
+
+    pb.finish_with_message(
+        format!("✓ Created snapshot {}.", output.display())
+            .green()
+            .to_string(),
+    );
+    println!("{} {}", "sha256:".cyan(), digest);
+
+    Ok(())
+}
+
+/// Paths of `dir` in `root` and everything below it, each directory before its contents
+fn tree(root: &Path, dir: &Path, entries: &mut Vec<PathBuf>) -> Result<()> {
+    if !dir.as_os_str().is_empty() {
+        entries.push(dir.to_path_buf());
+    }
+    for entry in std::fs::read_dir(root.join(dir))
+        .wrap_err(format!("Failed to read {}", root.join(dir).display()))?
+    {
+        let entry = entry?;
+        let path = dir.join(entry.file_name());
+        match entry.file_type()?.is_dir() {
+            true => tree(root, &path, entries)?,
+            false => entries.push(path),
+        }
+    }
+    Ok(())
+}
+
+/// Tar `entries` of `osmosis_home` into `writer`, advancing `pb` by the bytes read
+fn append_entries<W: Write>(
+    writer: W,
+    osmosis_home: &Path,
+    entries: &[PathBuf],
+    pb: &ProgressBar,
+) -> Result<W> {
+    let mut archive = tar::Builder::new(writer);
+    for entry in entries {
+        let path = osmosis_home.join(entry);
+        if path.is_dir() {
+            archive
+                .append_dir(entry, &path)
+                .wrap_err(format!("Failed to add {} to snapshot", path.display()))?;
+            continue;
+        }
+
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(&path.metadata()?);
+        let reader = File::open(&path).wrap_err(format!("Failed to open {}", path.display()))?;
+        archive
+            .append_data(&mut header, entry, pb.wrap_read(reader))
+            .wrap_err(format!("Failed to add {} to snapshot", path.display()))?;
+    }
+    archive
+        .into_inner()
+        .wrap_err("Failed to finish snapshot archive")
+}
+
+/// Snapshot chosen for download, recorded in the home dir once extracted
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotInfo {
+    pub url: String,
+    pub height: Option<u64>,
+    /// Snapshot time as `YYYYMMDDHHMM`
+    pub date: Option<String>,
+}
+
+impl SnapshotInfo {
+    pub fn from_url(url: &str) -> SnapshotInfo {
+        let (date, height) = url
+            .rsplit('/')
+            .next()
+            .and_then(parse_file_name)
+            .map_or((None, None), |(date, height)| (Some(date), Some(height)));
+
+        SnapshotInfo {
+            url: url.to_string(),
+            height,
+            date,
+        }
+    }
+}
+
+/// Parse `osmosis-snapshot-<YYYYMMDDHHMM>-<height>.tar.lz4` into its date and height
+fn parse_file_name(name: &str) -> Option<(String, u64)> {
+    let rest = name
+        .strip_prefix("osmosis-snapshot-")?
+        .strip_suffix(".tar.lz4")?;
+    let (date, height) = rest.split_once('-')?;
+
+    if date.len() != 12 || !date.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some((date.to_string(), height.parse().ok()?))
+}
+
+/// Pick a snapshot from the listing of the directory the latest snapshot lives in: the most recent
+/// one at or below `height`, or the most recent one taken on `date` (`YYYY-MM-DD`)
+pub async fn resolve_pinned(
+    latest_url: &str,
+    height: Option<u64>,
+    date: &Option<String>,
+    policy: &Policy,
+) -> Result<SnapshotInfo> {
+    let (base, _) = latest_url
+        .rsplit_once('/')
+        .ok_or_else(|| eyre!("Unexpected snapshot url: {}", latest_url))?;
+
+    let client = &policy.client()?;
+    let listing = policy
+        .network(&format!("Listing snapshots at {}/", base), || async move {
+            Ok(client
+                .get(format!("{}/", base))
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?)
+        })
+        .await?;
+
+    // pull file names out of the hrefs of the directory listing
+    let mut candidates = listing
+        .split("href=\"")
+        .skip(1)
+        .filter_map(|s| s.split('"').next())
+        .filter_map(|href| {
+            let name = href.rsplit('/').next()?;
+            let (snapshot_date, snapshot_height) = parse_file_name(name)?;
+            Some(SnapshotInfo {
+                url: format!("{}/{}", base, name),
+                height: Some(snapshot_height),
+                date: Some(snapshot_date),
+            })
+        })
+        .filter(|info| match (height, date) {
+            (Some(height), _) => info.height <= Some(height),
+            (None, Some(date)) => info
+                .date
+                .as_deref()
+                .is_some_and(|d| d.starts_with(&date.replace('-', ""))),
+            (None, None) => true,
+        })
+        .collect::<Vec<_>>();
+
+    candidates.sort_by_key(|info| info.height);
+    candidates.pop().ok_or_else(|| match (height, date) {
+        (Some(height), _) => eyre!("No snapshot at or below height {} at {}/", height, base),
+        (None, Some(date)) => eyre!("No snapshot taken on {} at {}/", date, base),
+        (None, None) => eyre!("No snapshots found at {}/", base),
+    })
+}
+
+/// Record which snapshot the home was created from
+pub fn write_metadata(osmosis_home: &Path, info: &SnapshotInfo) -> Result<()> {
+    let path = osmosis_home.join("snapshot.json");
+    std::fs::write(&path, serde_json::to_string_pretty(info)?).wrap_err(format!(
+        "Failed to write snapshot metadata: {}",
+        path.display()
+    ))
+}
+
+/// Snapshot the home was created from, if recorded
+pub fn read_metadata(osmosis_home: &Path) -> Option<SnapshotInfo> {
+    let content = std::fs::read_to_string(osmosis_home.join("snapshot.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rates_with_units() {
+        assert_eq!(parse_rate("512").unwrap(), 512);
+        assert_eq!(parse_rate("10K").unwrap(), 10 << 10);
+        assert_eq!(parse_rate("50mb").unwrap(), 50 << 20);
+        assert_eq!(parse_rate(" 2G ").unwrap(), 2 << 30);
+    }
+
+    #[test]
+    fn rejects_invalid_rates() {
+        for rate in ["", "0", "0M", "fast", "1.5M", "-1K", "99999999999999G"] {
+            assert!(parse_rate(rate).is_err(), "{:?} was accepted", rate);
+        }
+    }
+
+    #[test]
+    fn parses_snapshot_file_names() {
+        assert_eq!(
+            parse_file_name("osmosis-snapshot-202407150000-17500000.tar.lz4"),
+            Some(("202407150000".to_string(), 17500000))
+        );
+        assert_eq!(
+            parse_file_name("osmosis-snapshot-2024-17500000.tar.lz4"),
+            None
+        );
+        assert_eq!(
+            parse_file_name("osmosis-snapshot-202407150000-17500000.tar"),
+            None
+        );
+        assert_eq!(parse_file_name("latest"), None);
+    }
+}
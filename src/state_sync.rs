@@ -0,0 +1,152 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use colored::Colorize;
+
+use crate::{
+    init_home,
+    node_config::{self, ConfigFile},
+    policy::Policy,
+    safe_mode::SafeMode,
+    session::Session,
+    spinner,
+};
+
+#[derive(Args, Debug, Clone)]
+pub struct StateSyncArgs {
+    /// Trusted mainnet RPC servers to state sync from, at least two are required by CometBFT
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_values_t = [
+            "https://rpc.osmosis.zone:443".to_string(),
+            "https://osmosis-rpc.polkachu.com:443".to_string(),
+        ]
+    )]
+    pub state_sync_rpc: Vec<String>,
+
+    /// How many blocks below the latest height to trust
+    #[arg(long, default_value_t = 2000)]
+    pub state_sync_trust_offset: u64,
+
+    /// Seeds to discover peers serving state sync snapshots from
+    #[arg(
+        long,
+        default_value = "ade4d8bc8cbe014af6ebdf3cb7b1e9ad36f412c0@seeds.polkachu.com:12556"
+    )]
+    pub state_sync_seeds: String,
+}
+
+/// Initialize `osmosis_home` and configure it to bootstrap with CometBFT state sync on its next
+/// start instead of from a downloaded snapshot
+#[allow(clippy::too_many_arguments)]
+pub async fn bootstrap(
+    osmosisd: &PathBuf,
+    osmosis_home: &PathBuf,
+    args: &StateSyncArgs,
+    genesis_url: &[String],
+    genesis_overrides: &Option<PathBuf>,
+    safe_mode: &SafeMode,
+    policy: &Policy,
+    session: &Session,
+) -> Result<()> {
+    session.phase("state-sync");
+
+    if args.state_sync_rpc.len() < 2 {
+        return Err(eyre!(
+            "State sync needs at least two --state-sync-rpc servers"
+        ));
+    }
+
+    init_home(
+        osmosisd,
+        osmosis_home,
+        genesis_url,
+        genesis_overrides,
+        safe_mode,
+        policy,
+        session,
+    )
+    .await?;
+
+    let client = &policy.client()?;
+    let (trust_height, trust_hash) = spinner! {
+        session.events,
+        "Fetching state sync trust height and hash...",
+        "✓ Fetched state sync trust height and hash.",
+        policy
+            .mirrors("Fetching trust height and hash", &args.state_sync_rpc, |rpc| async move {
+                trusted_block(client, &rpc, args.state_sync_trust_offset).await
+            })
+            .await?
+    };
+    println!(
+        "{} {} ({})",
+        "Trust height:".cyan(),
+        trust_height,
+        trust_hash
+    );
+
+    spinner! {
+        session.events,
+        "Configuring state sync...",
+        "✓ Configured state sync.",
+        {
+            node_config::set(osmosis_home, ConfigFile::Config, "statesync.enable", true)?;
+            node_config::set(
+                osmosis_home,
+                ConfigFile::Config,
+                "statesync.rpc_servers",
+                args.state_sync_rpc.join(","),
+            )?;
+            node_config::set(
+                osmosis_home,
+                ConfigFile::Config,
+                "statesync.trust_height",
+                trust_height as i64,
+            )?;
+            node_config::set(osmosis_home, ConfigFile::Config, "statesync.trust_hash", trust_hash)?;
+            node_config::set(
+                osmosis_home,
+                ConfigFile::Config,
+                "p2p.seeds",
+                args.state_sync_seeds.as_str(),
+            )?;
+        }
+    };
+
+    Ok(())
+}
+
+/// Height `offset` blocks below the latest one on `rpc` and its hash
+async fn trusted_block(client: &reqwest::Client, rpc: &str, offset: u64) -> Result<(u64, String)> {
+    let rpc = rpc.trim_end_matches('/');
+
+    let latest: serde_json::Value = client
+        .get(format!("{}/block", rpc))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let latest_height = latest["result"]["block"]["header"]["height"]
+        .as_str()
+        .ok_or_else(|| eyre!("Missing height in latest block from {}", rpc))?
+        .parse::<u64>()
+        .wrap_err("Failed to parse latest block height")?;
+
+    let trust_height = latest_height.saturating_sub(offset).max(1);
+    let trusted: serde_json::Value = client
+        .get(format!("{}/block?height={}", rpc, trust_height))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let trust_hash = trusted["result"]["block_id"]["hash"]
+        .as_str()
+        .ok_or_else(|| eyre!("Missing hash of block {} from {}", trust_height, rpc))?;
+
+    Ok((trust_height, trust_hash.to_string()))
+}
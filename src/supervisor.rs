@@ -0,0 +1,358 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    process::{ExitStatus, Stdio},
+    time::Duration,
+};
+
+use clap::{Args, ValueEnum};
+use color_eyre::eyre::{Context, Result};
+use colored::Colorize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::{Child, Command},
+    sync::mpsc,
+    task::JoinSet,
+    time::{Instant, Interval},
+};
+
+use crate::{
+    diagnostics,
+    endpoints::Endpoints,
+    health, node_log,
+    output::Events,
+    platform, readiness,
+    session::Session,
+    shutdown::{self, Signal, Stop},
+};
+
+const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// When to restart osmosisd after it exits
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Restart {
+    Never,
+    /// After a non-zero exit or a consensus failure
+    OnFailure,
+    /// Whenever it exits, unless osmoinplace is stopped
+    Always,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RestartArgs {
+    /// Restart osmosisd when it crashes or exits, for long running testnets
+    #[arg(long, value_enum, default_value_t = Restart::Never)]
+    pub restart: Restart,
+
+    /// Give up after restarting this many times without the node becoming ready in between
+    #[arg(long, default_value_t = 5)]
+    pub restart_max_retries: u32,
+
+    /// Delay in seconds before the first restart, doubled on every further one
+    #[arg(long, default_value_t = 5)]
+    pub restart_backoff_secs: u64,
+}
+
+impl Default for RestartArgs {
+    fn default() -> Self {
+        RestartArgs {
+            restart: Restart::Never,
+            restart_max_retries: 5,
+            restart_backoff_secs: 5,
+        }
+    }
+}
+
+impl RestartArgs {
+    /// Whether to restart a node that exited, `crashed` or not
+    pub fn applies(&self, crashed: bool) -> bool {
+        match self.restart {
+            Restart::Never => false,
+            Restart::OnFailure => crashed,
+            Restart::Always => true,
+        }
+    }
+
+    /// Delay before restart `attempt` (starting at 1)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        Duration::from_secs(self.restart_backoff_secs * 2u64.pow(attempt.saturating_sub(1).min(6)))
+    }
+}
+
+/// Latest block height the node reported and when it last went up
+struct Stall {
+    timeout: Duration,
+    height: u64,
+    progressed: Instant,
+}
+
+/// What happened to a supervised node, in the order it happened
+pub enum Event {
+    /// Line the node wrote to stdout or stderr, already printed unless `quiet`
+    Line(String),
+    /// The node logged the ready pattern or its RPC reported a new block, sent once
+    Ready,
+    /// The node logged the failure pattern, e.g. when halting for an upgrade
+    Failure(String),
+    /// The node ran for the whole timeout it was spawned with
+    TimedOut(Duration),
+    /// The node didn't commit a new block for the stall timeout
+    Stalled(Duration),
+    /// SIGINT or SIGTERM was received, or the session asked to stop, the node should be `stop`ped
+    Interrupted(Signal),
+    /// The node exited and closed its output
+    Exited(ExitStatus),
+    /// A hook spawned with `spawn_hook` finished
+    HookFinished(Result<()>),
+}
+
+/// osmosisd process supervised without blocking a thread, reacting to whichever of its output,
+/// its readiness, a timeout or Ctrl-C comes first
+pub struct Supervisor {
+    child: Child,
+    lines: mpsc::UnboundedReceiver<std::io::Result<String>>,
+    probe: Pin<Box<dyn Future<Output = ()> + Send>>,
+    log_patterns: readiness::LogPatterns,
+    print_lines: bool,
+    recent_lines: VecDeque<String>,
+    ready: bool,
+    timeout: Option<(Instant, Duration)>,
+    pending: VecDeque<Event>,
+    stall: Option<Stall>,
+    stall_check: Interval,
+    hooks: JoinSet<Result<()>>,
+    endpoints: Endpoints,
+    events: Events,
+    stop: Stop,
+    _supervised: shutdown::Supervised,
+}
+
+impl Supervisor {
+    /// Spawn `cmd` with its stdout and stderr piped, with `caught_up` the node is only ready once
+    /// it is no longer catching up with peers. Readiness is probed on the endpoints of `session`
+    /// and the node's output reported to its events.
+    pub fn spawn(
+        mut cmd: std::process::Command,
+        log_patterns: &readiness::LogPatterns,
+        caught_up: bool,
+        timeout: Option<Duration>,
+        session: &Session,
+    ) -> Result<Supervisor> {
+        // signals are forwarded by `stop`, keep Ctrl-C in the terminal from reaching the node
+        platform::own_process_group(&mut cmd);
+        let mut cmd = Command::from(cmd);
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .wrap_err(format!("Failed to start {:?}", cmd.as_std().get_program()))?;
+        health::set_node_running(true);
+
+        // osmosisd logs much of its output to stderr, so both streams are interleaved
+        let (sender, lines) = mpsc::unbounded_channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward(stdout, sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward(stderr, sender);
+        }
+
+        let endpoints = session.endpoints.clone();
+        Ok(Supervisor {
+            child,
+            lines,
+            probe: Box::pin(async move { readiness::probe(&endpoints, caught_up).await }),
+            log_patterns: log_patterns.clone(),
+            print_lines: true,
+            recent_lines: VecDeque::new(),
+            ready: false,
+            timeout: timeout.map(|timeout| (Instant::now() + timeout, timeout)),
+            pending: VecDeque::new(),
+            stall: None,
+            stall_check: tokio::time::interval(STALL_CHECK_INTERVAL),
+            hooks: JoinSet::new(),
+            endpoints: session.endpoints.clone(),
+            events: session.events.clone(),
+            stop: session.stop.clone(),
+            _supervised: shutdown::supervise(),
+        })
+    }
+
+    /// Don't print the node's output, e.g. while showing a progress bar instead
+    pub fn quiet(mut self, quiet: bool) -> Supervisor {
+        self.print_lines = !quiet;
+        self
+    }
+
+    /// Send `Stalled` when the node's RPC doesn't report a new block for `timeout`
+    pub fn detect_stalls(mut self, timeout: Option<Duration>) -> Supervisor {
+        self.stall = timeout.map(|timeout| Stall {
+            timeout,
+            height: 0,
+            progressed: Instant::now(),
+        });
+        self
+    }
+
+    /// Run blocking hook `f` on another thread while the node's output keeps being processed, its
+    /// result is sent as `HookFinished`
+    pub fn spawn_hook(&mut self, f: impl FnOnce() -> Result<()> + Send + 'static) {
+        self.hooks.spawn_blocking(f);
+    }
+
+    /// Wait for the hooks still running, failing with the first one that failed
+    pub async fn finish_hooks(&mut self) -> Result<()> {
+        while let Some(result) = self.hooks.join_next().await {
+            result.wrap_err("Hook panicked")??;
+        }
+        Ok(())
+    }
+
+    /// Wait for the next event of the node
+    pub async fn next(&mut self) -> Result<Event> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(event);
+        }
+
+        loop {
+            if let Some(event) = self.select().await? {
+                return Ok(event);
+            }
+        }
+    }
+
+    async fn select(&mut self) -> Result<Option<Event>> {
+        let deadline = self.timeout.map(|(deadline, _)| deadline);
+        let event = tokio::select! {
+            line = self.lines.recv() => match line {
+                Some(line) => {
+                    let line = line?;
+                    if self.print_lines {
+                        node_log::echo(&line, &self.events);
+                    }
+                    node_log::write(&line);
+                    if self.recent_lines.len() == diagnostics::RECENT_LINES {
+                        self.recent_lines.pop_front();
+                    }
+                    self.recent_lines.push_back(line.clone());
+                    if !self.ready && self.log_patterns.is_ready(&line) {
+                        self.ready = true;
+                        self.pending.push_back(Event::Ready);
+                    }
+                    if self.log_patterns.is_failure(&line) {
+                        self.pending.push_back(Event::Failure(line.clone()));
+                    }
+                    Ok(Event::Line(line))
+                }
+                None => Ok(Event::Exited(self.wait().await?)),
+            },
+            _ = &mut self.probe, if !self.ready => {
+                self.ready = true;
+                Ok(Event::Ready)
+            }
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                let (_, timeout) = self.timeout.take().unwrap();
+                Ok(Event::TimedOut(timeout))
+            }
+            _ = self.stall_check.tick(), if self.stall.is_some() => return self.check_stall().await,
+            Some(result) = self.hooks.join_next(), if !self.hooks.is_empty() => {
+                Ok(Event::HookFinished(result.wrap_err("Hook panicked")?))
+            }
+            signal = shutdown::signaled() => Ok(Event::Interrupted(signal)),
+            signal = self.stop.requested() => Ok(Event::Interrupted(signal)),
+        };
+        event.map(Some)
+    }
+
+    async fn check_stall(&mut self) -> Result<Option<Event>> {
+        let height = readiness::status(&self.endpoints)
+            .await
+            .map(|status| status.latest_block_height)
+            .unwrap_or_default();
+        let Some(stall) = self.stall.as_mut() else {
+            return Ok(None);
+        };
+
+        if height > stall.height {
+            stall.height = height;
+            stall.progressed = Instant::now();
+        }
+        if stall.progressed.elapsed() < stall.timeout {
+            return Ok(None);
+        }
+
+        let timeout = stall.timeout;
+        self.stall = None;
+        Ok(Some(Event::Stalled(timeout)))
+    }
+
+    /// Last lines the node logged, for diagnostics
+    pub fn recent_lines(&self) -> Vec<String> {
+        self.recent_lines.iter().cloned().collect()
+    }
+
+    /// Kill the node and wait for it to exit
+    pub async fn kill(&mut self) -> Result<()> {
+        self.child.kill().await?;
+        health::set_node_running(false);
+        Ok(())
+    }
+
+    /// Forward `signal` to the node so it closes its databases cleanly, killing it if it doesn't
+    /// exit within `timeout`
+    pub async fn stop(&mut self, signal: Signal, timeout: Option<Duration>) -> Result<()> {
+        let Some(pid) = self.child.id() else {
+            return Ok(());
+        };
+        // pid is our own child, which hasn't been reaped yet
+        platform::signal(pid, signal);
+
+        let exited = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.wait()).await.ok(),
+            None => Some(self.wait().await),
+        };
+        match exited {
+            Some(status) => status.map(|_| ()),
+            None => {
+                println!(
+                    "{}",
+                    format!(
+                        "osmosisd didn't stop within {:?}, killing it",
+                        timeout.unwrap()
+                    )
+                    .yellow()
+                );
+                self.kill().await
+            }
+        }
+    }
+
+    /// Wait for the node to exit on its own
+    pub async fn wait(&mut self) -> Result<ExitStatus> {
+        let status = self.child.wait().await?;
+        health::set_node_running(false);
+        Ok(status)
+    }
+}
+
+fn forward(
+    stream: impl AsyncRead + Unpin + Send + 'static,
+    sender: mpsc::UnboundedSender<std::io::Result<String>>,
+) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stream).lines();
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => Ok(line),
+                Ok(None) => break,
+                Err(e) => Err(e),
+            };
+            let failed = line.is_err();
+            if sender.send(line).is_err() || failed {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use color_eyre::eyre::Result;
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::task::JoinHandle;
+
+use crate::{endpoints::Endpoints, policy::Policy, readiness};
+
+/// Times a stalled sync is restarted with --restart-on-stall before giving up
+pub const MAX_STALL_RESTARTS: u32 = 3;
+
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const NETWORK_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Options bounding how long syncing the node may take, so CI fails fast instead of hanging
+#[derive(Args, Debug, Clone)]
+pub struct SyncArgs {
+    /// Give up syncing after this many seconds, overrides --process-timeout-secs
+    #[arg(long)]
+    pub timeout: Option<u64>,
+
+    /// Consider the sync stalled when the node hasn't committed a new block for this many
+    /// seconds, e.g. when it can't find peers. 0 disables stall detection.
+    #[arg(long, default_value_t = 0)]
+    pub stall_timeout_secs: u64,
+
+    /// Restart the node when the sync stalls instead of failing, up to 3 times
+    #[arg(long)]
+    pub restart_on_stall: bool,
+
+    /// Stop syncing once this block is committed, so every run forks from the same mainnet height
+    #[arg(long)]
+    pub halt_height: Option<u64>,
+
+    /// Mainnet RPC to get the latest height to sync to from, for the progress bar
+    #[arg(long, default_value = "https://rpc.osmosis.zone:443")]
+    pub network_rpc: String,
+
+    /// Print the raw node logs instead of a progress bar
+    #[arg(long)]
+    pub node_logs: bool,
+}
+
+impl SyncArgs {
+    pub fn timeout(&self, policy: &Policy) -> Option<Duration> {
+        match self.timeout {
+            Some(secs) => (secs > 0).then(|| Duration::from_secs(secs)),
+            None => policy.process_timeout(),
+        }
+    }
+
+    pub fn stall_timeout(&self) -> Option<Duration> {
+        (self.stall_timeout_secs > 0).then(|| Duration::from_secs(self.stall_timeout_secs))
+    }
+}
+
+/// Progress bar from the height the node started syncing at to the network's latest height,
+/// updated from both RPCs in the background until dropped
+pub struct Progress {
+    bar: ProgressBar,
+    task: JoinHandle<()>,
+}
+
+impl Progress {
+    /// Track progress up to `halt_height`, or the latest height of `network_rpc` if not set
+    pub fn start(
+        network_rpc: &str,
+        halt_height: Option<u64>,
+        client: reqwest::Client,
+        endpoints: &Endpoints,
+    ) -> Result<Progress> {
+        let bar = ProgressBar::new(0);
+        bar.set_style(ProgressStyle::default_bar()
+                .template("{msg}\n{spinner:.cyan} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} blocks ({per_sec}, {eta})")?
+                .progress_chars("#>-"));
+        bar.set_message("Syncing...".cyan().to_string());
+
+        let (progress, network_rpc) = (bar.clone(), network_rpc.to_string());
+        let endpoints = endpoints.clone();
+        let task = tokio::spawn(async move {
+            let mut start_height = None;
+            let mut network_height = halt_height.unwrap_or(0);
+            let mut network_checked: Option<Instant> = None;
+            loop {
+                let network_due = match network_checked {
+                    Some(checked) => checked.elapsed() >= NETWORK_POLL_INTERVAL,
+                    None => true,
+                };
+                if halt_height.is_none() && network_due {
+                    if let Ok(status) = readiness::status_of(&client, &network_rpc).await {
+                        network_height = status.latest_block_height;
+                        network_checked = Some(Instant::now());
+                    }
+                }
+
+                if let Ok(status) = readiness::status(&endpoints).await {
+                    let height = status.latest_block_height;
+                    let start = *start_height.get_or_insert(height);
+                    let target = network_height.max(height);
+                    progress.set_length(target - start);
+                    progress.set_position(height - start);
+                    progress.set_message(
+                        format!("Syncing block {} of {}...", height, target)
+                            .cyan()
+                            .to_string(),
+                    );
+                }
+
+                tokio::time::sleep(PROGRESS_POLL_INTERVAL).await;
+            }
+        });
+
+        Ok(Progress { bar, task })
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.bar.finish_and_clear();
+    }
+}
@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::{eyre, Result};
+use tokio::task::JoinHandle;
+
+use crate::{
+    hooks::{Hooks, Setup},
+    maintenance::Maintenance,
+    policy::Policy,
+    readiness,
+    session::Session,
+    shutdown::Signal,
+    supervisor::RestartArgs,
+};
+
+/// Fork running in the background of the current tokio runtime, e.g. for `#[tokio::test]`
+/// integration tests. `kill` stops the node cleanly, dropping the handle without it kills the
+/// node outright (SIGKILL on unix), like a crash would.
+///
+/// The node listens on the endpoints of its session, so testnets run side by side when each
+/// session has its own port offset.
+pub struct Testnet {
+    osmosis_home: PathBuf,
+    session: Session,
+    node: Option<JoinHandle<Result<()>>>,
+}
+
+impl Testnet {
+    /// Fork the mainnet state in `osmosis_home` into a testnet run by `operator_address`, like
+    /// `start-in-place-testnet`
+    pub fn in_place(
+        osmosisd: PathBuf,
+        osmosis_home: PathBuf,
+        operator_address: String,
+        session: Session,
+    ) -> Testnet {
+        let (home, node_session) = (osmosis_home.clone(), session.clone());
+        let node = tokio::spawn(async move {
+            crate::start_in_place_testnet(
+                &osmosisd,
+                &home,
+                &None,
+                &None,
+                &[],
+                &operator_address,
+                &Setup::default(),
+                &Hooks::default(),
+                &readiness::LogPatterns::default(),
+                &RestartArgs::default(),
+                &Policy::default(),
+                &Maintenance::default(),
+                &node_session,
+            )
+            .await
+        });
+        Testnet {
+            osmosis_home,
+            session,
+            node: Some(node),
+        }
+    }
+
+    /// Run the testnet already forked in `osmosis_home`, like `start-standalone`
+    pub fn standalone(osmosisd: PathBuf, osmosis_home: PathBuf, session: Session) -> Testnet {
+        let (home, node_session) = (osmosis_home.clone(), session.clone());
+        let node = tokio::spawn(async move {
+            crate::start_standalone(
+                &osmosisd,
+                &home,
+                &Setup::default(),
+                &Hooks::default(),
+                &readiness::LogPatterns::default(),
+                &RestartArgs::default(),
+                &Policy::default(),
+                &Maintenance::default(),
+                &node_session,
+            )
+            .await
+        });
+        Testnet {
+            osmosis_home,
+            session,
+            node: Some(node),
+        }
+    }
+
+    /// Wait until the node commits blocks, failing if it stops before that
+    pub async fn wait_ready(&mut self) -> Result<()> {
+        let Some(node) = self.node.as_mut() else {
+            return Err(eyre!("osmosisd is not running"));
+        };
+
+        let result = tokio::select! {
+            _ = readiness::probe(&self.session.endpoints, true) => return Ok(()),
+            result = node => result,
+        };
+        self.node = None;
+        match result {
+            Ok(Ok(())) => Err(eyre!("osmosisd stopped before it was ready")),
+            Ok(Err(e)) => Err(e.wrap_err("osmosisd failed before it was ready")),
+            Err(e) => Err(eyre!("osmosisd task failed: {}", e)),
+        }
+    }
+
+    pub fn rpc_url(&self) -> String {
+        self.session.endpoints.rpc_url()
+    }
+
+    pub fn grpc_url(&self) -> String {
+        format!("http://{}", self.session.endpoints.grpc_addr())
+    }
+
+    pub fn home(&self) -> &PathBuf {
+        &self.osmosis_home
+    }
+
+    /// Stop the node like Ctrl-C does, so it closes its databases cleanly, and wait until it is
+    /// gone. A node that doesn't stop within the shutdown timeout is killed.
+    pub async fn kill(mut self) -> Result<()> {
+        if let Some(node) = self.node.take() {
+            self.session.stop.request(Signal::Terminate);
+            // the run ends with the interruption as its error
+            let _ = node.await;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Testnet {
+    /// Abort the node's task, which drops and so kills its process
+    fn drop(&mut self) {
+        if let Some(node) = self.node.take() {
+            node.abort();
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use serde::Deserialize;
+
+use crate::{accounts, gov, session::Session};
+
+/// Mainnet height each osmosisd major version took over at, oldest first
+const UPGRADE_HEIGHTS: &[(u64, u64)] = &[
+    (16, 10_517_000),
+    (17, 11_126_100),
+    (18, 11_155_350),
+    (19, 11_317_300),
+    (20, 12_028_900),
+    (21, 13_325_950),
+    (22, 13_945_650),
+    (23, 14_830_300),
+    (24, 15_753_500),
+    (25, 17_448_000),
+    (26, 20_954_800),
+    (27, 24_250_100),
+];
+
+/// Upgrades to run one after the other once the triggered one completed
+#[derive(Args, Debug, Clone, Default)]
+pub struct ChainArgs {
+    /// Further upgrade to run once the previous one completed, as `name=binary`, e.g.
+    /// v26=./osmosisd_v26. Repeat to chain more. Each is passed as a software upgrade proposal by
+    /// the operator, whose key has to be in the test keyring.
+    #[arg(long, value_parser = parse_chained_upgrade, requires = "new_osmosisd_bin")]
+    pub then_upgrade: Vec<(String, PathBuf)>,
+}
+
+pub fn parse_chained_upgrade(s: &str) -> Result<(String, PathBuf), String> {
+    let (name, binary) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Expected name=binary, got {}", s))?;
+    Ok((name.to_string(), PathBuf::from(binary)))
+}
+
+/// Upgrade to propose on a running node and halt it for
+#[derive(Debug, Clone)]
+pub struct NextUpgrade {
+    pub name: String,
+    operator: String,
+    gas_prices: String,
+}
+
+impl ChainArgs {
+    /// The chained upgrades with their binaries, proposed by `operator`, an address or key name
+    pub fn resolve(
+        &self,
+        operator: Option<&str>,
+        gas_prices: String,
+    ) -> Vec<(NextUpgrade, PathBuf)> {
+        let operator = operator.unwrap_or(accounts::DEFAULT_OPERATOR_ADDRESS);
+        self.then_upgrade
+            .iter()
+            .map(|(name, binary)| {
+                let next_upgrade = NextUpgrade {
+                    name: name.clone(),
+                    operator: operator.to_string(),
+                    gas_prices: gas_prices.clone(),
+                };
+                (next_upgrade, binary.clone())
+            })
+            .collect()
+    }
+}
+
+impl NextUpgrade {
+    /// Pass the upgrade proposal, so the node halts for it once its height is reached
+    pub async fn propose(
+        &self,
+        osmosisd: &PathBuf,
+        osmosis_home: &PathBuf,
+        session: &Session,
+    ) -> Result<()> {
+        let from = accounts::operator_key(osmosisd, osmosis_home, &self.operator)?;
+        let proposal = gov::Proposal::Upgrade {
+            name: self.name.clone(),
+            height: None,
+        };
+        gov::pass(
+            osmosisd,
+            osmosis_home,
+            &proposal,
+            &from,
+            &self.gas_prices,
+            session,
+        )
+        .await
+        .wrap_err(format!("Failed to pass upgrade {}", self.name))?;
+        Ok(())
+    }
+}
+
+/// Written by the node into `data/upgrade-info.json` when it halts for an upgrade
+#[derive(Deserialize, Debug)]
+pub struct UpgradeInfo {
+    pub name: String,
+    pub height: u64,
+}
+
+fn upgrade_info_path(osmosis_home: &Path) -> PathBuf {
+    osmosis_home.join("data").join("upgrade-info.json")
+}
+
+pub fn pending_upgrade(osmosis_home: &Path) -> Result<Option<UpgradeInfo>> {
+    let path = upgrade_info_path(osmosis_home);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .wrap_err(format!("Failed to read upgrade info: {}", path.display()))?;
+    let info = serde_json::from_str(&content).wrap_err("Failed to parse upgrade info")?;
+
+    Ok(Some(info))
+}
+
+/// Remove the upgrade info once the node committed blocks past the height it halted at, the
+/// node leaves it behind and `resume` would take the applied upgrade for a pending one
+pub fn clear_applied(osmosis_home: &Path, height: u64) -> Result<()> {
+    match pending_upgrade(osmosis_home)? {
+        Some(upgrade) if height > upgrade.height => {
+            let path = upgrade_info_path(osmosis_home);
+            std::fs::remove_file(&path)
+                .wrap_err(format!("Failed to remove upgrade info: {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn binary_version(osmosisd: &PathBuf) -> Result<String> {
+    let output = Command::new(osmosisd)
+        .arg("version")
+        .output()
+        .wrap_err(format!("Failed to get version of {}", osmosisd.display()))?;
+
+    // cosmos sdk has printed the version to either stream depending on release
+    let version = [output.stdout, output.stderr]
+        .iter()
+        .map(|out| String::from_utf8_lossy(out).trim().to_string())
+        .find(|out| !out.is_empty())
+        .ok_or_else(|| eyre!("{} printed no version", osmosisd.display()))?;
+
+    Ok(version)
+}
+
+/// Check that `osmosisd` is a release of the major version named by the upgrade, e.g. `v26`
+pub fn verify_binary_for_upgrade(osmosisd: &PathBuf, upgrade: &UpgradeInfo) -> Result<()> {
+    let version = binary_version(osmosisd)?;
+    let upgrade_major = upgrade.name.trim_start_matches('v').split('.').next();
+    let binary_major = version.trim_start_matches('v').split('.').next();
+
+    if upgrade_major != binary_major {
+        return Err(eyre!(
+            "{} is version {} but the pending upgrade is {}",
+            osmosisd.display(),
+            version,
+            upgrade.name
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check that `osmosisd` is the major version mainnet ran right after `height`, so a mismatched
+/// binary fails now rather than with a confusing panic hours later. Heights before the first known
+/// upgrade and binaries that don't report a release version aren't checked.
+pub fn verify_binary_for_height(osmosisd: &PathBuf, height: u64) -> Result<()> {
+    if required_major(height).is_none() {
+        return Ok(());
+    }
+
+    let version = binary_version(osmosisd)?;
+    let Some((major, range)) = incompatible(&version, height) else {
+        return Ok(());
+    };
+    Err(eyre!(
+        "{} is version {} but the snapshot at height {} needs v{}, which mainnet ran {}. \
+         Pass a v{} binary with --osmosisd-bin (see `osmoinplace bin install`), or \
+         --skip-version-check if the binary is known to work.",
+        osmosisd.display(),
+        version,
+        height,
+        major,
+        range,
+        major
+    ))
+}
+
+/// Major version mainnet ran right after `height`, with the heights it ran from and, unless it's
+/// the last known one, until
+fn required_major(height: u64) -> Option<(u64, u64, Option<u64>)> {
+    // the snapshot's next block is the first one the binary has to run
+    let index = UPGRADE_HEIGHTS
+        .iter()
+        .rposition(|(_, upgrade_height)| *upgrade_height <= height + 1)?;
+    let (major, from) = UPGRADE_HEIGHTS[index];
+    let until = UPGRADE_HEIGHTS.get(index + 1).map(|(_, height)| *height);
+    Some((major, from, until))
+}
+
+/// The major version and the heights mainnet ran it at when `version` can't run the state at
+/// `height`
+fn incompatible(version: &str, height: u64) -> Option<(u64, String)> {
+    let (major, from, until) = required_major(height)?;
+    let binary_major = version
+        .trim_start_matches('v')
+        .split('.')
+        .next()
+        .and_then(|major| major.parse::<u64>().ok())?;
+
+    // later upgrades than the known ones may have happened since
+    let compatible = match until {
+        Some(_) => binary_major == major,
+        None => binary_major >= major,
+    };
+    if compatible {
+        return None;
+    }
+
+    let range = match until {
+        Some(until) => format!("heights {} to {}", from, until - 1),
+        None => format!("from height {}", from),
+    };
+    Some((major, range))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn required(version: &str, height: u64) -> Option<u64> {
+        incompatible(version, height).map(|(major, _)| major)
+    }
+
+    #[test]
+    fn the_block_before_an_upgrade_needs_the_upgraded_version() {
+        // v26 took over at 20_954_800, the snapshot's next block is the first one run
+        assert_eq!(required("25.2.0", 20_954_798), None);
+        assert_eq!(required("25.2.0", 20_954_799), Some(26));
+        assert_eq!(required("26.0.0", 20_954_799), None);
+        assert_eq!(required("26.0.0", 20_954_800), None);
+        assert_eq!(required("26.0.0", 20_954_801), None);
+        assert_eq!(required("26.0.0", 20_954_798), Some(25));
+    }
+
+    #[test]
+    fn reports_the_heights_the_required_version_ran_at() {
+        assert_eq!(
+            incompatible("v24.0.1", 21_000_000),
+            Some((26, "heights 20954800 to 24250099".to_string()))
+        );
+        assert_eq!(
+            incompatible("v26.0.0", 30_000_000),
+            Some((27, "from height 24250100".to_string()))
+        );
+    }
+
+    #[test]
+    fn accepts_newer_versions_above_the_last_known_upgrade() {
+        assert_eq!(required("27.0.0", 24_250_099), None);
+        assert_eq!(required("28.1.0", 30_000_000), None);
+        assert_eq!(required("26.0.0", 30_000_000), Some(27));
+    }
+
+    #[test]
+    fn skips_heights_before_the_first_known_upgrade() {
+        assert_eq!(required_major(10_516_998), None);
+        assert_eq!(required("1.0.0", 10_516_998), None);
+        assert_eq!(
+            required_major(10_516_999),
+            Some((16, 10_517_000, Some(11_126_100)))
+        );
+    }
+
+    #[test]
+    fn skips_binaries_without_a_release_version() {
+        for version in ["", "dev", "main-4f3a2b1", "v", "unknown"] {
+            assert_eq!(required(version, 21_000_000), None, "{}", version);
+        }
+    }
+}
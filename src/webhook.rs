@@ -0,0 +1,104 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use color_eyre::eyre::{eyre, Result};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::{endpoints::Endpoints, policy::Policy, readiness, session::Session};
+
+/// --notify-url lifecycle events are POSTed to
+#[derive(Clone)]
+pub struct Webhook {
+    url: String,
+    client: reqwest::Client,
+    policy: Policy,
+}
+
+/// Point of the node's lifecycle reported to --notify-url
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    Ready,
+    Failure,
+    Exit,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: Event,
+    height: Option<u64>,
+    timestamp: u64,
+    error: Option<&'a str>,
+}
+
+impl Webhook {
+    /// POST lifecycle events to `url`, with the timeouts, retries and proxy of `policy`
+    pub fn new(url: &str, policy: &Policy) -> Result<Webhook> {
+        if policy.offline {
+            return Err(eyre!(
+                "--notify-url needs the network, which is disabled by --offline"
+            ));
+        }
+
+        Ok(Webhook {
+            url: url.to_string(),
+            client: policy.client()?,
+            policy: policy.clone(),
+        })
+    }
+}
+
+/// `send` `event` in the background, so the node's output keeps being processed meanwhile
+pub fn notify(session: &Session, event: Event, error: Option<String>) {
+    let Some(webhook) = session.webhook.clone() else {
+        return;
+    };
+    let endpoints = session.endpoints.clone();
+    tokio::spawn(async move { post(&webhook, &endpoints, event, error.as_deref()).await });
+}
+
+/// POST `event` with the node's current height and `error` as JSON to the --notify-url, if set.
+/// Notifications are best effort, a failing endpoint only warns.
+pub async fn send(session: &Session, event: Event, error: Option<&str>) {
+    if let Some(webhook) = &session.webhook {
+        post(webhook, &session.endpoints, event, error).await;
+    }
+}
+
+async fn post(webhook: &Webhook, endpoints: &Endpoints, event: Event, error: Option<&str>) {
+    let payload = Payload {
+        event,
+        height: readiness::status(endpoints)
+            .await
+            .ok()
+            .map(|status| status.latest_block_height),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default(),
+        error,
+    };
+    let body = match serde_json::to_string(&payload) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+
+    let (client, url, body) = (&webhook.client, &webhook.url, &body);
+    let result = webhook
+        .policy
+        .network(&format!("Notifying {}", url), || async move {
+            client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+        .await;
+
+    if let Err(e) = result {
+        eprintln!("{}", format!("{:?}", e).yellow());
+    }
+}
@@ -0,0 +1,115 @@
+// the harness stops runs with SIGINT
+#![cfg(unix)]
+
+mod common;
+
+use common::Home;
+
+#[test]
+fn fork_becomes_ready_on_the_offset_ports() {
+    let home = Home::init(200);
+    let run = home.spawn(&["start-in-place-testnet"]);
+
+    let ready = run.wait_for("node_ready");
+    assert_eq!(ready["rpc"], format!("http://{}", home.rpc_addr()));
+
+    let client = std::fs::read_to_string(home.path().join("config/client.toml")).unwrap();
+    assert!(client.contains(r#"chain-id = "edgenet""#), "{}", client);
+    assert!(
+        client.contains(&format!(r#"node = "tcp://{}""#, home.rpc_addr())),
+        "{}",
+        client
+    );
+}
+
+#[test]
+fn ready_fork_answers_wait_and_status() {
+    let home = Home::init(210);
+    let run = home.spawn(&["start-in-place-testnet"]);
+    run.wait_for("node_ready");
+
+    let waited = home
+        .osmoinplace(&["wait", "--ready", "--timeout-secs", "20"])
+        .output()
+        .unwrap();
+    assert!(
+        waited.status.success(),
+        "wait exited with {}",
+        waited.status
+    );
+
+    let report = |home: &Home| -> serde_json::Value {
+        let output = home
+            .osmoinplace(&["--output", "json", "status"])
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "status exited with {}",
+            output.status
+        );
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    let before = report(&home);
+    assert_eq!(before["running"], true);
+    assert_eq!(before["chain_id"], "edgenet");
+
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    let after = report(&home);
+    assert!(
+        after["height"].as_u64() > before["height"].as_u64(),
+        "height didn't advance: {} -> {}",
+        before["height"],
+        after["height"]
+    );
+}
+
+#[test]
+fn ctrl_c_shuts_the_fork_down() {
+    let home = Home::init(220);
+    let mut run = home.spawn(&["start-in-place-testnet"]);
+    run.wait_for("node_ready");
+    assert!(home.rpc_up());
+
+    let status = run.interrupt();
+    assert!(!status.success(), "an interrupted run should fail");
+    home.wait_rpc_down();
+}
+
+#[test]
+fn detach_from_the_environment_runs_one_background_copy() {
+    let home = Home::init(230);
+    let started = home
+        .osmoinplace(&["-y", "start-in-place-testnet"])
+        .env("OSMOINPLACE_START_IN_PLACE_TESTNET_DETACH", "true")
+        .output()
+        .unwrap();
+    assert!(
+        started.status.success(),
+        "detaching exited with {}",
+        started.status
+    );
+
+    // a copy that detached again would have left no node behind for wait or stop
+    let waited = home
+        .osmoinplace(&["wait", "--ready", "--timeout-secs", "20"])
+        .output()
+        .unwrap();
+    assert!(
+        waited.status.success(),
+        "wait exited with {}",
+        waited.status
+    );
+
+    let stopped = home.osmoinplace(&["stop"]).output().unwrap();
+    assert!(
+        stopped.status.success(),
+        "stop exited with {}",
+        stopped.status
+    );
+    home.wait_rpc_down();
+
+    let mut daemon_dir = home.path().as_os_str().to_owned();
+    daemon_dir.push(".daemon");
+    std::fs::remove_dir_all(daemon_dir).ok();
+}
@@ -0,0 +1,170 @@
+//! Harness running the osmoinplace executable, built with the `mock` feature, against its mock
+//! osmosisd. Every home gets its own port offset so tests can run side by side.
+#![allow(dead_code)]
+
+use std::{
+    io::{BufRead, BufReader},
+    net::TcpStream,
+    path::Path,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use tempfile::TempDir;
+
+/// Makes the osmoinplace executable act as osmosisd
+pub const MOCK_ENV: &str = "OSMOINPLACE_MOCK_OSMOSISD";
+
+const RPC_PORT: u16 = 26657;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Path of the osmoinplace executable under test
+pub fn bin() -> &'static str {
+    env!("CARGO_BIN_EXE_osmoinplace")
+}
+
+/// Temporary home initialized by the mock like `osmosisd init`
+pub struct Home {
+    dir: TempDir,
+    port_offset: u16,
+}
+
+impl Home {
+    pub fn init(port_offset: u16) -> Home {
+        let dir = tempfile::tempdir().expect("Failed to create home");
+        let status = Command::new(bin())
+            .env(MOCK_ENV, "1")
+            .args(["init", "test", "--home"])
+            .arg(dir.path())
+            .status()
+            .expect("Failed to run mock osmosisd init");
+        assert!(
+            status.success(),
+            "mock osmosisd init exited with {}",
+            status
+        );
+
+        Home { dir, port_offset }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn rpc_addr(&self) -> String {
+        format!("127.0.0.1:{}", RPC_PORT + self.port_offset)
+    }
+
+    /// osmoinplace with `args` against this home, using the mock as osmosisd
+    pub fn osmoinplace(&self, args: &[&str]) -> Command {
+        let mut cmd = Command::new(bin());
+        cmd.args(["--osmosisd-bin", "mock", "--home-dir"])
+            .arg(self.path())
+            .args(["--port-offset", &self.port_offset.to_string()])
+            .args(args)
+            .env_remove(MOCK_ENV);
+        cmd
+    }
+
+    /// Start osmoinplace with `args` in the background, reporting `--output json` events
+    pub fn spawn(&self, args: &[&str]) -> Run {
+        let mut cmd = self.osmoinplace(&[&["--output", "json"], args].concat());
+        let mut child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to start osmoinplace");
+
+        let (sender, events) = mpsc::channel();
+        let stdout = child.stdout.take().unwrap();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if let Ok(event) = serde_json::from_str(&line) {
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Run { child, events }
+    }
+
+    /// Whether something answers on the node's RPC port
+    pub fn rpc_up(&self) -> bool {
+        TcpStream::connect(self.rpc_addr()).is_ok()
+    }
+
+    /// Wait until nothing answers on the node's RPC port anymore
+    pub fn wait_rpc_down(&self) {
+        let deadline = Instant::now() + TIMEOUT;
+        while self.rpc_up() {
+            assert!(
+                Instant::now() < deadline,
+                "node still up after {:?}",
+                TIMEOUT
+            );
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+/// osmoinplace running in the background, interrupted when dropped
+pub struct Run {
+    child: Child,
+    events: mpsc::Receiver<Value>,
+}
+
+impl Run {
+    /// Next event named `name`, skipping the others
+    pub fn wait_for(&self, name: &str) -> Value {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let event = self
+                .events
+                .recv_timeout(timeout)
+                .unwrap_or_else(|e| panic!("No {} event: {}", name, e));
+            if event["event"] == name {
+                return event;
+            }
+        }
+    }
+
+    /// Send Ctrl-C and wait for osmoinplace to exit
+    pub fn interrupt(&mut self) -> ExitStatus {
+        // SAFETY: signals our own child, which hasn't been reaped yet
+        unsafe {
+            libc::kill(self.child.id() as libc::pid_t, libc::SIGINT);
+        }
+
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            if let Some(status) = self
+                .child
+                .try_wait()
+                .expect("Failed to wait for osmoinplace")
+            {
+                return status;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "osmoinplace still running {:?} after Ctrl-C",
+                TIMEOUT
+            );
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        // interrupted rather than killed, so it stops the mock node as well
+        if let Ok(None) = self.child.try_wait() {
+            self.interrupt();
+        }
+    }
+}
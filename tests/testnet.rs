@@ -0,0 +1,102 @@
+// the harness stops runs with SIGINT
+#![cfg(unix)]
+
+mod common;
+
+use std::{path::PathBuf, time::Duration};
+
+use common::{bin, Home, MOCK_ENV};
+use osmoinplace::{
+    endpoints::{self, Endpoints},
+    testnet::Testnet,
+    Session,
+};
+use tokio::sync::Mutex;
+
+/// The testnets in this file share a port offset, so they run one at a time
+static ENDPOINTS: Mutex<()> = Mutex::const_new(());
+
+const PORT_OFFSET: u16 = 300;
+const OPERATOR: &str = "osmo1testoperator";
+
+fn in_place(home: &Home) -> Testnet {
+    in_place_on(home, PORT_OFFSET)
+}
+
+fn in_place_on(home: &Home, port_offset: u16) -> Testnet {
+    // the executable acts as osmosisd for the node spawned by the library
+    std::env::set_var(MOCK_ENV, "1");
+    Testnet::in_place(
+        PathBuf::from(bin()),
+        home.path().to_path_buf(),
+        OPERATOR.to_string(),
+        Session::new(Endpoints::new(
+            endpoints::DEFAULT_CHAIN_ID,
+            Some(port_offset),
+        )),
+    )
+}
+
+#[tokio::test]
+async fn in_place_becomes_ready() {
+    let _endpoints = ENDPOINTS.lock().await;
+    let home = Home::init(PORT_OFFSET);
+    let mut testnet = in_place(&home);
+
+    testnet.wait_ready().await.unwrap();
+    assert_eq!(testnet.rpc_url(), format!("http://{}", home.rpc_addr()));
+    assert!(home.rpc_up());
+    testnet.kill().await.unwrap();
+}
+
+#[tokio::test]
+async fn kill_stops_the_node() {
+    let _endpoints = ENDPOINTS.lock().await;
+    let home = Home::init(PORT_OFFSET);
+    let mut testnet = in_place(&home);
+    testnet.wait_ready().await.unwrap();
+
+    testnet.kill().await.unwrap();
+    home.wait_rpc_down();
+}
+
+#[tokio::test]
+async fn drop_stops_the_node() {
+    let _endpoints = ENDPOINTS.lock().await;
+    let home = Home::init(PORT_OFFSET);
+    let mut testnet = in_place(&home);
+    testnet.wait_ready().await.unwrap();
+
+    drop(testnet);
+    // the node is killed once the aborted task is dropped by the runtime
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while home.rpc_up() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("node still up after the testnet was dropped");
+}
+
+#[tokio::test]
+async fn testnets_run_side_by_side_on_their_own_ports() {
+    let (first_home, second_home) = (Home::init(310), Home::init(320));
+    let mut first = in_place_on(&first_home, 310);
+    let mut second = in_place_on(&second_home, 320);
+
+    let (first_ready, second_ready) = tokio::join!(first.wait_ready(), second.wait_ready());
+    first_ready.unwrap();
+    second_ready.unwrap();
+    assert_eq!(first.rpc_url(), format!("http://{}", first_home.rpc_addr()));
+    assert_eq!(
+        second.rpc_url(),
+        format!("http://{}", second_home.rpc_addr())
+    );
+    assert_eq!(first.grpc_url(), "http://127.0.0.1:9400");
+    assert_eq!(second.grpc_url(), "http://127.0.0.1:9410");
+
+    first.kill().await.unwrap();
+    first_home.wait_rpc_down();
+    assert!(second_home.rpc_up());
+    second.kill().await.unwrap();
+}